@@ -0,0 +1,42 @@
+#!/usr/bin/env python3
+"""Speaker diarization for the processor.
+
+Usage: diarize.py AUDIO.wav [--num_speakers N]
+
+Prints {"turns": [{"start": s, "end": s, "speaker": "SPEAKER_00"}, ...]} on
+stdout. Needs pyannote.audio and a Hugging Face token (HF_TOKEN) that has
+accepted the pyannote/speaker-diarization-3.1 model terms.
+"""
+import argparse
+import json
+import os
+import sys
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("audio")
+    parser.add_argument("--num_speakers", type=int)
+    args = parser.parse_args()
+
+    token = os.environ.get("HF_TOKEN")
+    if not token:
+        print("HF_TOKEN is not set", file=sys.stderr)
+        return 1
+
+    from pyannote.audio import Pipeline
+
+    pipeline = Pipeline.from_pretrained("pyannote/speaker-diarization-3.1", use_auth_token=token)
+    options = {"num_speakers": args.num_speakers} if args.num_speakers else {}
+    diarization = pipeline(args.audio, **options)
+
+    turns = [
+        {"start": round(turn.start, 3), "end": round(turn.end, 3), "speaker": speaker}
+        for turn, _, speaker in diarization.itertracks(yield_label=True)
+    ]
+    json.dump({"turns": turns}, sys.stdout)
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
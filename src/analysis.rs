@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::ProcessorError;
+
+/// Sample rate audio is decoded at for waveforms; plenty for a visual
+/// envelope and small enough to hold an hour of it in memory
+pub const WAVEFORM_SAMPLE_RATE: u32 = 8000;
+
+/// Amplitude envelope of a recording for drawing in an editor, one value per
+/// bucket of `1 / samples_per_second` seconds
+#[derive(Debug, Serialize)]
+pub struct Waveform {
+    pub samples_per_second: u32,
+    pub duration: f64,
+    /// Largest absolute amplitude in each bucket, 0 to 1
+    pub peaks: Vec<f32>,
+    /// Root mean square amplitude of each bucket, 0 to 1
+    pub rms: Vec<f32>,
+}
+
+/// EBU R128 measurements of a whole recording
+#[derive(Debug, Clone, Serialize)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS
+    pub integrated_lufs: f64,
+    /// Maximum true peak in dBTP
+    pub true_peak_dbtp: f64,
+    /// Loudness range in LU
+    pub range_lu: f64,
+    /// Gating threshold in LUFS
+    pub threshold_lufs: f64,
+}
+
+/// What loudnorm reports with a target set: the input as measured, the
+/// output as it will measure, and the gain offset to pass to a second pass
+#[derive(Debug, Clone, Serialize)]
+pub struct LoudnormReport {
+    pub input: Loudness,
+    pub output: Loudness,
+    pub target_offset: f64,
+}
+
+/// The measurement block loudnorm prints with `print_format=json`; values
+/// come as strings
+#[derive(Deserialize)]
+struct LoudnormJson {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    output_i: Option<String>,
+    output_tp: Option<String>,
+    output_lra: Option<String>,
+    output_thresh: Option<String>,
+    target_offset: Option<String>,
+}
+
+/// Build a waveform from mono signed 16-bit little-endian PCM decoded at
+/// `WAVEFORM_SAMPLE_RATE`
+pub fn waveform_from_pcm(pcm: &[u8], samples_per_second: u32) -> Waveform {
+    let bucket = (WAVEFORM_SAMPLE_RATE / samples_per_second).max(1) as usize;
+    let samples: Vec<f32> = pcm
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0)
+        .collect();
+
+    let (peaks, rms) = samples
+        .chunks(bucket)
+        .map(|chunk| {
+            let peak = chunk.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            let mean_square = chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32;
+            (peak, mean_square.sqrt())
+        })
+        .unzip();
+
+    Waveform {
+        samples_per_second,
+        duration: samples.len() as f64 / WAVEFORM_SAMPLE_RATE as f64,
+        peaks,
+        rms,
+    }
+}
+
+/// Parse the JSON block loudnorm prints at the end of its log
+pub fn parse_loudnorm(stderr: &str) -> Result<Loudness, ProcessorError> {
+    let json = loudnorm_json(stderr)?;
+    Ok(Loudness {
+        integrated_lufs: loudnorm_number(&json.input_i)?,
+        true_peak_dbtp: loudnorm_number(&json.input_tp)?,
+        range_lu: loudnorm_number(&json.input_lra)?,
+        threshold_lufs: loudnorm_number(&json.input_thresh)?,
+    })
+}
+
+/// Parse loudnorm's full report, output measurements included
+pub fn parse_loudnorm_report(stderr: &str) -> Result<LoudnormReport, ProcessorError> {
+    let input = parse_loudnorm(stderr)?;
+    let json = loudnorm_json(stderr)?;
+    let field = |value: &Option<String>, name: &str| -> Result<f64, ProcessorError> {
+        value
+            .as_deref()
+            .ok_or_else(|| ProcessorError::FFmpegError(format!("loudnorm printed no {}", name)))
+            .and_then(loudnorm_number)
+    };
+
+    Ok(LoudnormReport {
+        input,
+        output: Loudness {
+            integrated_lufs: field(&json.output_i, "output_i")?,
+            true_peak_dbtp: field(&json.output_tp, "output_tp")?,
+            range_lu: field(&json.output_lra, "output_lra")?,
+            threshold_lufs: field(&json.output_thresh, "output_thresh")?,
+        },
+        target_offset: field(&json.target_offset, "target_offset")?,
+    })
+}
+
+fn loudnorm_json(stderr: &str) -> Result<LoudnormJson, ProcessorError> {
+    let unreadable = || ProcessorError::FFmpegError("loudnorm printed no measurements".to_string());
+
+    let start = stderr.rfind('{').ok_or_else(unreadable)?;
+    let end = stderr[start..].find('}').ok_or_else(unreadable)? + start;
+    serde_json::from_str(&stderr[start..=end])
+        .map_err(|e| ProcessorError::FFmpegError(format!("unreadable loudnorm output: {}", e)))
+}
+
+/// Silence measures as -inf, which is not a useful number to hand out
+fn loudnorm_number(value: &str) -> Result<f64, ProcessorError> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| ProcessorError::BadRequest(format!("Cannot measure loudness (got {:?}); is the audio silent?", value)))
+}
@@ -0,0 +1,163 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::glossary::Glossary;
+use crate::whisper::Segment;
+
+/// Capitalized words that start sentences or phrases but are not names
+const NOT_NAMES: &[&str] = &[
+    "I", "I'm", "I've", "I'll", "The", "A", "An", "And", "But", "So", "Or", "Now", "Then",
+    "This", "That", "These", "Those", "We", "You", "He", "She", "It", "They", "My", "Our",
+    "Your", "His", "Her", "Their", "If", "When", "What", "Why", "How", "Where", "Okay", "Ok",
+    "Yes", "Yeah", "No", "Hey", "Hi", "Hello", "Thanks", "Today", "Let's", "Just", "Well",
+    "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday",
+    "January", "February", "March", "April", "May", "June", "July", "August", "September",
+    "October", "November", "December",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionKind {
+    Name,
+    Phone,
+    Address,
+}
+
+impl RedactionKind {
+    fn placeholder(&self) -> &'static str {
+        match self {
+            RedactionKind::Name => "[NAME]",
+            RedactionKind::Phone => "[PHONE]",
+            RedactionKind::Address => "[ADDRESS]",
+        }
+    }
+}
+
+/// One piece of personal information removed from a transcript
+#[derive(Debug, Clone, Serialize)]
+pub struct Redaction {
+    pub segment: usize,
+    pub kind: RedactionKind,
+    pub original: String,
+    /// Approximate position in the audio, interpolated from where the text
+    /// sits within its segment
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Rule-based detector for names, phone numbers and street addresses.
+/// Terms from the profile glossary (channel and brand names) are never
+/// treated as personal names.
+pub struct Anonymizer {
+    phone: Regex,
+    address: Regex,
+    introduced_name: Regex,
+    titled_name: Regex,
+    full_name: Regex,
+    keep: Vec<String>,
+}
+
+impl Anonymizer {
+    pub fn new(glossary: &Glossary) -> Self {
+        Self {
+            phone: Regex::new(r"(?:\+?1[\s.-]?)?\(?\b\d{3}\)?[\s.-]?\d{3}[\s.-]?\d{4}\b").unwrap(),
+            address: Regex::new(
+                r"\b\d{1,5}\s+(?:[A-Z][a-z]+\s+){1,3}(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Drive|Dr|Lane|Ln|Court|Ct|Way|Place|Pl|Crescent|Terrace)\b",
+            )
+            .unwrap(),
+            introduced_name: Regex::new(
+                r"(?i:my name is|my name's|i'm|i am|this is|named|called|meet|thanks to|shout out to)\s+([A-Z][a-z]+(?:\s+[A-Z][a-z]+)?)",
+            )
+            .unwrap(),
+            titled_name: Regex::new(r"\b(?:Mr|Mrs|Ms|Miss|Dr|Coach)\.?\s+[A-Z][a-z]+(?:\s+[A-Z][a-z]+)?").unwrap(),
+            full_name: Regex::new(r"\b[A-Z][a-z]+(?:\s+[A-Z][a-z]+)+\b").unwrap(),
+            keep: glossary.terms().map(|term| term.to_lowercase()).collect(),
+        }
+    }
+
+    /// Redact personal information in place, returning what was removed
+    pub fn anonymize(&self, segments: &mut [Segment]) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+
+        for (index, segment) in segments.iter_mut().enumerate() {
+            let spans = self.detect(&segment.text);
+            if spans.is_empty() {
+                continue;
+            }
+
+            let length = segment.text.len().max(1) as f64;
+            let duration = segment.end - segment.start;
+            let mut redacted = String::new();
+            let mut last = 0;
+
+            for (start, end, kind) in spans {
+                redactions.push(Redaction {
+                    segment: index,
+                    kind,
+                    original: segment.text[start..end].to_string(),
+                    start: segment.start + duration * start as f64 / length,
+                    end: segment.start + duration * end as f64 / length,
+                });
+                redacted.push_str(&segment.text[last..start]);
+                redacted.push_str(kind.placeholder());
+                last = end;
+            }
+            redacted.push_str(&segment.text[last..]);
+            segment.text = redacted;
+        }
+
+        redactions
+    }
+
+    /// Non-overlapping byte spans to redact, in order. Earlier rules win
+    /// where matches overlap.
+    fn detect(&self, text: &str) -> Vec<(usize, usize, RedactionKind)> {
+        let mut spans: Vec<(usize, usize, RedactionKind)> = Vec::new();
+        let mut add = |start: usize, end: usize, kind: RedactionKind| {
+            if spans.iter().all(|(s, e, _)| end <= *s || start >= *e) {
+                spans.push((start, end, kind));
+            }
+        };
+
+        for m in self.phone.find_iter(text) {
+            add(m.start(), m.end(), RedactionKind::Phone);
+        }
+        for m in self.address.find_iter(text) {
+            add(m.start(), m.end(), RedactionKind::Address);
+        }
+        for captures in self.introduced_name.captures_iter(text) {
+            let name = captures.get(1).unwrap();
+            if self.is_name(name.as_str()) {
+                add(name.start(), name.end(), RedactionKind::Name);
+            }
+        }
+        for m in self.titled_name.find_iter(text) {
+            add(m.start(), m.end(), RedactionKind::Name);
+        }
+        for m in self.full_name.find_iter(text) {
+            // "Then Roger Federer" -> "Roger Federer"
+            let mut start = m.start();
+            for word in m.as_str().split_whitespace() {
+                if !NOT_NAMES.contains(&word) {
+                    break;
+                }
+                let rest = &text[start..m.end()];
+                let after = rest.find(word).unwrap() + word.len();
+                start += after + (rest[after..].len() - rest[after..].trim_start().len());
+            }
+            let candidate = &text[start..m.end()];
+            if candidate.split_whitespace().count() >= 2 && self.is_name(candidate) {
+                add(start, m.end(), RedactionKind::Name);
+            }
+        }
+
+        spans.sort_by_key(|(start, _, _)| *start);
+        spans
+    }
+
+    fn is_name(&self, candidate: &str) -> bool {
+        let first = candidate.split_whitespace().next().unwrap_or_default();
+        let candidate = candidate.to_lowercase();
+        !NOT_NAMES.contains(&first) && !self.keep.iter().any(|term| term.contains(&candidate))
+    }
+}
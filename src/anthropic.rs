@@ -0,0 +1,235 @@
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::error::ProcessorError;
+use crate::llm::{Completion, CompletionBackend, Message, Provider, Role, TokenUsage};
+use crate::openai::RetryPolicy;
+use crate::scheduler::{scheduler, LlmScheduler};
+use crate::telemetry;
+
+/// API version every request is pinned to
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// A client for Anthropic's Messages API
+pub struct AnthropicClient {
+    client: reqwest::Client,
+    config: AnthropicConfig,
+}
+
+/// Model settings sent with every request
+#[derive(Debug, Clone)]
+pub struct AnthropicConfig {
+    pub model: String,
+    /// Sampling temperature in [0, 1]; the API default when unset
+    pub temperature: Option<f32>,
+    /// Cap on tokens in the reply, which this API requires
+    pub max_tokens: u32,
+    /// API root, e.g. `https://api.anthropic.com/v1`
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub retry: RetryPolicy,
+}
+
+impl AnthropicConfig {
+    /// Defaults from ANTHROPIC_API_KEY, ANTHROPIC_MODEL (default
+    /// claude-3-5-sonnet-latest), ANTHROPIC_TEMPERATURE, ANTHROPIC_MAX_TOKENS
+    /// (default 4096) and ANTHROPIC_BASE_URL, with `RetryPolicy::from_env`
+    pub fn from_env() -> Self {
+        let setting = |name: &str| env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        Self {
+            model: setting("ANTHROPIC_MODEL").unwrap_or_else(|| "claude-3-5-sonnet-latest".to_string()),
+            temperature: setting("ANTHROPIC_TEMPERATURE").and_then(|v| v.parse().ok()),
+            max_tokens: setting("ANTHROPIC_MAX_TOKENS").and_then(|v| v.parse().ok()).unwrap_or(4096),
+            base_url: setting("ANTHROPIC_BASE_URL")
+                .map(|v| v.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| "https://api.anthropic.com/v1".to_string()),
+            api_key: setting("ANTHROPIC_API_KEY"),
+            retry: RetryPolicy::from_env(),
+        }
+    }
+}
+
+pub struct AnthropicClientBuilder {
+    config: AnthropicConfig,
+}
+
+impl AnthropicClientBuilder {
+    pub fn model(&mut self, model: &str) -> &mut Self {
+        self.config.model = model.to_string();
+        self
+    }
+
+    pub fn temperature(&mut self, temperature: f32) -> &mut Self {
+        self.config.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(&mut self, max_tokens: u32) -> &mut Self {
+        self.config.max_tokens = max_tokens;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn base_url(&mut self, base_url: &str) -> &mut Self {
+        self.config.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    #[cfg(test)]
+    pub fn api_key(&mut self, api_key: &str) -> &mut Self {
+        self.config.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Result<AnthropicClient, ProcessorError> {
+        let config = self.config.clone();
+        if config.model.trim().is_empty() {
+            return Err(ProcessorError::BadRequest("Model name is empty".to_string()));
+        }
+        if config.temperature.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err(ProcessorError::BadRequest(
+                "Temperature must be between 0 and 1 for Anthropic models".to_string(),
+            ));
+        }
+        if config.max_tokens == 0 {
+            return Err(ProcessorError::BadRequest("max_tokens must be at least 1".to_string()));
+        }
+        let api_key = config.api_key.as_deref()
+            .ok_or_else(|| ProcessorError::OpenAIError("ANTHROPIC_API_KEY is not set".to_string()))?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-api-key",
+            HeaderValue::from_str(api_key)
+                .map_err(|_| ProcessorError::OpenAIError("ANTHROPIC_API_KEY is not a valid header value".to_string()))?,
+        );
+        headers.insert("anthropic-version", HeaderValue::from_static(ANTHROPIC_VERSION));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+
+        Ok(AnthropicClient { client, config })
+    }
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    messages: &'a [Message],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    usage: Option<Usage>,
+}
+
+/// Replies are a list of blocks; only `text` ones matter here
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.input_tokens + usage.output_tokens,
+        }
+    }
+}
+
+impl AnthropicClient {
+    pub fn builder() -> AnthropicClientBuilder {
+        AnthropicClientBuilder { config: AnthropicConfig::from_env() }
+    }
+
+    /// Send a single prompt and return the reply with its token usage
+    pub async fn complete(&self, prompt: &str) -> Result<Completion, ProcessorError> {
+        self.send_messages(&[Message::user(prompt)]).await
+    }
+
+    /// Continue a conversation whose reply must be a JSON object. There is
+    /// no JSON mode, so the reply is prefilled with the opening brace.
+    pub async fn generate_structured(&self, messages: &[Message]) -> Result<Completion, ProcessorError> {
+        let mut messages = messages.to_vec();
+        messages.push(Message::assistant("{"));
+        let mut completion = self.send_messages(&messages).await?;
+        completion.content.insert(0, '{');
+        Ok(completion)
+    }
+
+    async fn send_messages(&self, messages: &[Message]) -> Result<Completion, ProcessorError> {
+        if messages.first().is_none_or(|m| m.role != Role::User) {
+            return Err(ProcessorError::BadRequest("Conversations must start with the user".to_string()));
+        }
+        let request = MessagesRequest {
+            model: &self.config.model,
+            max_tokens: self.config.max_tokens,
+            messages,
+            temperature: self.config.temperature,
+        };
+        let estimate = messages.iter().map(|m| LlmScheduler::estimate_tokens(&m.content)).sum();
+
+        let url = format!("{}/messages", self.config.base_url);
+        let (response, mut permit) = self.config.retry
+            .send_throttled("Anthropic message", scheduler(Provider::Anthropic), estimate, || Ok(self.client.post(&url).json(&request)))
+            .await?;
+
+        let response = tokio::time::timeout(self.config.retry.timeout, response.json::<MessagesResponse>())
+            .await
+            .map_err(|_| ProcessorError::OpenAIError("Timed out reading the completion".to_string()))??;
+        let usage = response.usage.map(TokenUsage::from);
+        if let Some(usage) = &usage {
+            telemetry::metrics().tokens_used(Provider::Anthropic, usage);
+        }
+        permit.record_usage(usage.map_or(estimate, |u| u.total_tokens));
+
+        let content: String = response.content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text)
+            .collect();
+        if content.is_empty() {
+            return Err(ProcessorError::OpenAIError("No text in the reply".to_string()));
+        }
+        Ok(Completion { content, usage })
+    }
+}
+
+impl CompletionBackend for AnthropicClient {
+    fn provider(&self) -> Provider {
+        Provider::Anthropic
+    }
+
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Completion, ProcessorError>> {
+        Box::pin(AnthropicClient::complete(self, prompt))
+    }
+
+    fn generate_structured<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<Completion, ProcessorError>> {
+        Box::pin(AnthropicClient::generate_structured(self, messages))
+    }
+}
@@ -0,0 +1,239 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::jobs::{now, JobStatus};
+use crate::telemetry;
+
+/// Finished batches are forgotten this long after they complete
+const FINISHED_BATCH_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One media file's progress through a batch
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchFile {
+    /// Media ID, or for directory batches the path under the media directory
+    pub input: String,
+    pub status: JobStatus,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+/// Counts of files per status
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+/// The same work run over several media files
+#[derive(Clone, Debug, Serialize)]
+pub struct Batch {
+    pub id: String,
+    pub kind: String,
+    /// `completed` once every file has finished, even if some failed
+    pub status: JobStatus,
+    pub summary: BatchSummary,
+    pub files: Vec<BatchFile>,
+    /// Every file's outcome in one JSON file, written when the batch finishes
+    pub output_file: Option<String>,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+impl Batch {
+    fn summarize(&mut self) {
+        let count = |status| self.files.iter().filter(|file| file.status == status).count();
+        self.summary = BatchSummary {
+            total: self.files.len(),
+            queued: count(JobStatus::Queued),
+            running: count(JobStatus::Running),
+            completed: count(JobStatus::Completed),
+            failed: count(JobStatus::Failed),
+        };
+    }
+}
+
+/// In-memory registry of batches. Each batch works through its files at
+/// most `concurrency` at a time; one file failing doesn't stop the rest.
+/// State is lost on restart.
+pub struct BatchQueue {
+    batches: Arc<Mutex<HashMap<String, Batch>>>,
+    concurrency: usize,
+    next_id: AtomicU64,
+}
+
+impl BatchQueue {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            batches: Arc::new(Mutex::new(HashMap::new())),
+            concurrency: concurrency.max(1),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Files processed at once per batch from BATCH_CONCURRENCY (default 2)
+    pub fn from_env() -> Self {
+        let concurrency = std::env::var("BATCH_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(2);
+        Self::new(concurrency)
+    }
+
+    /// Start running `work` on each of `inputs` and return the batch ID.
+    /// The finished batch is written as `batch-<id>.json` into `report_dir`,
+    /// which API responses call `report_label`.
+    pub fn submit<F, Fut>(
+        &self,
+        kind: &str,
+        inputs: Vec<String>,
+        report_dir: PathBuf,
+        report_label: &str,
+        work: F,
+    ) -> String
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        let id = format!("{:x}-{}", now(), self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut batch = Batch {
+            id: id.clone(),
+            kind: kind.to_string(),
+            status: JobStatus::Running,
+            summary: BatchSummary::default(),
+            files: inputs
+                .iter()
+                .map(|input| BatchFile {
+                    input: input.clone(),
+                    status: JobStatus::Queued,
+                    result: None,
+                    error: None,
+                    started_at: None,
+                    finished_at: None,
+                })
+                .collect(),
+            output_file: None,
+            created_at: now(),
+            finished_at: None,
+        };
+        batch.summarize();
+
+        {
+            let mut batches = self.batches.lock().unwrap();
+            let cutoff = now().saturating_sub(FINISHED_BATCH_TTL.as_secs());
+            batches.retain(|_, batch| batch.finished_at.is_none_or(|at| at >= cutoff));
+            batches.insert(id.clone(), batch);
+        }
+        tracing::info!("Queued {} batch {} of {} files", kind, id, inputs.len());
+
+        let report = report_dir.join(format!("batch-{}.json", id));
+        let output_file = format!("{}/batch-{}.json", report_label, id);
+        let batches = self.batches.clone();
+        let slots = Arc::new(Semaphore::new(self.concurrency));
+        let work = Arc::new(work);
+        let batch_id = id.clone();
+        let job_kind = format!("batch_{}", kind);
+        let span = tracing::info_span!("batch", batch_id = %id, kind = %kind);
+        tokio::spawn(async move {
+            let mut tasks = Vec::new();
+            for (index, input) in inputs.into_iter().enumerate() {
+                let slots = slots.clone();
+                let work = work.clone();
+                let batches = batches.clone();
+                let batch_id = batch_id.clone();
+                let job_kind = job_kind.clone();
+                let file_span = tracing::info_span!("file", input = %input);
+                tasks.push(tokio::spawn(async move {
+                    let _slot = slots.acquire_owned().await.expect("batch semaphore closed");
+                    update_file(&batches, &batch_id, index, |file| {
+                        file.status = JobStatus::Running;
+                        file.started_at = Some(now());
+                    });
+                    tracing::info!("Batch {}: processing {}", batch_id, input);
+                    let outcome = work(input).await;
+                    telemetry::metrics().job_finished(&job_kind, outcome.is_ok());
+                    update_file(&batches, &batch_id, index, |file| {
+                        file.finished_at = Some(now());
+                        match outcome {
+                            Ok(result) => {
+                                file.status = JobStatus::Completed;
+                                file.result = Some(result);
+                            }
+                            Err(error) => {
+                                file.status = JobStatus::Failed;
+                                file.error = Some(error);
+                            }
+                        }
+                    });
+                }.instrument(file_span)));
+            }
+            for task in tasks {
+                let _ = task.await;
+            }
+
+            let finished = {
+                let mut batches = batches.lock().unwrap();
+                let Some(batch) = batches.get_mut(&batch_id) else {
+                    return;
+                };
+                // A panicked file never reported back
+                for file in batch.files.iter_mut().filter(|file| file.finished_at.is_none()) {
+                    file.status = JobStatus::Failed;
+                    file.error = Some("Processing panicked".to_string());
+                    file.finished_at = Some(now());
+                }
+                batch.status = JobStatus::Completed;
+                batch.finished_at = Some(now());
+                batch.output_file = Some(output_file);
+                batch.summarize();
+                batch.clone()
+            };
+            let written = serde_json::to_vec_pretty(&finished)
+                .map_err(std::io::Error::other)
+                .and_then(|content| std::fs::write(&report, content));
+            if let Err(e) = written {
+                tracing::warn!("Could not write batch report {:?}: {}", report, e);
+                if let Some(batch) = batches.lock().unwrap().get_mut(&batch_id) {
+                    batch.output_file = None;
+                }
+            }
+            tracing::info!(
+                "Finished batch {}: {} completed, {} failed",
+                batch_id, finished.summary.completed, finished.summary.failed
+            );
+        }.instrument(span));
+
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<Batch> {
+        self.batches.lock().unwrap().get(id).cloned()
+    }
+}
+
+fn update_file(
+    batches: &Mutex<HashMap<String, Batch>>,
+    id: &str,
+    index: usize,
+    change: impl FnOnce(&mut BatchFile),
+) {
+    if let Some(batch) = batches.lock().unwrap().get_mut(id) {
+        if let Some(file) = batch.files.get_mut(index) {
+            change(file);
+        }
+        batch.summarize();
+    }
+}
@@ -0,0 +1,117 @@
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::error::ProcessorError;
+use crate::ffmpeg::{FFmpegClient, MergeOptions};
+use crate::whisper::WhisperClient;
+
+/// Timing for one pipeline stage
+#[derive(Debug, Serialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Seconds of media processed per second of wall time
+    pub realtime_factor: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub input: String,
+    pub media_seconds: f64,
+    pub whisper_model: String,
+    pub cpus: usize,
+    pub stages: Vec<StageTiming>,
+    pub total_ms: u64,
+}
+
+/// What to benchmark and where to put scratch output
+pub struct BenchmarkOptions<'a> {
+    pub input: &'a Path,
+    pub whisper_model: Option<&'a str>,
+    pub chunk_seconds: u64,
+    /// Scratch directory; removed when the run finishes
+    pub work_dir: &'a Path,
+}
+
+fn time_stage<T>(
+    stages: &mut Vec<StageTiming>,
+    stage: &str,
+    media_seconds: f64,
+    run: impl FnOnce() -> Result<T, ProcessorError>,
+) -> Option<T> {
+    tracing::info!("Benchmark: {}", stage);
+    let started = Instant::now();
+    let result = run();
+    let elapsed = started.elapsed();
+
+    let (success, error, value) = match result {
+        Ok(value) => (true, None, Some(value)),
+        Err(e) => (false, Some(e.to_string()), None),
+    };
+    stages.push(StageTiming {
+        stage: stage.to_string(),
+        success,
+        duration_ms: elapsed.as_millis() as u64,
+        realtime_factor: (success && elapsed.as_secs_f64() > 0.0)
+            .then(|| media_seconds / elapsed.as_secs_f64()),
+        error,
+    });
+
+    value
+}
+
+/// Run a reference file through each stage (probe, split, merge, frame grab,
+/// transcription), uncached, and report how long each took. Stages that
+/// depend on a failed one are skipped.
+pub fn run_benchmark(options: &BenchmarkOptions) -> Result<BenchmarkReport, ProcessorError> {
+    let started = Instant::now();
+    std::fs::create_dir_all(options.work_dir)?;
+
+    let mut ffmpeg = FFmpegClient::new();
+    ffmpeg.with_input(options.input);
+    let media_seconds = ffmpeg.duration_seconds()?;
+
+    let mut stages = Vec::new();
+
+    let info = time_stage(&mut stages, "probe", media_seconds, || ffmpeg.get_info());
+
+    let chunks_dir = options.work_dir.join("chunks");
+    ffmpeg
+        .with_output_dir(&chunks_dir)
+        .with_chunk_duration(options.chunk_seconds);
+    let chunks = time_stage(&mut stages, "split", media_seconds, || ffmpeg.split_into_chunks());
+
+    if let Some(chunks) = chunks {
+        let merged = options.work_dir.join("merged.mp3");
+        time_stage(&mut stages, "merge", media_seconds, || ffmpeg.merge_chunks(chunks, merged, &MergeOptions::default()));
+    }
+
+    if info.is_some_and(|info| info.has_video()) {
+        let frame = options.work_dir.join("frame.jpg");
+        time_stage(&mut stages, "extract_frame", media_seconds, || {
+            ffmpeg.extract_frame(media_seconds / 2.0, &frame)
+        });
+    }
+
+    let mut whisper = WhisperClient::new();
+    whisper.with_output_dir(options.work_dir.join("transcripts"));
+    if let Some(model) = options.whisper_model {
+        whisper.with_model(model);
+    }
+    let whisper_model = whisper.model().to_string();
+    time_stage(&mut stages, "transcribe", media_seconds, || whisper.transcribe(options.input));
+
+    let _ = std::fs::remove_dir_all(options.work_dir);
+
+    Ok(BenchmarkReport {
+        input: options.input.to_string_lossy().to_string(),
+        media_seconds,
+        whisper_model,
+        cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        stages,
+        total_ms: started.elapsed().as_millis() as u64,
+    })
+}
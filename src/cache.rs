@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Content-addressed store for ffmpeg outputs.
+///
+/// Entries are keyed by the SHA-256 of the input file(s) plus the operation
+/// name and its parameters, so re-running the same operation on unchanged
+/// media is a lookup instead of an ffmpeg run.
+pub struct ArtifactCache {
+    root: PathBuf,
+    entries: Mutex<HashMap<String, Vec<PathBuf>>>,
+    hasher: FileHasher,
+}
+
+/// SHA-256 of file contents, remembered by (path, size, mtime) so unchanged
+/// files aren't re-hashed
+#[derive(Default)]
+pub struct FileHasher {
+    hashes: Mutex<HashMap<(PathBuf, u64, SystemTime), String>>,
+}
+
+impl FileHasher {
+    /// Hex-encoded SHA-256 of the file at `path`
+    pub fn hash(&self, path: &Path) -> Result<String, std::io::Error> {
+        let metadata = std::fs::metadata(path)?;
+        let fingerprint = (path.to_path_buf(), metadata.len(), metadata.modified()?);
+
+        if let Some(hash) = self.hashes.lock().unwrap().get(&fingerprint) {
+            return Ok(hash.clone());
+        }
+
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        let hash = format!("{:x}", hasher.finalize());
+
+        self.hashes.lock().unwrap().insert(fingerprint, hash.clone());
+        Ok(hash)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    operation: String,
+    artifacts: Vec<String>,
+}
+
+impl ArtifactCache {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            entries: Mutex::new(HashMap::new()),
+            hasher: FileHasher::default(),
+        }
+    }
+
+    /// Build the cache key for an operation over the given inputs
+    pub fn key(&self, inputs: &[&Path], operation: &str, params: &str) -> Result<String, std::io::Error> {
+        let mut hasher = Sha256::new();
+        for input in inputs {
+            hasher.update(self.hasher.hash(input)?.as_bytes());
+        }
+        hasher.update(operation.as_bytes());
+        hasher.update(params.as_bytes());
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Look up cached artifacts, checking memory first and then disk
+    pub fn get(&self, key: &str) -> Option<Vec<PathBuf>> {
+        if let Some(paths) = self.entries.lock().unwrap().get(key) {
+            if paths.iter().all(|p| p.exists()) {
+                return Some(paths.clone());
+            }
+        }
+
+        let entry_dir = self.root.join(key);
+        let manifest = std::fs::read_to_string(entry_dir.join("manifest.json")).ok()?;
+        let manifest: Manifest = serde_json::from_str(&manifest).ok()?;
+        let paths: Vec<PathBuf> = manifest.artifacts
+            .iter()
+            .map(|name| entry_dir.join(name))
+            .collect();
+
+        if !paths.iter().all(|p| p.exists()) {
+            return None;
+        }
+
+        self.entries.lock().unwrap().insert(key.to_string(), paths.clone());
+        Some(paths)
+    }
+
+    /// Copy freshly produced artifacts into the cache under `key`
+    pub fn put(&self, key: &str, operation: &str, artifacts: &[PathBuf]) -> Result<(), std::io::Error> {
+        let entry_dir = self.root.join(key);
+        std::fs::create_dir_all(&entry_dir)?;
+
+        let mut names = Vec::new();
+        let mut paths = Vec::new();
+        for artifact in artifacts {
+            let name = artifact.file_name()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Invalid artifact path"
+                ))?;
+            let cached = entry_dir.join(name);
+            std::fs::copy(artifact, &cached)?;
+            names.push(name.to_string());
+            paths.push(cached);
+        }
+
+        let manifest = Manifest {
+            operation: operation.to_string(),
+            artifacts: names,
+        };
+        std::fs::write(entry_dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+
+        self.entries.lock().unwrap().insert(key.to_string(), paths);
+        Ok(())
+    }
+
+    /// Restore cached artifacts into `output_dir`, keeping their file names
+    pub fn restore(&self, cached: &[PathBuf], output_dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+        std::fs::create_dir_all(output_dir)?;
+
+        let mut restored = Vec::new();
+        for artifact in cached {
+            let target = output_dir.join(artifact.file_name().unwrap());
+            if target.exists() {
+                std::fs::remove_file(&target)?;
+            }
+            // A copy rather than a hard link: ffmpeg later overwrites outputs
+            // in place, which would write through a link into the cache
+            std::fs::copy(artifact, &target)?;
+            restored.push(target);
+        }
+
+        Ok(restored)
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+
+use crate::whisper::Segment;
+
+/// Hesitation sounds dropped wherever they appear. Words like "like" or
+/// "you know" stay, since they are only sometimes filler.
+const FILLER_WORDS: &[&str] = &["um", "umm", "uh", "uhh", "uhm", "er", "erm", "hmm", "hmmm"];
+
+/// What a cleanup pass changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub fillers_removed: usize,
+    pub repeats_removed: usize,
+    /// Segments the LLM polish changed, when it ran
+    pub polished: usize,
+}
+
+/// One whitespace-separated word, split into the punctuation around it and
+/// the word itself
+struct Token {
+    lead: String,
+    core: String,
+    trail: String,
+}
+
+impl Token {
+    fn parse(raw: &str) -> Self {
+        let start = raw.find(char::is_alphanumeric).unwrap_or(raw.len());
+        let end = raw
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_alphanumeric())
+            .map_or(start, |(i, c)| i + c.len_utf8());
+        Token {
+            lead: raw[..start].to_string(),
+            core: raw[start..end].to_string(),
+            trail: raw[end..].to_string(),
+        }
+    }
+
+    fn ends_sentence(&self) -> bool {
+        self.trail
+            .trim_end_matches(['"', '\'', ')', ']'])
+            .ends_with(['.', '?', '!'])
+    }
+}
+
+/// Rule-based cleanup of whisper segments, in place: filler words and
+/// stuttered repeats ("the the") are dropped, stray punctuation is tidied,
+/// "i" becomes "I" and sentences start with a capital. Segment timing is
+/// untouched and segments left empty are kept, so the result lines up with
+/// the raw transcript; word timings keep the raw words.
+pub fn clean_segments(segments: &mut [Segment]) -> CleanupReport {
+    let mut report = CleanupReport::default();
+    let mut sentence_start = true;
+    for segment in segments.iter_mut() {
+        segment.text = clean_text(&segment.text, &mut sentence_start, &mut report);
+    }
+    report
+}
+
+fn clean_text(text: &str, sentence_start: &mut bool, report: &mut CleanupReport) -> String {
+    let mut kept: Vec<Token> = Vec::new();
+
+    for raw in text.split_whitespace() {
+        let token = Token::parse(raw);
+
+        // Punctuation on its own belongs to the word before it
+        if token.core.is_empty() {
+            if let Some(previous) = kept.last_mut() {
+                previous.trail.push_str(&token.lead);
+            }
+            continue;
+        }
+
+        let lower = token.core.to_lowercase();
+        if token.lead.is_empty() && FILLER_WORDS.contains(&lower.as_str()) {
+            report.fillers_removed += 1;
+            if let Some(previous) = kept.last_mut() {
+                previous.trail = previous.trail.trim_end_matches(',').to_string();
+                if token.ends_sentence() && !previous.ends_sentence() {
+                    previous.trail.push_str(token.trail.trim_start_matches(','));
+                }
+            }
+            continue;
+        }
+
+        let stutter = kept.last().is_some_and(|previous| {
+            token.lead.is_empty()
+                && matches!(previous.trail.as_str(), "" | ",")
+                && previous.core.to_lowercase() == lower
+                && !lower.chars().all(|c| c.is_ascii_digit())
+        });
+        if stutter {
+            report.repeats_removed += 1;
+            kept.last_mut().unwrap().trail = token.trail;
+            continue;
+        }
+
+        kept.push(token);
+    }
+
+    let mut words = Vec::with_capacity(kept.len());
+    for mut token in kept {
+        if token.core == "i" || token.core.starts_with("i'") {
+            token.core.replace_range(..1, "I");
+        }
+        if *sentence_start {
+            token.core = capitalize(&token.core);
+        }
+        token.trail = tidy_punctuation(&token.trail);
+        *sentence_start = token.ends_sentence();
+        words.push(format!("{}{}{}", token.lead, token.core, token.trail));
+    }
+    words.join(" ")
+}
+
+/// Collapse doubled commas, and drop commas next to a sentence end
+fn tidy_punctuation(trail: &str) -> String {
+    let mut tidy = trail.to_string();
+    while tidy.contains(",,") {
+        tidy = tidy.replace(",,", ",");
+    }
+    if tidy.contains(['.', '?', '!']) {
+        tidy.retain(|c| c != ',');
+    }
+    tidy
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,233 @@
+use clap::Parser;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::llm::Provider;
+use crate::telemetry::LogFormat;
+
+/// Where the server listens, where it keeps its files and which tools it
+/// runs. Settings come from, lowest precedence first: built-in defaults,
+/// `config.toml`, environment variables, then command-line flags.
+///
+/// ```toml
+/// bind = "0.0.0.0"
+/// port = 8080
+/// data_dir = "/var/lib/processor"
+/// media_dir = "/mnt/footage"
+/// ffmpeg_path = "/opt/ffmpeg/bin/ffmpeg"
+/// ffprobe_path = "/opt/ffmpeg/bin/ffprobe"
+/// whisper_path = "/opt/whisper/bin/whisper"
+/// whisper_model = "small"
+/// diarize_path = "/opt/processor/scripts/diarize.py"
+/// max_processes = 4
+/// llm_provider = "anthropic"
+/// log_format = "json"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: IpAddr,
+    pub port: u16,
+    /// Workspace root (DATA_DIR)
+    pub data_dir: PathBuf,
+    /// Source media; defaults to `media/` under the data directory (MEDIA_DIR)
+    pub media_dir: Option<PathBuf>,
+    /// FFMPEG_PATH
+    pub ffmpeg_path: String,
+    /// FFPROBE_PATH
+    pub ffprobe_path: String,
+    /// WHISPER_PATH
+    pub whisper_path: String,
+    /// Default whisper model (WHISPER_MODEL)
+    pub whisper_model: String,
+    /// Speaker diarization script (DIARIZE_PATH)
+    pub diarize_path: String,
+    /// Most ffmpeg/whisper processes running at once across all requests;
+    /// defaults to the number of CPUs (MAX_PROCESSES)
+    pub max_processes: usize,
+    /// LLM service used when a request doesn't name one (LLM_PROVIDER)
+    pub llm_provider: Provider,
+    /// `text` or `json` log lines (LOG_FORMAT)
+    pub log_format: LogFormat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 3000,
+            data_dir: PathBuf::from("data"),
+            media_dir: None,
+            ffmpeg_path: "ffmpeg".to_string(),
+            ffprobe_path: "ffprobe".to_string(),
+            whisper_path: "whisper".to_string(),
+            whisper_model: "base".to_string(),
+            diarize_path: "scripts/diarize.py".to_string(),
+            max_processes: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+            llm_provider: Provider::default(),
+            log_format: LogFormat::default(),
+        }
+    }
+}
+
+/// Command-line flags; each one overrides the matching setting
+#[derive(Debug, Default, Parser)]
+#[command(about = "Sports video processing server")]
+pub struct Cli {
+    /// Config file to read (default `config.toml`, skipped if missing)
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub bind: Option<IpAddr>,
+    #[arg(long)]
+    pub port: Option<u16>,
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    #[arg(long, value_name = "DIR")]
+    pub media_dir: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    pub ffmpeg_path: Option<String>,
+    #[arg(long, value_name = "PATH")]
+    pub ffprobe_path: Option<String>,
+    #[arg(long, value_name = "PATH")]
+    pub whisper_path: Option<String>,
+    #[arg(long, value_name = "MODEL")]
+    pub whisper_model: Option<String>,
+    #[arg(long, value_name = "PATH")]
+    pub diarize_path: Option<String>,
+    #[arg(long, value_name = "N")]
+    pub max_processes: Option<usize>,
+    #[arg(long, value_enum)]
+    pub llm_provider: Option<Provider>,
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+}
+
+impl Config {
+    /// Build the configuration from all sources. An explicitly named config
+    /// file must exist; the default `config.toml` is optional.
+    pub fn load(cli: Cli) -> Result<Self, String> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new("config.toml").is_file() => Self::from_file(Path::new("config.toml"))?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        config.apply_cli(cli);
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        toml::from_str(&content).map_err(|e| format!("Invalid config {:?}: {}", path, e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        if let Some(bind) = env("BIND_ADDRESS") {
+            self.bind = bind.parse().map_err(|_| format!("Invalid BIND_ADDRESS {:?}", bind))?;
+        }
+        if let Some(port) = env("PORT") {
+            self.port = port.parse().map_err(|_| format!("Invalid PORT {:?}", port))?;
+        }
+        if let Some(dir) = env("DATA_DIR") {
+            self.data_dir = dir.into();
+        }
+        if let Some(dir) = env("MEDIA_DIR") {
+            self.media_dir = Some(dir.into());
+        }
+        if let Some(path) = env("FFMPEG_PATH") {
+            self.ffmpeg_path = path;
+        }
+        if let Some(path) = env("FFPROBE_PATH") {
+            self.ffprobe_path = path;
+        }
+        if let Some(path) = env("WHISPER_PATH") {
+            self.whisper_path = path;
+        }
+        if let Some(model) = env("WHISPER_MODEL") {
+            self.whisper_model = model;
+        }
+        if let Some(path) = env("DIARIZE_PATH") {
+            self.diarize_path = path;
+        }
+        if let Some(max) = env("MAX_PROCESSES") {
+            self.max_processes = max.parse().map_err(|_| format!("Invalid MAX_PROCESSES {:?}", max))?;
+        }
+        if let Some(provider) = env("LLM_PROVIDER") {
+            self.llm_provider = Provider::from_name(&provider)
+                .ok_or_else(|| format!("Invalid LLM_PROVIDER {:?}", provider))?;
+        }
+        if let Some(format) = env("LOG_FORMAT") {
+            self.log_format = LogFormat::from_name(&format)
+                .ok_or_else(|| format!("Invalid LOG_FORMAT {:?}", format))?;
+        }
+        if self.max_processes == 0 {
+            return Err("max_processes must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    fn apply_cli(&mut self, cli: Cli) {
+        if let Some(bind) = cli.bind {
+            self.bind = bind;
+        }
+        if let Some(port) = cli.port {
+            self.port = port;
+        }
+        if let Some(dir) = cli.data_dir {
+            self.data_dir = dir;
+        }
+        if let Some(dir) = cli.media_dir {
+            self.media_dir = Some(dir);
+        }
+        if let Some(path) = cli.ffmpeg_path {
+            self.ffmpeg_path = path;
+        }
+        if let Some(path) = cli.ffprobe_path {
+            self.ffprobe_path = path;
+        }
+        if let Some(path) = cli.whisper_path {
+            self.whisper_path = path;
+        }
+        if let Some(model) = cli.whisper_model {
+            self.whisper_model = model;
+        }
+        if let Some(path) = cli.diarize_path {
+            self.diarize_path = path;
+        }
+        if let Some(max) = cli.max_processes {
+            self.max_processes = max.max(1);
+        }
+        if let Some(provider) = cli.llm_provider {
+            self.llm_provider = provider;
+        }
+        if let Some(format) = cli.log_format {
+            self.log_format = format;
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+}
+
+/// A set, non-blank environment variable
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Install the configuration. Only the first call has any effect, and it
+/// must happen before any client is built.
+pub fn init(config: Config) -> &'static Config {
+    CONFIG.get_or_init(|| config)
+}
+
+/// The installed configuration, or the defaults if none was installed
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::config;
+use crate::error::ProcessorError;
+use crate::events::Stage;
+use crate::ffmpeg::{AudioCodec, FFmpegClient};
+use crate::process;
+use crate::whisper::Segment;
+use crate::workspace;
+
+/// A stretch of audio the diarizer attributed to one (unnamed) speaker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerTurn {
+    pub start: f64,
+    pub end: f64,
+    pub speaker: String,
+}
+
+/// What the diarization script prints on stdout
+#[derive(Deserialize)]
+struct DiarizeOutput {
+    turns: Vec<SpeakerTurn>,
+}
+
+/// Finds who speaks when by running the diarization script
+/// (`scripts/diarize.py`, pyannote under the hood) on the input's audio
+#[derive(Clone)]
+pub struct Diarizer {
+    binary_path: String,
+    speakers: Option<u32>,
+    work_dir: Option<PathBuf>,
+}
+
+impl Diarizer {
+    /// Create a diarizer using the configured script
+    pub fn new() -> Self {
+        Self {
+            binary_path: config().diarize_path.clone(),
+            speakers: None,
+            work_dir: None,
+        }
+    }
+
+    /// How many people talk, when known; otherwise the script guesses
+    pub fn with_speakers(&mut self, speakers: u32) -> &mut Self {
+        self.speakers = Some(speakers);
+        self
+    }
+
+    /// Where the audio handed to the script is extracted (in a scratch
+    /// directory that is removed afterwards)
+    pub fn with_work_dir<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.work_dir = Some(path.into());
+        self
+    }
+
+    /// Speaker turns in `input`, in playback order
+    pub fn diarize(&self, input: &Path) -> Result<Vec<SpeakerTurn>, ProcessorError> {
+        let stage = Stage::start("diarize");
+        stage.finish(self.run_diarize(input))
+    }
+
+    fn run_diarize(&self, input: &Path) -> Result<Vec<SpeakerTurn>, ProcessorError> {
+        if self.speakers.is_some_and(|n| !(1..=20).contains(&n)) {
+            return Err(ProcessorError::BadRequest("speakers must be between 1 and 20".to_string()));
+        }
+        let work_dir = self.work_dir.as_ref()
+            .ok_or_else(|| ProcessorError::BadRequest("Work directory not set".to_string()))?;
+
+        // pyannote wants 16 kHz mono WAV, which is exactly what whisper gets
+        let scratch = workspace::scratch_dir(work_dir)?;
+        let audio = FFmpegClient::new()
+            .with_input(input)
+            .with_output_dir(scratch.path())
+            .extract_audio(AudioCodec::Pcm, 16000)?;
+
+        let mut args = vec![audio.to_str().unwrap().to_string()];
+        if let Some(speakers) = self.speakers {
+            args.extend(["--num_speakers".to_string(), speakers.to_string()]);
+        }
+        tracing::info!("Running diarization on {:?}", input);
+        let output = process::run(&self.binary_path, &args)
+            .map_err(|e| process::spawn_error(&self.binary_path, e, ProcessorError::DiarizationError))?;
+        if !output.status.success() {
+            return Err(ProcessorError::DiarizationError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+
+        let parsed: DiarizeOutput = serde_json::from_slice(&output.stdout)
+            .map_err(|e| ProcessorError::DiarizationError(format!("unreadable output: {}", e)))?;
+        let mut turns: Vec<SpeakerTurn> = parsed.turns
+            .into_iter()
+            .filter(|turn| turn.end > turn.start)
+            .collect();
+        turns.sort_by(|a, b| a.start.total_cmp(&b.start));
+        tracing::info!("Found {} speaker turns", turns.len());
+        Ok(turns)
+    }
+}
+
+/// Give each segment the speaker whose turns overlap it the most. The
+/// diarizer's labels become `Speaker 1`, `Speaker 2`, ... in order of first
+/// appearance. Segments nobody overlaps keep no speaker. Returns the labels
+/// used, in that order.
+pub fn assign_speakers(segments: &mut [Segment], turns: &[SpeakerTurn]) -> Vec<String> {
+    let mut labels: HashMap<&str, String> = HashMap::new();
+    let mut order = Vec::new();
+
+    for segment in segments.iter_mut() {
+        let mut overlaps: HashMap<&str, f64> = HashMap::new();
+        for turn in turns {
+            let overlap = segment.end.min(turn.end) - segment.start.max(turn.start);
+            if overlap > 0.0 {
+                *overlaps.entry(turn.speaker.as_str()).or_default() += overlap;
+            }
+        }
+        // Ties go to whoever spoke first, so the result doesn't depend on hashing
+        let best = overlaps
+            .into_iter()
+            .max_by(|(a, x), (b, y)| x.total_cmp(y).then_with(|| first_turn(turns, b).total_cmp(&first_turn(turns, a))));
+        segment.speaker = best.map(|(speaker, _)| {
+            labels
+                .entry(speaker)
+                .or_insert_with(|| {
+                    let label = format!("Speaker {}", order.len() + 1);
+                    order.push(label.clone());
+                    label
+                })
+                .clone()
+        });
+    }
+
+    order
+}
+
+fn first_turn(turns: &[SpeakerTurn], speaker: &str) -> f64 {
+    turns.iter().find(|turn| turn.speaker == speaker).map_or(f64::MAX, |turn| turn.start)
+}
+
+/// Replace speaker labels with the names in `names`; labels without a name
+/// are kept
+pub fn apply_names(segments: &mut [Segment], names: &HashMap<String, String>) {
+    for segment in segments.iter_mut() {
+        if let Some(name) = segment.speaker.as_ref().and_then(|label| names.get(label)) {
+            segment.speaker = Some(name.clone());
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Edit decision list: the ordered record of edits that produced an output
+/// file, stored as `<output>.edl.json` next to it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EditDecisionList {
+    /// The original source media the edits started from
+    pub source: String,
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Edit {
+    /// A clip inserted into the timeline with fades at both joins
+    InsertClip {
+        at: f64,
+        clip: String,
+        clip_duration: f64,
+        fade: f64,
+    },
+}
+
+impl EditDecisionList {
+    /// Path of the EDL sidecar for a media file
+    pub fn path_for(media: &Path) -> PathBuf {
+        let mut name = media.file_name().unwrap_or_default().to_os_string();
+        name.push(".edl.json");
+        media.with_file_name(name)
+    }
+
+    /// Load the EDL for `media`. Media without one is an unedited source.
+    pub fn load(media: &Path) -> Self {
+        std::fs::read_to_string(Self::path_for(media))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| Self {
+                source: media.to_string_lossy().to_string(),
+                edits: Vec::new(),
+            })
+    }
+
+    /// Record an edit that turned `input` into `output`, carrying over the
+    /// input's own history so chained edits stay traceable to the source
+    pub fn record(input: &Path, output: &Path, edit: Edit) -> Result<Self, std::io::Error> {
+        let mut edl = Self::load(input);
+        edl.edits.push(edit);
+        std::fs::write(Self::path_for(output), serde_json::to_string_pretty(&edl)?)?;
+        Ok(edl)
+    }
+}
@@ -0,0 +1,115 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde_json::json;
+use std::fmt;
+
+/// Everything that can go wrong while handling a request. Each variant maps
+/// to an HTTP status and a JSON body of the form
+/// `{"error": "...", "kind": "..."}`.
+#[derive(Debug)]
+pub enum ProcessorError {
+    /// ffmpeg could not be started or exited with an error
+    FFmpegError(String),
+    /// whisper could not be started, failed, or wrote unreadable output
+    WhisperError(String),
+    /// The diarization script could not be started, failed, or printed
+    /// something unreadable
+    DiarizationError(String),
+    /// An LLM provider's API (OpenAI, Anthropic, Ollama) was unreachable,
+    /// refused the request or replied with something we couldn't use. Kept
+    /// under this name, and the `openai` kind, for existing clients.
+    OpenAIError(String),
+    NotFound(String),
+    BadRequest(String),
+    PayloadTooLarge(String),
+    /// The transcript database could not be read or written
+    Database(String),
+    /// Too much work is already running; try again later
+    Busy(String),
+    /// Filesystem and other local failures
+    Io(std::io::Error),
+}
+
+impl ProcessorError {
+    pub fn media_not_found(media_id: &str) -> Self {
+        ProcessorError::NotFound(format!("Media not found: {}", media_id))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ProcessorError::NotFound(_) => StatusCode::NOT_FOUND,
+            ProcessorError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ProcessorError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ProcessorError::OpenAIError(_) => StatusCode::BAD_GATEWAY,
+            ProcessorError::Busy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ProcessorError::FFmpegError(_)
+            | ProcessorError::WhisperError(_)
+            | ProcessorError::DiarizationError(_)
+            | ProcessorError::Database(_)
+            | ProcessorError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Short machine-readable name for the variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProcessorError::FFmpegError(_) => "ffmpeg",
+            ProcessorError::WhisperError(_) => "whisper",
+            ProcessorError::DiarizationError(_) => "diarization",
+            ProcessorError::OpenAIError(_) => "openai",
+            ProcessorError::NotFound(_) => "not_found",
+            ProcessorError::BadRequest(_) => "bad_request",
+            ProcessorError::PayloadTooLarge(_) => "payload_too_large",
+            ProcessorError::Database(_) => "database",
+            ProcessorError::Busy(_) => "busy",
+            ProcessorError::Io(_) => "io",
+        }
+    }
+}
+
+impl fmt::Display for ProcessorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessorError::FFmpegError(message) => write!(f, "ffmpeg failed: {}", message),
+            ProcessorError::WhisperError(message) => write!(f, "whisper failed: {}", message),
+            ProcessorError::DiarizationError(message) => write!(f, "diarization failed: {}", message),
+            ProcessorError::OpenAIError(message) => write!(f, "OpenAI request failed: {}", message),
+            ProcessorError::Database(message) => write!(f, "database error: {}", message),
+            ProcessorError::NotFound(message)
+            | ProcessorError::BadRequest(message)
+            | ProcessorError::PayloadTooLarge(message)
+            | ProcessorError::Busy(message) => write!(f, "{}", message),
+            ProcessorError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProcessorError {}
+
+impl From<std::io::Error> for ProcessorError {
+    fn from(e: std::io::Error) -> Self {
+        ProcessorError::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for ProcessorError {
+    fn from(e: reqwest::Error) -> Self {
+        ProcessorError::OpenAIError(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for ProcessorError {
+    fn from(e: rusqlite::Error) -> Self {
+        ProcessorError::Database(e.to_string())
+    }
+}
+
+impl IntoResponse for ProcessorError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("Request failed ({}): {}", self.kind(), self);
+        }
+        (status, Json(json!({ "error": self.to_string(), "kind": self.kind() }))).into_response()
+    }
+}
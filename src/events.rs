@@ -0,0 +1,172 @@
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+use crate::telemetry;
+
+/// Something significant the service did. Every event goes onto one bus so
+/// logs, metrics and live subscribers all see the same stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipelineEvent {
+    StageStarted {
+        stage: String,
+    },
+    StageFinished {
+        stage: String,
+        success: bool,
+        duration_ms: u64,
+    },
+    SubprocessSpawned {
+        program: String,
+        args: Vec<String>,
+    },
+    SubprocessExited {
+        program: String,
+        success: bool,
+        duration_ms: u64,
+    },
+    ArtifactProduced {
+        path: String,
+        /// True when the artifact was restored from the cache
+        cached: bool,
+    },
+    /// How far an ffmpeg run has got through its input
+    Progress {
+        input: String,
+        percent: u8,
+    },
+}
+
+impl PipelineEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            PipelineEvent::StageStarted { .. } => "stage_started",
+            PipelineEvent::StageFinished { .. } => "stage_finished",
+            PipelineEvent::SubprocessSpawned { .. } => "subprocess_spawned",
+            PipelineEvent::SubprocessExited { .. } => "subprocess_exited",
+            PipelineEvent::ArtifactProduced { .. } => "artifact_produced",
+            PipelineEvent::Progress { .. } => "progress",
+        }
+    }
+}
+
+/// An event with its sequence number and emission time
+#[derive(Debug, Clone, Serialize)]
+pub struct Envelope {
+    pub id: u64,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: PipelineEvent,
+}
+
+pub struct EventBus {
+    sender: broadcast::Sender<Envelope>,
+    next_id: AtomicU64,
+}
+
+impl EventBus {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(1024);
+        Self {
+            sender,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Envelope> {
+        self.sender.subscribe()
+    }
+
+    fn emit(&self, event: PipelineEvent) {
+        let envelope = Envelope {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            event,
+        };
+        // No subscribers is fine; the event is simply dropped
+        let _ = self.sender.send(envelope);
+    }
+}
+
+/// The process-wide event bus. Emitters live deep inside the ffmpeg and
+/// whisper clients, so the bus is global rather than threaded through them.
+pub fn bus() -> &'static EventBus {
+    static BUS: OnceLock<EventBus> = OnceLock::new();
+    BUS.get_or_init(EventBus::new)
+}
+
+/// Publish `event`. It is logged and counted right away, in the span of
+/// whatever emitted it, before it goes out to subscribers.
+pub fn emit(event: PipelineEvent) {
+    telemetry::metrics().observe(&event);
+    bus().emit(event);
+}
+
+pub fn artifact(path: &Path, cached: bool) {
+    emit(PipelineEvent::ArtifactProduced {
+        path: path.to_string_lossy().to_string(),
+        cached,
+    });
+}
+
+/// A running pipeline stage; reports its outcome and duration when finished
+pub struct Stage {
+    name: String,
+    started: Instant,
+}
+
+impl Stage {
+    pub fn start(name: &str) -> Self {
+        emit(PipelineEvent::StageStarted { stage: name.to_string() });
+        Self {
+            name: name.to_string(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Finish the stage with the outcome of `result`, passing it through
+    pub fn finish<T, E>(self, result: Result<T, E>) -> Result<T, E> {
+        emit(PipelineEvent::StageFinished {
+            stage: self.name,
+            success: result.is_ok(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+        });
+        result
+    }
+}
+
+/// Server-sent event stream of everything on the bus
+pub async fn stream() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = bus().subscribe();
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(envelope) => {
+                    let event = Event::default()
+                        .event(envelope.event.name())
+                        .id(envelope.id.to_string())
+                        .json_data(&envelope)
+                        .unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                // A slow subscriber misses events rather than blocking emitters
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+use crate::config::config;
+use crate::llm::Provider;
+
+/// Optional subsystems, switched on or off at startup. The ffmpeg and whisper
+/// routes are always available; everything here needs credentials or costs
+/// money per call.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Features {
+    /// LLM-backed routes: chat, generation, translation, review (FEATURE_LLM)
+    pub llm: bool,
+    /// Publish kit assembly, which also calls the LLM (FEATURE_PUBLISH)
+    pub publish: bool,
+}
+
+impl Features {
+    /// Read flags from the environment. The LLM defaults to on only when the
+    /// configured provider is usable: its key is set, or it is a local
+    /// Ollama server that needs none. A minimal install needs no setup.
+    pub fn from_env() -> Self {
+        let provider = config().llm_provider;
+        let key = match provider {
+            Provider::OpenAI => Some("OPENAI_API_KEY"),
+            Provider::Anthropic => Some("ANTHROPIC_API_KEY"),
+            Provider::Ollama => None,
+        };
+        let has_key = key.is_none_or(|key| std::env::var(key).is_ok_and(|value| !value.is_empty()));
+        let llm = flag("FEATURE_LLM", has_key);
+
+        if let Some(key) = key.filter(|_| llm && !has_key) {
+            tracing::warn!("FEATURE_LLM is enabled but {} is not set; LLM routes will fail", key);
+        }
+
+        Self {
+            llm,
+            publish: llm && flag("FEATURE_PUBLISH", true),
+        }
+    }
+}
+
+fn flag(name: &str, default: bool) -> bool {
+    match std::env::var(name).map(|v| v.to_lowercase()) {
+        Ok(v) if matches!(v.as_str(), "1" | "true" | "on" | "yes") => true,
+        Ok(v) if matches!(v.as_str(), "0" | "false" | "off" | "no") => false,
+        Ok(v) => {
+            tracing::warn!("Ignoring invalid value {:?} for {}", v, name);
+            default
+        }
+        Err(_) => default,
+    }
+}
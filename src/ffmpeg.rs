@@ -1,29 +1,630 @@
-use std::process::{Command};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Output;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
+use tempfile::NamedTempFile;
+use crate::analysis::{self, Loudness, Waveform};
+use crate::cache::ArtifactCache;
+use crate::config::config;
+use crate::error::ProcessorError;
+use crate::events;
+use crate::process;
 
 pub struct FFmpegClient {
     binary_path: String,
+    probe_path: String,
     input_file: Option<PathBuf>,
     output_dir: Option<PathBuf>,
     chunk_duration: Option<Duration>,
+    output_format: OutputFormat,
+    reencode: Option<ReencodeOptions>,
+    image_format: ImageFormat,
+    cache: Option<Arc<ArtifactCache>>,
+    progress: Option<ProgressCallback>,
 }
 
-#[derive(Debug)]
+/// Receives the fraction of the input processed so far, in [0, 1]
+pub type ProgressCallback = Arc<dyn Fn(f64) + Send + Sync>;
+
+/// What ffprobe reports about a media file
+#[derive(Debug, Clone, Serialize)]
 pub struct MediaInfo {
-    pub duration: String,
-    pub bitrate: String,
+    /// Container format as ffprobe names it, e.g. `mov,mp4,m4a,3gp,3g2,mj2`
     pub format: String,
+    pub format_long_name: Option<String>,
+    /// Seconds
+    pub duration: f64,
+    /// Overall bitrate in bits per second
+    pub bitrate: Option<u64>,
+    pub size_bytes: Option<u64>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Frames per second of the first video stream
+    pub fps: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Other,
+}
+
+/// One stream of a media file
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: Option<String>,
+    pub codec_long_name: Option<String>,
+    /// Bits per second, when the container records it per stream
+    pub bitrate: Option<u64>,
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub pixel_format: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub language: Option<String>,
+    /// Cover art embedded in audio files shows up as a one-frame video stream
+    pub attached_picture: bool,
+}
+
+/// `ffprobe -print_format json -show_format -show_streams` output. Numbers
+/// come as strings.
+#[derive(Deserialize)]
+struct ProbeJson {
+    format: ProbeFormat,
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: String,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    size: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    index: u32,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    codec_long_name: Option<String>,
+    bit_rate: Option<String>,
+    duration: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    pix_fmt: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    disposition: HashMap<String, i64>,
+}
+
+/// Noise gate settings and where to capture room tone from
+#[derive(Debug, Clone, Copy)]
+pub struct GateOptions {
+    /// Level below which audio is gated, in dBFS
+    pub threshold_db: f64,
+    /// Start of a stretch of "silence" in the input to use as room tone
+    pub room_tone_start: f64,
+    pub room_tone_duration: f64,
+    /// Gain applied to the looped room tone, in dB
+    pub room_tone_db: f64,
+}
+
+impl GateOptions {
+    /// Longest room tone capture, in seconds
+    pub const MAX_ROOM_TONE: f64 = 30.0;
+
+    pub fn validate(&self) -> Result<(), ProcessorError> {
+        if !(-100.0..=0.0).contains(&self.threshold_db) {
+            return Err(ProcessorError::BadRequest("threshold_db must be between -100 and 0".to_string()));
+        }
+        if !(self.room_tone_start.is_finite() && self.room_tone_start >= 0.0) {
+            return Err(ProcessorError::BadRequest("room_tone_start must not be negative".to_string()));
+        }
+        if !(self.room_tone_duration > 0.0 && self.room_tone_duration <= Self::MAX_ROOM_TONE) {
+            return Err(ProcessorError::BadRequest(format!(
+                "room_tone_duration must be more than 0 and at most {} seconds",
+                Self::MAX_ROOM_TONE
+            )));
+        }
+        if !(-60.0..=20.0).contains(&self.room_tone_db) {
+            return Err(ProcessorError::BadRequest("room_tone_db must be between -60 and 20".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Thresholds for finding a black or frozen intro
+#[derive(Debug, Clone, Copy)]
+pub struct IntroOptions {
+    /// Only the first `scan` seconds of the video are analysed
+    pub scan: f64,
+    /// Shortest black stretch, in seconds, that counts
+    pub min_black: f64,
+    /// Shortest frozen stretch (static slate), in seconds, that counts
+    pub min_static: f64,
+    /// Largest gap, in seconds, allowed between intro stretches
+    pub max_gap: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalKind {
+    Black,
+    Static,
+}
+
+/// A stretch of black or frozen frames
+#[derive(Debug, Clone, Serialize)]
+pub struct VisualInterval {
+    pub kind: IntervalKind,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// A stretch of audio below the silence threshold, in seconds
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SilenceRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl SilenceRange {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Leading black frames and static slates, if any
+#[derive(Debug, Clone, Serialize)]
+pub struct Intro {
+    /// Where the real content starts, in seconds (0 when there is no intro)
+    pub end: f64,
+    pub intervals: Vec<VisualInterval>,
+}
+
+/// How the still image behaves in a rendered video
+#[derive(Debug, Clone, Copy)]
+pub enum ImageMotion {
+    Static,
+    /// Slow zoom and pan across the image over the length of the track
+    KenBurns,
+}
+
+/// Containers the split operations can write. Parts are stream-copied when
+/// the input is already in this format and re-encoded otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Mp3,
+    Wav,
+    M4a,
+    Flac,
+    Ogg,
+    Mp4,
+    Mov,
+}
+
+impl OutputFormat {
+    const ALL: [OutputFormat; 7] = [
+        OutputFormat::Mp3,
+        OutputFormat::Wav,
+        OutputFormat::M4a,
+        OutputFormat::Flac,
+        OutputFormat::Ogg,
+        OutputFormat::Mp4,
+        OutputFormat::Mov,
+    ];
+
+    /// The format a file is already in, judging by its extension
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        Self::ALL.into_iter().find(|format| format.extension() == extension)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::Wav => "wav",
+            OutputFormat::M4a => "m4a",
+            OutputFormat::Flac => "flac",
+            OutputFormat::Ogg => "ogg",
+            OutputFormat::Mp4 => "mp4",
+            OutputFormat::Mov => "mov",
+        }
+    }
+
+    /// Whether the container carries video
+    pub fn is_video(&self) -> bool {
+        matches!(self, OutputFormat::Mp4 | OutputFormat::Mov)
+    }
+
+    /// Audio encoder settings for re-encoding into this format: `kbps`, or
+    /// 192k for lossy formats when unset. Lossless formats ignore the bitrate.
+    fn audio_encoder_args(&self, kbps: Option<u32>) -> Vec<String> {
+        let codec = match self {
+            OutputFormat::Mp3 => "libmp3lame",
+            OutputFormat::Wav => "pcm_s16le",
+            OutputFormat::Flac => "flac",
+            OutputFormat::Ogg => "libvorbis",
+            OutputFormat::M4a | OutputFormat::Mp4 | OutputFormat::Mov => "aac",
+        };
+        let mut args = vec!["-c:a".to_string(), codec.to_string()];
+        if !matches!(self, OutputFormat::Wav | OutputFormat::Flac) {
+            args.extend(["-b:a".to_string(), format!("{}k", kbps.unwrap_or(192))]);
+        }
+        args
+    }
+}
+
+/// True peak ceiling `normalize_loudness` keeps under, leaving headroom for
+/// lossy re-encoding on upload
+const NORMALIZE_TRUE_PEAK: f64 = -1.5;
+/// Loudness range `normalize_loudness` allows before it has to compress
+const NORMALIZE_RANGE: f64 = 11.0;
+
+/// The result of `normalize_loudness`
+#[derive(Debug, Serialize)]
+pub struct Normalization {
+    pub output: PathBuf,
+    pub target_lufs: f64,
+    /// The input as measured by the first pass
+    pub before: Loudness,
+    /// The output as loudnorm measured it while writing
+    pub after: Loudness,
+}
+
+/// Video transitions `merge_chunks` can put between chunks, named as
+/// ffmpeg's xfade filter names them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    /// Cross-fade from one picture to the next
+    #[default]
+    Fade,
+    /// Pixel-by-pixel random dissolve
+    Dissolve,
+    /// Fade through black
+    Fadeblack,
+    /// Fade through white
+    Fadewhite,
+    Wipeleft,
+    Wiperight,
+    Slideleft,
+    Slideright,
+}
+
+impl Transition {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transition::Fade => "fade",
+            Transition::Dissolve => "dissolve",
+            Transition::Fadeblack => "fadeblack",
+            Transition::Fadewhite => "fadewhite",
+            Transition::Wipeleft => "wipeleft",
+            Transition::Wiperight => "wiperight",
+            Transition::Slideleft => "slideleft",
+            Transition::Slideright => "slideright",
+        }
+    }
+}
+
+/// Longest overlap `merge_chunks` accepts between two chunks, in seconds
+pub const MAX_CROSSFADE: f64 = 10.0;
+
+/// How `merge_chunks` joins each chunk to the next. The default is a hard
+/// cut, which stream-copies; anything else re-encodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MergeOptions {
+    /// Seconds each chunk overlaps the next: its audio crossfades into the
+    /// next one's and, for video, its picture transitions into the next
+    pub crossfade: f64,
+    /// Video transition for the overlap (`Fade` when unset); only for video
+    pub transition: Option<Transition>,
+}
+
+impl MergeOptions {
+    pub fn is_hard_cut(&self) -> bool {
+        self.crossfade <= 0.0
+    }
+}
+
+/// Name of the playlist `to_hls` writes
+pub const HLS_PLAYLIST: &str = "index.m3u8";
+
+/// The result of `to_hls`: a VOD playlist and the segments it lists, in order
+#[derive(Debug)]
+pub struct HlsOutput {
+    pub playlist: PathBuf,
+    pub segments: Vec<PathBuf>,
+}
+
+/// Still image formats for extracted frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Jpg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// A frame where the picture differs markedly from the one before it
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SceneChange {
+    pub time: f64,
+    /// ffmpeg's scene score in [0, 1]; higher is a bigger change
+    pub score: f64,
+}
+
+/// x264 speed presets, fastest first
+pub const X264_PRESETS: &[&str] = &[
+    "ultrafast", "superfast", "veryfast", "faster", "fast",
+    "medium", "slow", "slower", "veryslow",
+];
+
+/// Settings for frame-accurate cuts. Stream copy can only cut on keyframes,
+/// so precise cuts re-encode video with x264 and audio with AAC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReencodeOptions {
+    /// One of `X264_PRESETS`
+    pub preset: String,
+    /// x264 constant rate factor, 0 (lossless) to 51; lower is better quality
+    pub crf: u8,
+}
+
+impl Default for ReencodeOptions {
+    fn default() -> Self {
+        Self { preset: "veryfast".to_string(), crf: 18 }
+    }
+}
+
+impl ReencodeOptions {
+    pub fn validate(&self) -> Result<(), ProcessorError> {
+        if !X264_PRESETS.contains(&self.preset.as_str()) {
+            return Err(ProcessorError::BadRequest(format!(
+                "Unknown preset {}; expected one of {}",
+                self.preset,
+                X264_PRESETS.join(", ")
+            )));
+        }
+        if self.crf > 51 {
+            return Err(ProcessorError::BadRequest("crf must be between 0 and 51".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Quote a path for use inside a single-quoted filter option value
+fn filter_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace(':', "\\:")
+        .replace('\'', "'\\\\\\''")
+}
+
+/// Codec arguments for cutting `input` into `output`. With re-encoding on,
+/// video containers get x264/AAC at the given preset and CRF. Otherwise it's
+/// `-c copy` when `output` has the same extension as `input`, or nothing, so
+/// ffmpeg picks the output container's default encoder.
+fn codec_args(input: &Path, output: &Path, reencode: Option<&ReencodeOptions>) -> Vec<String> {
+    let output_format = OutputFormat::of(output);
+    if let Some(options) = reencode {
+        if !output_format.is_some_and(|format| format.is_video()) {
+            return Vec::new();
+        }
+        return [
+            "-c:v", "libx264",
+            "-preset", &options.preset,
+            "-crf", &options.crf.to_string(),
+            "-c:a", "aac",
+            "-b:a", "192k",
+        ].iter().map(|s| s.to_string()).collect();
+    }
+
+    let extension = |path: &Path| path.extension().and_then(|s| s.to_str()).map(|s| s.to_lowercase());
+    if extension(input) == extension(output) {
+        vec!["-c".to_string(), "copy".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Encodings `extract_audio` can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioCodec {
+    Mp3,
+    /// 16-bit stereo WAV
+    Wav,
+    /// 16-bit mono WAV, the input whisper expects (at 16 kHz)
+    Pcm,
+}
+
+impl AudioCodec {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mp3" => Some(AudioCodec::Mp3),
+            "wav" => Some(AudioCodec::Wav),
+            "pcm" => Some(AudioCodec::Pcm),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Wav => "wav",
+            AudioCodec::Pcm => "pcm",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Wav | AudioCodec::Pcm => "wav",
+        }
+    }
+
+    /// Sample rate used when none is given
+    pub fn default_sample_rate(&self) -> u32 {
+        match self {
+            AudioCodec::Pcm => 16000,
+            AudioCodec::Mp3 | AudioCodec::Wav => 44100,
+        }
+    }
+
+    fn encoder_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            AudioCodec::Mp3 => &["-c:a", "libmp3lame", "-q:a", "2"],
+            AudioCodec::Wav => &["-c:a", "pcm_s16le"],
+            AudioCodec::Pcm => &["-c:a", "pcm_s16le", "-ac", "1"],
+        };
+        args.iter().map(|a| a.to_string()).collect()
+    }
+}
+
+impl MediaInfo {
+    /// Parse ffprobe's JSON report
+    pub fn from_ffprobe(json: &str) -> Result<Self, ProcessorError> {
+        let probe: ProbeJson = serde_json::from_str(json)
+            .map_err(|e| ProcessorError::FFmpegError(format!("unreadable ffprobe output: {}", e)))?;
+
+        let streams: Vec<StreamInfo> = probe.streams.into_iter().map(StreamInfo::from).collect();
+        // Still images and streams without a container duration fall back to
+        // their longest stream
+        let duration = number::<f64>(&probe.format.duration)
+            .or_else(|| streams.iter().filter_map(|stream| stream.duration).reduce(f64::max))
+            .unwrap_or(0.0);
+
+        let video = streams.iter().find(|stream| stream.kind == StreamKind::Video && !stream.attached_picture);
+        let audio = streams.iter().find(|stream| stream.kind == StreamKind::Audio);
+
+        Ok(Self {
+            format: probe.format.format_name,
+            format_long_name: probe.format.format_long_name,
+            duration,
+            bitrate: number(&probe.format.bit_rate),
+            size_bytes: number(&probe.format.size),
+            video_codec: video.and_then(|stream| stream.codec.clone()),
+            audio_codec: audio.and_then(|stream| stream.codec.clone()),
+            width: video.and_then(|stream| stream.width),
+            height: video.and_then(|stream| stream.height),
+            fps: video.and_then(|stream| stream.fps),
+            sample_rate: audio.and_then(|stream| stream.sample_rate),
+            channels: audio.and_then(|stream| stream.channels),
+            streams,
+        })
+    }
+
+    /// The duration, or an error when ffprobe couldn't tell
+    pub fn known_duration(&self) -> Result<f64, ProcessorError> {
+        if self.duration > 0.0 {
+            Ok(self.duration)
+        } else {
+            Err(ProcessorError::FFmpegError("Could not determine media duration".to_string()))
+        }
+    }
+
+    /// Whether there is a picture to work with; embedded cover art doesn't count
+    pub fn has_video(&self) -> bool {
+        self.video_codec.is_some()
+    }
+}
+
+impl From<ProbeStream> for StreamInfo {
+    fn from(stream: ProbeStream) -> Self {
+        let kind = match stream.codec_type.as_deref() {
+            Some("video") => StreamKind::Video,
+            Some("audio") => StreamKind::Audio,
+            Some("subtitle") => StreamKind::Subtitle,
+            Some("data") => StreamKind::Data,
+            _ => StreamKind::Other,
+        };
+        let fps = stream.avg_frame_rate
+            .as_deref()
+            .and_then(frame_rate)
+            .or_else(|| stream.r_frame_rate.as_deref().and_then(frame_rate));
+
+        Self {
+            index: stream.index,
+            kind,
+            codec: stream.codec_name,
+            codec_long_name: stream.codec_long_name,
+            bitrate: number(&stream.bit_rate),
+            duration: number(&stream.duration),
+            width: stream.width,
+            height: stream.height,
+            fps: if kind == StreamKind::Video { fps } else { None },
+            pixel_format: stream.pix_fmt,
+            sample_rate: number(&stream.sample_rate),
+            channels: stream.channels,
+            channel_layout: stream.channel_layout,
+            language: stream.tags.get("language").cloned(),
+            attached_picture: stream.disposition.get("attached_pic") == Some(&1),
+        }
+    }
+}
+
+/// One of ffprobe's stringly-typed numbers; `N/A` and friends are `None`
+fn number<T: std::str::FromStr>(value: &Option<String>) -> Option<T> {
+    value.as_deref().and_then(|v| v.trim().parse().ok())
+}
+
+/// A rational frame rate like `30000/1001`; `0/0` means unknown
+fn frame_rate(rate: &str) -> Option<f64> {
+    let (numerator, denominator) = rate.split_once('/')?;
+    let numerator: f64 = numerator.parse().ok()?;
+    let denominator: f64 = denominator.parse().ok()?;
+    (numerator > 0.0 && denominator > 0.0).then(|| numerator / denominator)
 }
 
 impl FFmpegClient {
-    /// Create a new FFmpeg client
+    /// Create a new FFmpeg client using the configured binaries
     pub fn new() -> Self {
         Self {
-            binary_path: "ffmpeg".to_string(),
+            binary_path: config().ffmpeg_path.clone(),
+            probe_path: config().ffprobe_path.clone(),
             input_file: None,
             output_dir: None,
             chunk_duration: None,
+            output_format: OutputFormat::default(),
+            reencode: None,
+            image_format: ImageFormat::default(),
+            cache: None,
+            progress: None,
         }
     }
 
@@ -45,59 +646,128 @@ impl FFmpegClient {
         self
     }
 
-    /// Get media info
-    pub fn get_info(&self) -> Result<MediaInfo, std::io::Error> {
-        let input = self.input_file.as_ref()
-            .expect("Input file not set");
+    /// Set the container split parts are written in (mp3 by default)
+    pub fn with_output_format(&mut self, format: OutputFormat) -> &mut Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Re-encode when cutting, so cuts land on the exact frame instead of
+    /// the nearest keyframe
+    pub fn with_reencode(&mut self, options: ReencodeOptions) -> &mut Self {
+        self.reencode = Some(options);
+        self
+    }
 
-        let output = Command::new(&self.binary_path)
-            .arg("-i")
-            .arg(input)
-            .output()?;
+    /// Set the image format extracted frames are written in (jpg by default)
+    pub fn with_image_format(&mut self, format: ImageFormat) -> &mut Self {
+        self.image_format = format;
+        self
+    }
 
-        // FFmpeg returns info on stderr with status 1 (by design)
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        
-        // Parse the output into our MediaInfo struct
-        let info = MediaInfo {
-            duration: stderr
-                .lines()
-                .find(|line| line.contains("Duration:"))
-                .and_then(|line| line.split("Duration: ").nth(1))
-                .and_then(|time| time.split(',').next())
-                .unwrap_or("unknown")
-                .to_string(),
-            bitrate: stderr
-                .lines()
-                .find(|line| line.contains("bitrate:"))
-                .and_then(|line| line.split("bitrate: ").nth(1))
-                .unwrap_or("unknown")
-                .to_string(),
-            format: stderr
-                .lines()
-                .find(|line| line.contains("Input #0,"))
-                .and_then(|line| line.split("Input #0, ").nth(1))
-                .and_then(|s| s.split(',').next())
-                .unwrap_or("unknown")
-                .to_string(),
+    /// Consult an artifact cache before running operations
+    pub fn with_cache(&mut self, cache: Arc<ArtifactCache>) -> &mut Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Report how far through the input each ffmpeg run has got, read
+    /// from ffmpeg's `-progress` output
+    pub fn with_progress(&mut self, callback: ProgressCallback) -> &mut Self {
+        self.progress = Some(callback);
+        self
+    }
+
+    fn require_input(&self) -> Result<&PathBuf, ProcessorError> {
+        self.input_file.as_ref()
+            .ok_or_else(|| ProcessorError::BadRequest("Input file not set".to_string()))
+    }
+
+    fn require_output_dir(&self) -> Result<&PathBuf, ProcessorError> {
+        self.output_dir.as_ref()
+            .ok_or_else(|| ProcessorError::BadRequest("Output directory not set".to_string()))
+    }
+
+    /// Run `operation` through the artifact cache when one is configured.
+    /// On a hit the cached artifacts are restored into `output_dir` instead
+    /// of invoking ffmpeg.
+    fn cached<F>(
+        &self,
+        inputs: &[&Path],
+        operation: &str,
+        params: &str,
+        output_dir: &Path,
+        run: F,
+    ) -> Result<Vec<PathBuf>, ProcessorError>
+    where
+        F: FnOnce() -> Result<Vec<PathBuf>, ProcessorError>,
+    {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return run(),
         };
 
-        Ok(info)
+        let key = cache.key(inputs, operation, params)?;
+        if let Some(cached) = cache.get(&key) {
+            tracing::info!("Cache hit for {} ({})", operation, key);
+            let restored = cache.restore(&cached, output_dir)?;
+            for artifact in &restored {
+                events::artifact(artifact, true);
+            }
+            return Ok(restored);
+        }
+
+        let artifacts = run()?;
+        cache.put(&key, operation, &artifacts)?;
+        Ok(artifacts)
+    }
+
+    /// Probe the input with ffprobe
+    pub fn get_info(&self) -> Result<MediaInfo, ProcessorError> {
+        let input = self.require_input()?;
+
+        let args: Vec<String> = [
+            "-v", "error",
+            "-print_format", "json",
+            "-show_format",
+            "-show_streams",
+        ].iter().map(|s| s.to_string()).chain([input.to_string_lossy().to_string()]).collect();
+        let output = process::run(&self.probe_path, &args)
+            .map_err(|e| process::spawn_error(&self.probe_path, e, ProcessorError::FFmpegError))?;
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        MediaInfo::from_ffprobe(&String::from_utf8_lossy(&output.stdout))
     }
 
-    pub fn split_into_chunks(&self) -> Result<Vec<PathBuf>, std::io::Error> {
-        let input = self.input_file.as_ref()
-            .expect("Input file not set");
-        let output_dir = self.output_dir.as_ref()
-            .expect("Output directory not set");
+    pub fn split_into_chunks(&self) -> Result<Vec<PathBuf>, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
         let duration = self.chunk_duration
-            .expect("Chunk duration not set");
+            .ok_or_else(|| ProcessorError::BadRequest("Chunk duration not set".to_string()))?;
 
+        self.cached(
+            &[input],
+            "split",
+            &format!(
+                "chunk_duration={},format={},reencode={:?}",
+                duration.as_secs(),
+                self.output_format.extension(),
+                self.reencode
+            ),
+            output_dir,
+            || self.run_split(input, output_dir, duration),
+        )
+    }
+
+    fn run_split(&self, input: &Path, output_dir: &Path, duration: Duration) -> Result<Vec<PathBuf>, ProcessorError> {
         // Create output directory if it doesn't exist
         std::fs::create_dir_all(output_dir)?;
 
         // Create the output path first so it lives long enough
-        let output_path = output_dir.join("chunk_%03d.mp3");
+        let extension = self.output_format.extension();
+        let output_path = output_dir.join(format!("chunk_%03d.{}", extension));
         let output_str = output_path.to_str()
             .expect("Invalid output path");
 
@@ -108,28 +778,17 @@ impl FFmpegClient {
         // Store duration string so it lives long enough
         let duration_str = duration.as_secs().to_string();
 
-        let args = vec![
+        let mut args: Vec<String> = [
             "-i",
             input_str,
             "-f", "segment",
             "-segment_time", &duration_str,
-            "-c", "copy",
             "-reset_timestamps", "1",
-            output_str,
-        ];
+        ].iter().map(|s| s.to_string()).collect();
+        args.extend(codec_args(input, &output_path, self.reencode.as_ref()));
+        args.push(output_str.to_string());
 
-        println!("Running command: ffmpeg {}", args.join(" "));
-
-        let output = Command::new(&self.binary_path)
-            .args(&args)
-            .output()?;
-
-        if !output.status.success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                String::from_utf8_lossy(&output.stderr).to_string()
-            ));
-        }
+        self.run(&args)?;
 
         // Get list of created chunks, sorted
         let mut chunks: Vec<PathBuf> = std::fs::read_dir(output_dir)?
@@ -137,7 +796,7 @@ impl FFmpegClient {
             .map(|entry| entry.path())
             .filter(|path| {
                 // Only include numbered chunks, not the pattern file
-                path.extension().and_then(|s| s.to_str()) == Some("mp3") && 
+                path.extension().and_then(|s| s.to_str()) == Some(extension) && 
                 path.file_name()
                     .and_then(|s| s.to_str())
                     .map(|s| !s.contains("%03d"))
@@ -149,44 +808,187 @@ impl FFmpegClient {
         chunks.sort();
 
         if chunks.is_empty() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "No chunks were created".to_string()
-            ));
+            return Err(ProcessorError::FFmpegError("No chunks were created".to_string()));
+        }
+
+        for chunk in &chunks {
+            events::artifact(chunk, false);
         }
 
         Ok(chunks)
     }
 
-    /// Merge multiple audio chunks into a single file
-    pub fn merge_chunks(&self, chunks: Vec<PathBuf>, output_path: PathBuf) -> Result<PathBuf, std::io::Error> {
-        // Create a temporary concat file
-        let concat_file = self.create_concat_file(&chunks)?;
+    /// Merge multiple chunks into a single file, back to back or overlapped
+    /// as `options` say
+    pub fn merge_chunks(
+        &self,
+        chunks: Vec<PathBuf>,
+        output_path: PathBuf,
+        options: &MergeOptions,
+    ) -> Result<PathBuf, ProcessorError> {
+        let inputs: Vec<&Path> = chunks.iter().map(|p| p.as_path()).collect();
+        let output_dir = output_path.parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let output_name = output_path.file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut params = format!("output={}", output_name);
+        if !options.is_hard_cut() {
+            params.push_str(&format!(
+                ",crossfade={},transition={}",
+                options.crossfade,
+                options.transition.unwrap_or_default().name()
+            ));
+        }
+
+        let merged = self.cached(
+            &inputs,
+            "merge",
+            &params,
+            &output_dir,
+            || {
+                if options.is_hard_cut() || chunks.len() < 2 {
+                    self.run_merge(&chunks, &output_path).map(|p| vec![p])
+                } else {
+                    self.run_overlapped_merge(&chunks, &output_path, options).map(|p| vec![p])
+                }
+            },
+        )?;
+
+        Ok(merged.into_iter().next().unwrap_or(output_path))
+    }
+
+    fn run_merge(&self, chunks: &[PathBuf], output_path: &Path) -> Result<PathBuf, ProcessorError> {
+        // Create a temporary concat file, deleted when it goes out of scope
+        let concat_file = self.create_concat_file(chunks)?;
         
         // Run FFmpeg concat command
-        let output = Command::new(&self.binary_path)
-            .args(&[
-                "-f", "concat",           // Use concat demuxer
-                "-safe", "0",             // Allow absolute paths
-                "-i", concat_file.to_str().unwrap(),
-                "-c", "copy",             // Copy codec (no re-encoding)
-                output_path.to_str().unwrap()
-            ])
-            .output()?;
+        self.run(&[
+            "-y".to_string(),                                 // Replace an earlier merge
+            "-f".to_string(), "concat".to_string(),           // Use concat demuxer
+            "-safe".to_string(), "0".to_string(),             // Allow absolute paths
+            "-i".to_string(), concat_file.path().to_str().unwrap().to_string(),
+            "-c".to_string(), "copy".to_string(),             // Copy codec (no re-encoding)
+            output_path.to_str().unwrap().to_string(),
+        ])?;
 
-        if !output.status.success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                String::from_utf8_lossy(&output.stderr).to_string()
-            ));
+        Ok(produced(output_path))
+    }
+
+    /// Join chunks so each one's last `crossfade` seconds overlap the next
+    /// one's first: audio through acrossfade, video through xfade. The chunks
+    /// must all be video or all audio; video is re-encoded with x264 (the
+    /// client's re-encode settings, or the defaults).
+    fn run_overlapped_merge(
+        &self,
+        chunks: &[PathBuf],
+        output_path: &Path,
+        options: &MergeOptions,
+    ) -> Result<PathBuf, ProcessorError> {
+        let crossfade = options.crossfade;
+        if !crossfade.is_finite() || crossfade > MAX_CROSSFADE {
+            return Err(ProcessorError::BadRequest(format!(
+                "crossfade must be between 0 and {} seconds",
+                MAX_CROSSFADE
+            )));
+        }
+
+        let infos = chunks
+            .iter()
+            .map(|chunk| FFmpegClient::new().with_input(chunk).get_info())
+            .collect::<Result<Vec<_>, _>>()?;
+        let video = infos.iter().all(MediaInfo::has_video);
+        if !video && infos.iter().any(MediaInfo::has_video) {
+            return Err(ProcessorError::BadRequest("Can't crossfade a mix of video and audio-only chunks".to_string()));
+        }
+        if !video && options.transition.is_some() {
+            return Err(ProcessorError::BadRequest("Transitions need video chunks".to_string()));
+        }
+        let audio = infos.iter().all(|info| info.audio_codec.is_some());
+
+        // Inner chunks lose `crossfade` seconds at both ends, so they must be
+        // longer than both overlaps together
+        let mut durations = Vec::with_capacity(infos.len());
+        for (i, (chunk, info)) in chunks.iter().zip(&infos).enumerate() {
+            let duration = info.known_duration()?;
+            let overlaps = if i == 0 || i == chunks.len() - 1 { 1.0 } else { 2.0 };
+            if duration <= crossfade * overlaps {
+                return Err(ProcessorError::BadRequest(format!(
+                    "Crossfade of {}s is too long for {:?} ({:.2}s)",
+                    crossfade,
+                    chunk.file_name().unwrap_or_default(),
+                    duration
+                )));
+            }
+            durations.push(duration);
         }
 
-        Ok(output_path)
+        let mut filters = Vec::new();
+        let last = chunks.len() - 1;
+        if video {
+            let transition = options.transition.unwrap_or_default();
+            for i in 0..chunks.len() {
+                filters.push(format!("[{i}:v]settb=AVTB,setpts=PTS-STARTPTS,format=yuv420p[v{i}in]"));
+            }
+            // Each transition starts `crossfade` before the joined video so far ends
+            let mut previous = "v0in".to_string();
+            let mut offset = 0.0;
+            for i in 1..chunks.len() {
+                offset += durations[i - 1] - crossfade;
+                let label = if i == last { "v".to_string() } else { format!("v{}", i) };
+                filters.push(format!(
+                    "[{previous}][v{i}in]xfade=transition={}:duration={crossfade}:offset={offset:.3}[{label}]",
+                    transition.name()
+                ));
+                previous = label;
+            }
+        }
+        if audio {
+            let mut previous = "0:a".to_string();
+            for i in 1..chunks.len() {
+                let label = if i == last { "a".to_string() } else { format!("a{}", i) };
+                filters.push(format!("[{previous}][{i}:a]acrossfade=d={crossfade}:c1=tri:c2=tri[{label}]"));
+                previous = label;
+            }
+        }
+
+        let mut args = vec!["-y".to_string()];
+        for chunk in chunks {
+            args.extend(["-i".to_string(), chunk.to_str().unwrap().to_string()]);
+        }
+        args.extend(["-filter_complex".to_string(), filters.join(";")]);
+        if video {
+            args.extend(["-map".to_string(), "[v]".to_string()]);
+        }
+        if audio {
+            args.extend(["-map".to_string(), "[a]".to_string()]);
+        }
+        if video {
+            let reencode = self.reencode.clone().unwrap_or_default();
+            args.extend([
+                "-c:v".to_string(), "libx264".to_string(),
+                "-preset".to_string(), reencode.preset,
+                "-crf".to_string(), reencode.crf.to_string(),
+                "-c:a".to_string(), "aac".to_string(),
+                "-b:a".to_string(), "192k".to_string(),
+            ]);
+        } else if let Some(format) = OutputFormat::of(output_path) {
+            args.extend(format.audio_encoder_args(None));
+        }
+        args.push(output_path.to_str().unwrap().to_string());
+
+        tracing::info!("Merging {} chunks with {}s crossfades", chunks.len(), crossfade);
+        self.run(&args)?;
+        Ok(produced(output_path))
     }
 
-    /// Creates a temporary file listing chunks to concatenate
-    fn create_concat_file(&self, chunks: &[PathBuf]) -> Result<PathBuf, std::io::Error> {
-        let concat_file = std::env::temp_dir().join("concat.txt");
+    /// Creates a temporary file listing chunks to concatenate, uniquely
+    /// named so concurrent merges can't overwrite each other's list
+    fn create_concat_file(&self, chunks: &[PathBuf]) -> Result<NamedTempFile, ProcessorError> {
+        let concat_file = tempfile::Builder::new().prefix("concat-").suffix(".txt").tempfile()?;
         let mut content = String::new();
         
         // Format required by FFmpeg concat demuxer
@@ -196,36 +998,85 @@ impl FFmpegClient {
             ));
         }
         
-        std::fs::write(&concat_file, content)?;
+        std::fs::write(concat_file.path(), content)?;
         Ok(concat_file)
     }
 
-    /// Split audio into three chunks based on a selected region
+    /// Split the input into three chunks based on a selected region, written
+    /// in the configured output format. Cuts are stream-copied (so land on
+    /// keyframes in video) unless re-encoding is on.
     /// Returns paths to the three resulting chunks:
     /// 1. Before selection (0 to start)
     /// 2. Selected region (start to end)
     /// 3. After selection (end to file_end)
-    pub fn split_at_region(&self, start_time: f64, end_time: f64) -> Result<Vec<PathBuf>, std::io::Error> {
-        let input = self.input_file.as_ref()
-            .expect("Input file not set");
-        let output_dir = self.output_dir.as_ref()
-            .expect("Output directory not set");
+    pub fn split_at_region(&self, start_time: f64, end_time: f64) -> Result<Vec<PathBuf>, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+
+        self.cached(
+            &[input],
+            "split_region",
+            &format!(
+                "start={},end={},format={},reencode={:?}",
+                start_time,
+                end_time,
+                self.output_format.extension(),
+                self.reencode
+            ),
+            output_dir,
+            || self.run_split_region(input, output_dir, start_time, end_time),
+        )
+    }
 
+    fn run_split_region(&self, input: &Path, output_dir: &Path, start_time: f64, end_time: f64) -> Result<Vec<PathBuf>, ProcessorError> {
         // Create three chunks:
         // 1. From start of file to start_time
-        let chunk1 = self.extract_chunk(input, 0.0, start_time, output_dir.join("chunk_before.mp3"))?;
+        let extension = self.output_format.extension();
+        let chunk1 = self.extract_chunk(input, 0.0, start_time, output_dir.join(format!("chunk_before.{}", extension)))?;
         
         // 2. From start_time to end_time (selected region)
-        let chunk2 = self.extract_chunk(input, start_time, end_time, output_dir.join("chunk_selected.mp3"))?;
+        let chunk2 = self.extract_chunk(input, start_time, end_time, output_dir.join(format!("chunk_selected.{}", extension)))?;
         
         // 3. From end_time to end of file
-        let chunk3 = self.extract_chunk(input, end_time, -1.0, output_dir.join("chunk_after.mp3"))?;
+        let chunk3 = self.extract_chunk(input, end_time, -1.0, output_dir.join(format!("chunk_after.{}", extension)))?;
 
         Ok(vec![chunk1, chunk2, chunk3])
     }
 
+    /// Cut the input at each of `points` (seconds), producing one file per part
+    /// named `<stem>_partNN.<ext>` in the output directory
+    pub fn split_at_points(&self, points: &[f64]) -> Result<Vec<PathBuf>, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+        std::fs::create_dir_all(output_dir)?;
+
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("part");
+        let extension = input.extension().and_then(|s| s.to_str()).unwrap_or("mp3");
+
+        // Part boundaries: 0, each cut point, then -1 for "until the end"
+        let mut bounds = vec![0.0];
+        bounds.extend_from_slice(points);
+        bounds.push(-1.0);
+
+        bounds
+            .windows(2)
+            .enumerate()
+            .map(|(i, range)| {
+                let output = output_dir.join(format!("{}_part{:02}.{}", stem, i + 1, extension));
+                self.extract_chunk(input, range[0], range[1], output)
+            })
+            .collect()
+    }
+
+    /// Cut `start..end` seconds of the input into `output`. Set re-encoding
+    /// for frame-accurate cuts.
+    pub fn extract_clip(&self, start: f64, end: f64, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        self.extract_chunk(input, start, end, output.to_path_buf())
+    }
+
     /// Helper method to extract a portion of audio
-    fn extract_chunk(&self, input: &PathBuf, start: f64, end: f64, output: PathBuf) -> Result<PathBuf, std::io::Error> {
+    fn extract_chunk(&self, input: &Path, start: f64, end: f64, output: PathBuf) -> Result<PathBuf, ProcessorError> {
         // Create string values that live long enough
         let start_str = start.to_string();
         let input_str = input.to_str().unwrap();
@@ -233,6 +1084,7 @@ impl FFmpegClient {
         
         // Create all arguments as a Vec<String> first
         let mut args = vec![
+            "-y".to_string(),
             "-i".to_string(),
             input_str.to_string(),
             "-ss".to_string(),
@@ -249,26 +1101,1050 @@ impl FFmpegClient {
         }
 
         // Add output arguments
-        args.extend_from_slice(&[
-            "-c".to_string(),
-            "copy".to_string(),
-            output_str.to_string(),
+        args.extend(codec_args(input, &output, self.reencode.as_ref()));
+        args.push(output_str.to_string());
+
+        self.run(&args)?;
+
+        Ok(produced(&output))
+    }
+
+    /// Spawn ffmpeg and wait for it. Every ffmpeg invocation goes through here.
+    fn exec(&self, args: &[String]) -> Result<Output, ProcessorError> {
+        process::run(&self.binary_path, args)
+            .map_err(|e| process::spawn_error(&self.binary_path, e, ProcessorError::FFmpegError))
+    }
+
+    /// Run ffmpeg with the given arguments, returning the end of stderr as the error on failure
+    fn run(&self, args: &[String]) -> Result<(), ProcessorError> {
+        tracing::debug!("Running command: ffmpeg {}", args.join(" "));
+
+        let output = match (&self.progress, self.input_file.is_some()) {
+            (Some(callback), true) => self.exec_with_progress(args, callback)?,
+            _ => self.exec(args)?,
+        };
+
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        Ok(())
+    }
+
+    /// Run ffmpeg with `-progress` on stdout, turning its reports into
+    /// fractions of the input's duration
+    fn exec_with_progress(&self, args: &[String], callback: &ProgressCallback) -> Result<Output, ProcessorError> {
+        let total = self.duration_seconds()?;
+        let input = self.input_file.as_deref().unwrap_or(Path::new("")).to_string_lossy().to_string();
+
+        let mut progress_args: Vec<String> = ["-progress", "pipe:1", "-nostats"].iter().map(|s| s.to_string()).collect();
+        progress_args.extend_from_slice(args);
+
+        let mut reported_percent = None;
+        let mut report = |fraction: f64| {
+            let fraction = fraction.clamp(0.0, 1.0);
+            callback(fraction);
+            // Whole percents are plenty for anyone watching the event stream
+            let percent = (fraction * 100.0).floor() as u8;
+            if reported_percent != Some(percent) {
+                reported_percent = Some(percent);
+                events::emit(events::PipelineEvent::Progress { input: input.clone(), percent });
+            }
+        };
+
+        process::run_streaming(&self.binary_path, &progress_args, &mut |line| {
+            match parse_progress_line(line) {
+                Some(ProgressLine::OutTime(seconds)) if total > 0.0 => report(seconds / total),
+                Some(ProgressLine::End) => report(1.0),
+                _ => {}
+            }
+        })
+        .map_err(|e| process::spawn_error(&self.binary_path, e, ProcessorError::FFmpegError))
+    }
+
+    /// Duration of the input file in seconds
+    pub fn duration_seconds(&self) -> Result<f64, ProcessorError> {
+        self.get_info()?.known_duration()
+    }
+
+    /// Process the input voice track with `voice_filter`, mix a looped ambient
+    /// bed underneath it, and let the bed ring out for `fade_out` seconds after
+    /// the voice ends
+    pub fn mix_with_ambient(
+        &self,
+        ambient: &Path,
+        voice_filter: &str,
+        ambient_volume: f64,
+        fade_out: f64,
+        output: &Path,
+    ) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let voice_duration = self.duration_seconds()?;
+
+        let filter = format!(
+            "[0:a]{voice},apad=pad_dur={fade}[voice];\
+            [1:a]volume={volume}[bed];\
+            [voice][bed]amix=inputs=2:duration=first:dropout_transition=0,\
+            afade=t=out:st={start}:d={fade}[out]",
+            voice = voice_filter,
+            fade = fade_out,
+            volume = ambient_volume,
+            start = voice_duration,
+        );
+
+        let args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-stream_loop".to_string(), "-1".to_string(),
+            "-i".to_string(), ambient.to_str().unwrap().to_string(),
+            "-filter_complex".to_string(), filter,
+            "-map".to_string(), "[out]".to_string(),
+            output.to_str().unwrap().to_string(),
+        ];
+
+        self.run(&args)?;
+        Ok(produced(output))
+    }
+
+    /// Render a YouTube-ready 1080p video from a still image and the input audio
+    pub fn still_image_video(&self, image: &Path, motion: ImageMotion, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+
+        let mut args = vec!["-y".to_string()];
+
+        let video_filter = match motion {
+            ImageMotion::Static => {
+                args.extend(["-loop".to_string(), "1".to_string()]);
+                "scale=1920:1080:force_original_aspect_ratio=decrease,\
+                pad=1920:1080:(ow-iw)/2:(oh-ih)/2,format=yuv420p".to_string()
+            }
+            ImageMotion::KenBurns => {
+                // zoompan emits `d` frames per input frame, so feed the image
+                // once and stretch it over the whole track
+                let frames = (self.duration_seconds()? * 30.0).ceil() as u64;
+                format!(
+                    "scale=3840:2160:force_original_aspect_ratio=increase,crop=3840:2160,\
+                    zoompan=z='1+0.15*on/{frames}':x='(iw-iw/zoom)*on/{frames}':y='(ih-ih/zoom)/2':\
+                    d={frames}:s=1920x1080:fps=30,format=yuv420p",
+                    frames = frames.max(1)
+                )
+            }
+        };
+
+        args.extend([
+            "-i".to_string(), image.to_str().unwrap().to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-vf".to_string(), video_filter,
+            "-c:v".to_string(), "libx264".to_string(),
+            "-tune".to_string(), "stillimage".to_string(),
+            "-r".to_string(), "30".to_string(),
+            "-c:a".to_string(), "aac".to_string(),
+            "-b:a".to_string(), "192k".to_string(),
+            "-shortest".to_string(),
+            output.to_str().unwrap().to_string(),
         ]);
 
-        // Convert Vec<String> to Vec<&str> for the Command
-        let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        self.run(&args)?;
+        Ok(produced(output))
+    }
+
+    /// Build a seamless loop of the input track by crossfading its ending into
+    /// its beginning, then repeat it `loops` times. When `max_duration` is set
+    /// the result is cut to that length with a fade-out.
+    pub fn seamless_loop(
+        &self,
+        crossfade: f64,
+        loops: u32,
+        max_duration: Option<f64>,
+        output: &Path,
+    ) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let duration = self.duration_seconds()?;
+
+        if crossfade <= 0.0 || duration <= crossfade * 2.0 {
+            return Err(ProcessorError::BadRequest(format!("Crossfade of {}s is too long for a {:.2}s track", crossfade, duration)));
+        }
+
+        // One loop unit = body + (tail crossfaded into head). Played back to
+        // back, each unit ends exactly where the next one's body begins.
+        // Rendered as WAV: mp3 encoder padding would click at every repeat.
+        // The unit is a temporary file beside the output, removed however
+        // this ends.
+        let unit = tempfile::Builder::new()
+            .prefix(".loop-unit-")
+            .suffix(".wav")
+            .tempfile_in(output.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")))?;
+        let filter = format!(
+            "[0:a]asplit=3[a][b][c];\
+            [a]atrim=0:{cf},asetpts=PTS-STARTPTS[head];\
+            [b]atrim={cf}:{body_end},asetpts=PTS-STARTPTS[body];\
+            [c]atrim={body_end},asetpts=PTS-STARTPTS[tail];\
+            [tail][head]acrossfade=d={cf}:c1=tri:c2=tri[seam];\
+            [body][seam]concat=n=2:v=0:a=1[unit]",
+            cf = crossfade,
+            body_end = duration - crossfade,
+        );
 
-        let command_output = Command::new(&self.binary_path)
-            .args(&args_ref)
-            .output()?;
+        self.run(&[
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-filter_complex".to_string(), filter,
+            "-map".to_string(), "[unit]".to_string(),
+            unit.path().to_str().unwrap().to_string(),
+        ])?;
+
+        let mut args = vec![
+            "-y".to_string(),
+            "-stream_loop".to_string(), loops.saturating_sub(1).to_string(),
+            "-i".to_string(), unit.path().to_str().unwrap().to_string(),
+        ];
+        if let Some(max_duration) = max_duration {
+            let fade = crossfade.min(max_duration / 2.0);
+            args.extend([
+                "-t".to_string(), max_duration.to_string(),
+                "-af".to_string(), format!("afade=t=out:st={}:d={}", max_duration - fade, fade),
+            ]);
+        }
+        args.push(output.to_str().unwrap().to_string());
+
+        self.run(&args)?;
+
+        Ok(produced(output))
+    }
+
+    /// Gate the input's speech and fill the gated gaps with looped room tone
+    /// captured from the input itself, so cleanup doesn't leave digital silence.
+    /// The room tone is ducked by the gated speech, so it only comes up in the gaps.
+    pub fn gate_with_room_tone(&self, options: GateOptions, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        options.validate()?;
+
+        const SAMPLE_RATE: f64 = 48000.0;
+        let filter = format!(
+            "[0:a]aresample={rate},asplit=2[speech][source];\
+            [speech]agate=threshold={threshold}:ratio=10:attack=5:release=150:range=0.001,asplit=2[gated][sidechain];\
+            [source]atrim=start={start}:duration={duration},asetpts=PTS-STARTPTS,\
+            aloop=loop=-1:size={samples},volume={gain}dB[tone];\
+            [tone][sidechain]sidechaincompress=threshold=0.01:ratio=20:attack=5:release=300[fill];\
+            [gated][fill]amix=inputs=2:duration=first:normalize=0[out]",
+            rate = SAMPLE_RATE,
+            threshold = 10f64.powf(options.threshold_db / 20.0),
+            start = options.room_tone_start,
+            duration = options.room_tone_duration,
+            samples = (SAMPLE_RATE * options.room_tone_duration).round() as u64,
+            gain = options.room_tone_db,
+        );
+
+        self.run(&[
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-filter_complex".to_string(), filter,
+            "-map".to_string(), "0:v?".to_string(),
+            "-c:v".to_string(), "copy".to_string(),
+            "-map".to_string(), "[out]".to_string(),
+            output.to_str().unwrap().to_string(),
+        ])?;
+
+        Ok(produced(output))
+    }
+
+    /// Cut the audio between `start` and `end` into an mp3 snippet in the output
+    /// directory. Snippets go through the artifact cache, so each one is only
+    /// encoded once.
+    pub fn snippet(&self, start: f64, end: f64) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+
+        if end <= start {
+            return Err(ProcessorError::BadRequest("Snippet end must be after start".to_string()));
+        }
+
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("snippet");
+        let output = output_dir.join(format!("{}_{:.3}_{:.3}.mp3", stem, start, end));
+
+        let snippets = self.cached(
+            &[input],
+            "snippet",
+            &format!("start={:.3},end={:.3}", start, end),
+            output_dir,
+            || {
+                std::fs::create_dir_all(output_dir)?;
+                self.run(&[
+                    "-y".to_string(),
+                    "-ss".to_string(), start.to_string(),
+                    "-t".to_string(), (end - start).to_string(),
+                    "-i".to_string(), input.to_str().unwrap().to_string(),
+                    "-vn".to_string(),
+                    "-c:a".to_string(), "libmp3lame".to_string(),
+                    "-q:a".to_string(), "4".to_string(),
+                    output.to_str().unwrap().to_string(),
+                ])?;
+                Ok(vec![produced(&output)])
+            },
+        )?;
+
+        Ok(snippets.into_iter().next().unwrap_or(output))
+    }
+
+    /// Insert `clip` into the input at `at` seconds, fading out of the main
+    /// timeline into the clip and back. Video inputs are conformed to 1080p30
+    /// so clips from different sources can be joined.
+    pub fn insert_clip(&self, clip: &Path, at: f64, fade: f64, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let info = self.get_info()?;
+        let duration = info.known_duration()?;
+
+        if at <= 0.0 || at >= duration {
+            return Err(ProcessorError::BadRequest(format!("Insert point {}s is outside the {:.2}s timeline", at, duration)));
+        }
+
+        let mut clip_probe = FFmpegClient::new();
+        let clip_duration = clip_probe.with_input(clip).duration_seconds()?;
+        // The clip fades in and out, and the two must not overlap
+        if fade * 2.0 > clip_duration {
+            return Err(ProcessorError::BadRequest(format!("A {}s fade is too long for the {:.2}s clip", fade, clip_duration)));
+        }
+
+        let audio = "aresample=48000,aformat=channel_layouts=stereo";
+        let mut filter = format!(
+            "[0:a]{audio},asplit=2[a_in][a_in2];\
+            [a_in]atrim=0:{at},asetpts=PTS-STARTPTS,afade=t=out:st={fade_out}:d={fade}[a0];\
+            [1:a]{audio},afade=t=in:d={fade},afade=t=out:st={clip_fade}:d={fade}[a1];\
+            [a_in2]atrim={at},asetpts=PTS-STARTPTS,afade=t=in:d={fade}[a2];",
+            audio = audio,
+            at = at,
+            fade = fade,
+            fade_out = (at - fade).max(0.0),
+            clip_fade = (clip_duration - fade).max(0.0),
+        );
+
+        let mut args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-i".to_string(), clip.to_str().unwrap().to_string(),
+        ];
 
-        if !command_output.status.success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                String::from_utf8_lossy(&command_output.stderr).to_string()
+        if info.has_video() {
+            let video = "scale=1920:1080:force_original_aspect_ratio=decrease,\
+                pad=1920:1080:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30,format=yuv420p";
+            filter.push_str(&format!(
+                "[0:v]{video},split=2[v_in][v_in2];\
+                [v_in]trim=0:{at},setpts=PTS-STARTPTS,fade=t=out:st={fade_out}:d={fade}[v0];\
+                [1:v]{video},fade=t=in:d={fade},fade=t=out:st={clip_fade}:d={fade}[v1];\
+                [v_in2]trim={at},setpts=PTS-STARTPTS,fade=t=in:d={fade}[v2];\
+                [v0][a0][v1][a1][v2][a2]concat=n=3:v=1:a=1[v][a]",
+                video = video,
+                at = at,
+                fade = fade,
+                fade_out = (at - fade).max(0.0),
+                clip_fade = (clip_duration - fade).max(0.0),
             ));
+            args.extend([
+                "-filter_complex".to_string(), filter,
+                "-map".to_string(), "[v]".to_string(),
+                "-map".to_string(), "[a]".to_string(),
+                "-c:v".to_string(), "libx264".to_string(),
+                "-c:a".to_string(), "aac".to_string(),
+            ]);
+        } else {
+            filter.push_str("[a0][a1][a2]concat=n=3:v=0:a=1[a]");
+            args.extend([
+                "-filter_complex".to_string(), filter,
+                "-map".to_string(), "[a]".to_string(),
+            ]);
+        }
+
+        args.push(output.to_str().unwrap().to_string());
+        self.run(&args)?;
+
+        Ok(produced(output))
+    }
+
+    /// Silence each `(start, end)` range of the input's audio and play a 1 kHz
+    /// tone over it instead. Video, if any, is copied through untouched.
+    pub fn beep(&self, ranges: &[(f64, f64)], output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+
+        if ranges.is_empty() {
+            return Err(ProcessorError::BadRequest("No ranges to beep".to_string()));
+        }
+
+        let during = ranges
+            .iter()
+            .map(|(start, end)| format!("between(t,{:.3},{:.3})", start, end))
+            .collect::<Vec<_>>()
+            .join("+");
+        let filter = format!(
+            "[0:a]aresample=48000,volume=0:enable='{during}'[main];\
+            sine=frequency=1000:sample_rate=48000,volume=0.3,volume=0:enable='not({during})'[tone];\
+            [main][tone]amix=inputs=2:duration=first:normalize=0[out]",
+            during = during,
+        );
+
+        self.run(&[
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-filter_complex".to_string(), filter,
+            "-map".to_string(), "0:v?".to_string(),
+            "-c:v".to_string(), "copy".to_string(),
+            "-map".to_string(), "[out]".to_string(),
+            output.to_str().unwrap().to_string(),
+        ])?;
+
+        Ok(produced(output))
+    }
+
+    /// Find stretches quieter than `threshold_db` lasting at least
+    /// `min_duration` seconds, using ffmpeg's silencedetect filter
+    pub fn detect_silence(&self, threshold_db: f64, min_duration: f64) -> Result<Vec<SilenceRange>, ProcessorError> {
+        let input = self.require_input()?;
+
+        let output = self.exec(&[
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-af".to_string(), format!("silencedetect=noise={}dB:d={}", threshold_db, min_duration),
+            "-vn".to_string(),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ])?;
+
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        let (mut ranges, trailing) = parse_silences(&String::from_utf8_lossy(&output.stderr));
+        // Silence running to the end of the file is never closed in the log
+        if let Some(start) = trailing {
+            ranges.push(SilenceRange { start, end: self.duration_seconds()? });
+        }
+
+        Ok(ranges)
+    }
+
+    /// Cut `silences` out of the input and join what's left. Each cut is
+    /// narrowed by `padding` seconds at both ends so speech keeps a natural
+    /// pause. Re-encodes; video is kept in step with the audio.
+    pub fn remove_silence(&self, silences: &[SilenceRange], padding: f64, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let info = self.get_info()?;
+        let duration = info.known_duration()?;
+
+        // The parts to keep are the gaps between the (padded) silences
+        let mut keep = Vec::new();
+        let mut position = 0.0;
+        for silence in silences {
+            let (start, end) = (silence.start + padding, silence.end - padding);
+            if end <= start {
+                continue;
+            }
+            if start > position {
+                keep.push((position, start));
+            }
+            position = f64::max(position, end);
+        }
+        if position < duration {
+            keep.push((position, duration));
+        }
+        if keep.is_empty() {
+            return Err(ProcessorError::BadRequest("Nothing would be left after removing silence".to_string()));
+        }
+
+        let mut filter = String::new();
+        let mut labels = String::new();
+        for (i, (start, end)) in keep.iter().enumerate() {
+            if info.has_video() {
+                filter.push_str(&format!("[0:v]trim=start={start}:end={end},setpts=PTS-STARTPTS[v{i}];"));
+                labels.push_str(&format!("[v{i}]"));
+            }
+            filter.push_str(&format!("[0:a]atrim=start={start}:end={end},asetpts=PTS-STARTPTS[a{i}];"));
+            labels.push_str(&format!("[a{i}]"));
+        }
+        let video = if info.has_video() { 1 } else { 0 };
+        filter.push_str(&format!("{}concat=n={}:v={}:a=1", labels, keep.len(), video));
+        filter.push_str(if info.has_video() { "[v][a]" } else { "[a]" });
+
+        let mut args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-filter_complex".to_string(), filter,
+        ];
+        if info.has_video() {
+            args.extend(["-map".to_string(), "[v]".to_string()]);
+        }
+        args.extend([
+            "-map".to_string(), "[a]".to_string(),
+            output.to_str().unwrap().to_string(),
+        ]);
+
+        self.run(&args)?;
+        Ok(produced(output))
+    }
+
+    /// Find leading black frames and static countdown slates with ffmpeg's
+    /// blackdetect and freezedetect filters. Stretches are chained from the
+    /// start of the video as long as each begins close to where the last ended.
+    pub fn detect_intro(&self, options: IntroOptions) -> Result<Intro, ProcessorError> {
+        let input = self.require_input()?;
+
+        let output = self.exec(&[
+            "-t".to_string(), options.scan.to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-vf".to_string(), format!(
+                "blackdetect=d={}:pix_th=0.10,freezedetect=n=-50dB:d={}",
+                options.min_black, options.min_static
+            ),
+            "-an".to_string(),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ])?;
+
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        let mut intervals = parse_visual_intervals(&String::from_utf8_lossy(&output.stderr), options.scan);
+        intervals.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        let mut end = 0.0;
+        let mut intro = Vec::new();
+        for interval in intervals {
+            if interval.start > end + options.max_gap {
+                break;
+            }
+            end = f64::max(end, interval.end);
+            intro.push(interval);
+        }
+
+        Ok(Intro { end, intervals: intro })
+    }
+
+    /// Drop everything before `start` seconds. Video is re-encoded so the
+    /// cut lands on the exact frame rather than the previous keyframe.
+    pub fn trim_start(&self, start: f64, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+
+        self.run(&[
+            "-y".to_string(),
+            "-ss".to_string(), start.to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-map".to_string(), "0".to_string(),
+            "-c:v".to_string(), "libx264".to_string(),
+            "-preset".to_string(), "veryfast".to_string(),
+            "-crf".to_string(), "18".to_string(),
+            "-c:a".to_string(), "aac".to_string(),
+            "-b:a".to_string(), "192k".to_string(),
+            output.to_str().unwrap().to_string(),
+        ])?;
+
+        Ok(produced(output))
+    }
+
+    /// Render the input with `subtitles` (SRT or WebVTT) drawn into the
+    /// picture. Video is re-encoded; audio is copied.
+    pub fn burn_subtitles(&self, subtitles: &Path, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        if !subtitles.is_file() {
+            return Err(ProcessorError::NotFound(format!("Subtitle file not found: {}", subtitles.display())));
         }
 
-        Ok(output)
+        self.run(&[
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-vf".to_string(), format!("subtitles=filename='{}'", filter_path(subtitles)),
+            "-c:v".to_string(), "libx264".to_string(),
+            "-preset".to_string(), "veryfast".to_string(),
+            "-crf".to_string(), "18".to_string(),
+            "-c:a".to_string(), "copy".to_string(),
+            output.to_str().unwrap().to_string(),
+        ])?;
+
+        Ok(produced(output))
     }
-} 
\ No newline at end of file
+
+    /// Package the input for HTTP Live Streaming: `index.m3u8` plus
+    /// `segment_NNN.ts` files of about `segment_duration` seconds in
+    /// `output_dir`, replacing any earlier ones there. Video is re-encoded to
+    /// H.264 with a keyframe at every segment boundary so players can seek
+    /// to any segment; audio becomes AAC.
+    pub fn to_hls(&self, segment_duration: f64, output_dir: &Path) -> Result<HlsOutput, ProcessorError> {
+        let input = self.require_input()?;
+        if !(1.0..=60.0).contains(&segment_duration) {
+            return Err(ProcessorError::BadRequest("segment_duration must be between 1 and 60 seconds".to_string()));
+        }
+        let has_video = self.get_info()?.has_video();
+
+        std::fs::create_dir_all(output_dir)?;
+        for entry in std::fs::read_dir(output_dir)?.filter_map(Result::ok) {
+            let path = entry.path();
+            if matches!(path.extension().and_then(|s| s.to_str()), Some("ts" | "m3u8")) {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        let playlist = output_dir.join(HLS_PLAYLIST);
+        let mut args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+        ];
+        if has_video {
+            args.extend([
+                "-c:v".to_string(), "libx264".to_string(),
+                "-preset".to_string(), "veryfast".to_string(),
+                "-crf".to_string(), "23".to_string(),
+                "-pix_fmt".to_string(), "yuv420p".to_string(),
+                "-force_key_frames".to_string(), format!("expr:gte(t,n_forced*{})", segment_duration),
+            ]);
+        } else {
+            args.push("-vn".to_string());
+        }
+        args.extend([
+            "-c:a".to_string(), "aac".to_string(),
+            "-b:a".to_string(), "128k".to_string(),
+            "-f".to_string(), "hls".to_string(),
+            "-hls_time".to_string(), segment_duration.to_string(),
+            "-hls_playlist_type".to_string(), "vod".to_string(),
+            "-hls_segment_filename".to_string(), output_dir.join("segment_%03d.ts").to_str().unwrap().to_string(),
+            playlist.to_str().unwrap().to_string(),
+        ]);
+        self.run(&args)?;
+
+        if !playlist.is_file() {
+            return Err(ProcessorError::FFmpegError("ffmpeg wrote no HLS playlist".to_string()));
+        }
+        let mut segments: Vec<PathBuf> = std::fs::read_dir(output_dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("ts"))
+            .collect();
+        segments.sort();
+
+        Ok(HlsOutput {
+            playlist: produced(&playlist),
+            segments,
+        })
+    }
+
+    /// Write the input's audio track into the output directory as
+    /// `<stem>.<sample_rate>.<ext>`, dropping any video. Goes through the
+    /// artifact cache.
+    pub fn extract_audio(&self, codec: AudioCodec, sample_rate: u32) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let output = output_dir.join(format!("{}.{}.{}", stem, sample_rate, codec.extension()));
+
+        let extracted = self.cached(
+            &[input],
+            "extract_audio",
+            &format!("codec={:?},sample_rate={}", codec, sample_rate),
+            output_dir,
+            || {
+                std::fs::create_dir_all(output_dir)?;
+                let mut args = vec![
+                    "-y".to_string(),
+                    "-i".to_string(), input.to_str().unwrap().to_string(),
+                    "-vn".to_string(),
+                    "-ar".to_string(), sample_rate.to_string(),
+                ];
+                args.extend(codec.encoder_args());
+                args.push(output.to_str().unwrap().to_string());
+                self.run(&args)?;
+                Ok(vec![produced(&output)])
+            },
+        )?;
+
+        Ok(extracted.into_iter().next().unwrap_or(output))
+    }
+
+    /// Grab a single full-resolution frame at `timestamp` seconds as an image
+    pub fn extract_frame(&self, timestamp: f64, output: &Path) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+
+        self.run(&[
+            "-y".to_string(),
+            "-ss".to_string(), timestamp.to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-frames:v".to_string(), "1".to_string(),
+            "-q:v".to_string(), "2".to_string(),
+            output.to_str().unwrap().to_string(),
+        ])?;
+
+        Ok(produced(output))
+    }
+
+    /// Grab a frame at each of `timestamps` into the output directory as
+    /// `<stem>_<seconds>.<ext>`, in the configured image format. With a
+    /// `size`, frames are scaled to fit and padded to exactly that many
+    /// pixels. Goes through the artifact cache.
+    pub fn extract_frames(&self, timestamps: &[f64], size: Option<(u32, u32)>) -> Result<Vec<PathBuf>, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+        if let Some(bad) = timestamps.iter().find(|t| !t.is_finite() || **t < 0.0) {
+            return Err(ProcessorError::BadRequest(format!("Invalid frame time: {}", bad)));
+        }
+        if size.is_some_and(|(width, height)| width == 0 || height == 0) {
+            return Err(ProcessorError::BadRequest("Frame size must be at least 1x1".to_string()));
+        }
+
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+        let suffix = size.map(|(width, height)| format!("_{}x{}", width, height)).unwrap_or_default();
+        let extension = self.image_format.extension();
+        let params = format!("times={:?},size={:?},format={}", timestamps, size, extension);
+
+        self.cached(&[input], "extract_frames", &params, output_dir, || {
+            std::fs::create_dir_all(output_dir)?;
+            timestamps
+                .iter()
+                .map(|timestamp| {
+                    let output = output_dir.join(format!("{}_{:.3}{}.{}", stem, timestamp, suffix, extension));
+                    let mut args = vec![
+                        "-y".to_string(),
+                        "-ss".to_string(), timestamp.to_string(),
+                        "-i".to_string(), input.to_str().unwrap().to_string(),
+                        "-frames:v".to_string(), "1".to_string(),
+                    ];
+                    if let Some((width, height)) = size {
+                        args.push("-vf".to_string());
+                        args.push(format!(
+                            "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+                            w = width,
+                            h = height
+                        ));
+                    }
+                    if self.image_format == ImageFormat::Jpg {
+                        args.push("-q:v".to_string());
+                        args.push("2".to_string());
+                    }
+                    args.push(output.to_str().unwrap().to_string());
+                    self.run(&args)?;
+                    Ok(produced(&output))
+                })
+                .collect()
+        })
+    }
+
+    /// Frames where the picture changes by more than `threshold` (0-1),
+    /// using the scene score of ffmpeg's select filter
+    pub fn scene_changes(&self, threshold: f64) -> Result<Vec<SceneChange>, ProcessorError> {
+        let input = self.require_input()?;
+
+        let output = self.exec(&[
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-an".to_string(),
+            "-vf".to_string(), format!("select='gt(scene,{})',metadata=print", threshold),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ])?;
+
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        Ok(parse_scene_changes(&String::from_utf8_lossy(&output.stderr)))
+    }
+
+    /// Peak and RMS amplitude of the input's audio, `samples_per_second`
+    /// values per second of media, mixed down to mono
+    pub fn waveform(&self, samples_per_second: u32) -> Result<Waveform, ProcessorError> {
+        let input = self.require_input()?;
+        if !(1..=analysis::WAVEFORM_SAMPLE_RATE).contains(&samples_per_second) {
+            return Err(ProcessorError::BadRequest(format!(
+                "samples_per_second must be between 1 and {}",
+                analysis::WAVEFORM_SAMPLE_RATE
+            )));
+        }
+
+        let output = self.exec(&[
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-vn".to_string(),
+            "-ac".to_string(), "1".to_string(),
+            "-ar".to_string(), analysis::WAVEFORM_SAMPLE_RATE.to_string(),
+            "-f".to_string(), "s16le".to_string(),
+            "-".to_string(),
+        ])?;
+
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        Ok(analysis::waveform_from_pcm(&output.stdout, samples_per_second))
+    }
+
+    /// Integrated loudness, true peak and loudness range of the input, as
+    /// measured by ffmpeg's loudnorm filter (EBU R128)
+    pub fn loudness(&self) -> Result<Loudness, ProcessorError> {
+        let input = self.require_input()?;
+
+        let output = self.exec(&[
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-vn".to_string(),
+            "-af".to_string(), "loudnorm=print_format=json".to_string(),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ])?;
+
+        if !output.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&output)));
+        }
+
+        analysis::parse_loudnorm(&String::from_utf8_lossy(&output.stderr))
+    }
+
+    /// Bring the input to `target_lufs` integrated loudness (true peak at
+    /// most -1.5 dBTP) with loudnorm's two-pass linear mode: the first pass
+    /// measures, the second applies one gain change using those
+    /// measurements, so the dynamics are left alone where the range allows.
+    /// Written to `<stem>.normalized.<ext>` in the output directory, in the
+    /// input's container; video is stream-copied.
+    pub fn normalize_loudness(&self, target_lufs: f64) -> Result<Normalization, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+        if !(-70.0..=-5.0).contains(&target_lufs) {
+            return Err(ProcessorError::BadRequest("target_lufs must be between -70 and -5".to_string()));
+        }
+
+        let format = OutputFormat::of(input).unwrap_or(self.output_format);
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let output = output_dir.join(format!("{}.normalized.{}", stem, format.extension()));
+        let target = format!("I={}:TP={}:LRA={}", target_lufs, NORMALIZE_TRUE_PEAK, NORMALIZE_RANGE);
+
+        // First pass: measure against the same target the second pass uses
+        let measured = self.exec(&[
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-vn".to_string(),
+            "-af".to_string(), format!("loudnorm={}:print_format=json", target),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ])?;
+        if !measured.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&measured)));
+        }
+        let before = analysis::parse_loudnorm_report(&String::from_utf8_lossy(&measured.stderr))?;
+
+        // Second pass: apply it. loudnorm resamples to 192 kHz internally, so
+        // set the output rate back to the source's.
+        let info = self.get_info()?;
+        let filter = format!(
+            "loudnorm={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=json",
+            target,
+            before.input.integrated_lufs,
+            before.input.true_peak_dbtp,
+            before.input.range_lu,
+            before.input.threshold_lufs,
+            before.target_offset,
+        );
+        std::fs::create_dir_all(output_dir)?;
+        let mut args = vec![
+            "-y".to_string(),
+            "-i".to_string(), input.to_str().unwrap().to_string(),
+            "-af".to_string(), filter,
+            "-ar".to_string(), info.sample_rate.unwrap_or(48000).to_string(),
+        ];
+        if info.has_video() && format.is_video() {
+            args.extend(["-c:v".to_string(), "copy".to_string()]);
+        } else {
+            args.push("-vn".to_string());
+        }
+        args.extend(format.audio_encoder_args(None));
+        args.push(output.to_str().unwrap().to_string());
+
+        tracing::info!("Normalizing {:?} from {:.1} to {} LUFS", input, before.input.integrated_lufs, target_lufs);
+        let applied = self.exec(&args)?;
+        if !applied.status.success() {
+            return Err(ProcessorError::FFmpegError(stderr_tail(&applied)));
+        }
+        let after = analysis::parse_loudnorm_report(&String::from_utf8_lossy(&applied.stderr))?;
+
+        Ok(Normalization {
+            output: produced(&output),
+            target_lufs,
+            before: before.input,
+            after: after.output,
+        })
+    }
+
+    /// Re-encode the input into `format`, optionally at an audio bitrate (in
+    /// kbps) and sample rate other than the encoder's defaults. Video is
+    /// kept when the format carries it and dropped otherwise. Written to the
+    /// output directory as `<stem>[.<kbps>k][.<rate>hz].<ext>`.
+    pub fn transcode(&self, format: OutputFormat, bitrate: Option<u32>, sample_rate: Option<u32>) -> Result<PathBuf, ProcessorError> {
+        let input = self.require_input()?;
+        let output_dir = self.require_output_dir()?;
+        if bitrate.is_some_and(|kbps| !(32..=512).contains(&kbps)) {
+            return Err(ProcessorError::BadRequest("bitrate must be between 32 and 512 kbps".to_string()));
+        }
+        if sample_rate.is_some_and(|rate| !(8000..=192000).contains(&rate)) {
+            return Err(ProcessorError::BadRequest("sample_rate must be between 8000 and 192000 Hz".to_string()));
+        }
+
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let mut name = stem.to_string();
+        if let Some(kbps) = bitrate {
+            name.push_str(&format!(".{}k", kbps));
+        }
+        if let Some(rate) = sample_rate {
+            name.push_str(&format!(".{}hz", rate));
+        }
+        let output = output_dir.join(format!("{}.{}", name, format.extension()));
+        if output == *input {
+            return Err(ProcessorError::BadRequest("Transcoding would overwrite the input".to_string()));
+        }
+
+        let transcoded = self.cached(
+            &[input],
+            "transcode",
+            &format!("format={},bitrate={:?},sample_rate={:?}", format.extension(), bitrate, sample_rate),
+            output_dir,
+            || {
+                std::fs::create_dir_all(output_dir)?;
+                let mut args = vec![
+                    "-y".to_string(),
+                    "-i".to_string(), input.to_str().unwrap().to_string(),
+                ];
+                if !format.is_video() {
+                    args.push("-vn".to_string());
+                }
+                if let Some(rate) = sample_rate {
+                    args.extend(["-ar".to_string(), rate.to_string()]);
+                }
+                args.extend(format.audio_encoder_args(bitrate));
+                args.push(output.to_str().unwrap().to_string());
+                self.run(&args)?;
+                Ok(vec![produced(&output)])
+            },
+        )?;
+
+        Ok(transcoded.into_iter().next().unwrap_or(output))
+    }
+}
+
+/// Choose `count` timestamps in a `duration`-second video whose frames
+/// differ the most: the strongest scene changes first, kept at least
+/// `duration / (2 * count)` apart, topped up with evenly spaced times when
+/// there are too few. Returned in playback order.
+pub fn pick_distinct_times(changes: &[SceneChange], count: usize, duration: f64) -> Vec<f64> {
+    if count == 0 || duration <= 0.0 {
+        return Vec::new();
+    }
+    let min_gap = duration / (2 * count) as f64;
+
+    let mut by_score = changes.to_vec();
+    by_score.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let evenly_spaced = (0..count).map(|i| duration * (i as f64 + 0.5) / count as f64);
+
+    let mut times: Vec<f64> = Vec::new();
+    for time in by_score.iter().map(|change| change.time).chain(evenly_spaced) {
+        if times.len() == count {
+            break;
+        }
+        if time < duration && times.iter().all(|picked| (picked - time).abs() >= min_gap) {
+            times.push(time);
+        }
+    }
+
+    times.sort_by(f64::total_cmp);
+    times
+}
+
+/// Parse `metadata=print` output for a scene `select`: a `pts_time:` line
+/// per selected frame followed by its `lavfi.scene_score=` line
+fn parse_scene_changes(stderr: &str) -> Vec<SceneChange> {
+    let mut changes = Vec::new();
+    let mut time = None;
+    for line in stderr.lines() {
+        if let Some(rest) = line.split("pts_time:").nth(1) {
+            time = rest.split_whitespace().next().and_then(|t| t.parse().ok());
+        } else if let Some(score) = line.split("lavfi.scene_score=").nth(1) {
+            if let (Some(time), Ok(score)) = (time.take(), score.trim().parse()) {
+                changes.push(SceneChange { time, score });
+            }
+        }
+    }
+    changes
+}
+
+/// Parse blackdetect and freezedetect log lines. A freeze still running when
+/// the scan ends is closed at `scan_end`.
+fn parse_visual_intervals(stderr: &str, scan_end: f64) -> Vec<VisualInterval> {
+    let value = |line: &str, key: &str| -> Option<f64> {
+        line.split(key).nth(1)?.split_whitespace().next()?.parse().ok()
+    };
+
+    let mut intervals = Vec::new();
+    let mut freeze_start = None;
+    for line in stderr.lines() {
+        if line.contains("black_start:") {
+            if let (Some(start), Some(end)) = (value(line, "black_start:"), value(line, "black_end:")) {
+                intervals.push(VisualInterval { kind: IntervalKind::Black, start, end });
+            }
+        } else if line.contains("freeze_start:") {
+            freeze_start = value(line, "freeze_start:");
+        } else if line.contains("freeze_end:") {
+            if let (Some(start), Some(end)) = (freeze_start.take(), value(line, "freeze_end:")) {
+                intervals.push(VisualInterval { kind: IntervalKind::Static, start, end });
+            }
+        }
+    }
+    if let Some(start) = freeze_start {
+        intervals.push(VisualInterval { kind: IntervalKind::Static, start, end: scan_end });
+    }
+
+    intervals
+}
+
+/// Parse silencedetect's paired `silence_start:` / `silence_end:` log lines.
+/// Also returns the start of a silence still open when the log ends.
+fn parse_silences(stderr: &str) -> (Vec<SilenceRange>, Option<f64>) {
+    let value = |line: &str, key: &str| -> Option<f64> {
+        line.split(key).nth(1)?.split_whitespace().next()?.parse().ok()
+    };
+
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for line in stderr.lines() {
+        if line.contains("silence_start:") {
+            start = value(line, "silence_start:");
+        } else if line.contains("silence_end:") {
+            if let (Some(start), Some(end)) = (start.take(), value(line, "silence_end:")) {
+                ranges.push(SilenceRange { start: start.max(0.0), end });
+            }
+        }
+    }
+    (ranges, start.map(|start| start.max(0.0)))
+}
+
+/// One `key=value` line of ffmpeg's `-progress` output that we act on
+#[derive(Debug, PartialEq)]
+enum ProgressLine {
+    /// Seconds of output written so far
+    OutTime(f64),
+    End,
+}
+
+fn parse_progress_line(line: &str) -> Option<ProgressLine> {
+    let (key, value) = line.trim().split_once('=')?;
+    match key {
+        // Despite its name, out_time_ms is in microseconds too
+        "out_time_us" | "out_time_ms" => value
+            .parse::<i64>()
+            .ok()
+            .filter(|us| *us >= 0)
+            .map(|us| ProgressLine::OutTime(us as f64 / 1_000_000.0)),
+        "progress" if value == "end" => Some(ProgressLine::End),
+        _ => None,
+    }
+}
+
+/// The last few lines of ffmpeg's stderr, where the actual error is; the
+/// rest is the banner and stream listing
+fn stderr_tail(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().filter(|line| !line.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(5)..].join("\n")
+}
+
+/// Report a freshly written artifact on the event bus
+fn produced(path: &Path) -> PathBuf {
+    events::artifact(path, false);
+    path.to_path_buf()
+}
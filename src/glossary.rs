@@ -0,0 +1,100 @@
+use regex::{NoExpand, Regex};
+
+use crate::templates::PromptTemplate;
+use crate::whisper::Segment;
+
+/// Per-profile list of canonical spellings (channel names, player names,
+/// Sanskrit terms) and the ways whisper tends to mishear them
+pub struct Glossary {
+    entries: Vec<GlossaryEntry>,
+}
+
+struct GlossaryEntry {
+    term: String,
+    pattern: Regex,
+}
+
+impl Glossary {
+    /// Load the glossary for a profile from `prompt_dev/glossary/<profile>.txt`.
+    /// A missing file is an empty glossary.
+    pub fn load(template: &PromptTemplate) -> Self {
+        let path = format!("prompt_dev/glossary/{}.txt", template.name());
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => {
+                tracing::info!("No glossary found at {}", path);
+                Self { entries: Vec::new() }
+            }
+        }
+    }
+
+    /// Parse `Term: variant, variant` lines; `#` starts a comment
+    pub fn parse(content: &str) -> Self {
+        let entries = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (term, variants) = match line.split_once(':') {
+                    Some((term, variants)) => (term.trim(), variants),
+                    None => (line, ""),
+                };
+
+                // Match the term itself too, so its casing is normalized.
+                // Longest alternatives first so the regex prefers them.
+                let mut alternatives: Vec<&str> = variants
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .chain(std::iter::once(term))
+                    .collect();
+                alternatives.sort_by_key(|v| std::cmp::Reverse(v.len()));
+
+                let pattern = alternatives
+                    .iter()
+                    .map(|v| regex::escape(v))
+                    .collect::<Vec<_>>()
+                    .join("|");
+
+                Regex::new(&format!(r"(?i)\b(?:{})\b", pattern))
+                    .ok()
+                    .map(|pattern| GlossaryEntry { term: term.to_string(), pattern })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Replace misheard variants with canonical spellings, on word boundaries only
+    pub fn apply(&self, text: &str) -> String {
+        self.entries.iter().fold(text.to_string(), |text, entry| {
+            entry.pattern.replace_all(&text, NoExpand(&entry.term)).into_owned()
+        })
+    }
+
+    pub fn apply_to_segments(&self, segments: &mut [Segment]) {
+        for segment in segments {
+            segment.text = self.apply(&segment.text);
+        }
+    }
+
+    /// Canonical spellings of every entry
+    pub fn terms(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.term.as_str())
+    }
+
+    /// Prompt section listing the canonical spellings, or an empty string
+    pub fn prompt_section(&self) -> String {
+        if self.entries.is_empty() {
+            return String::new();
+        }
+
+        let terms = self.entries
+            .iter()
+            .map(|entry| format!("- {}", entry.term))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!("\n\n## Glossary\nAlways spell these names and terms exactly as written:\n{}\n", terms)
+    }
+}
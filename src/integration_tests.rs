@@ -0,0 +1,1784 @@
+//! HTTP-level tests of the split/merge/transcribe flows. Real ffmpeg and
+//! whisper are replaced by a fake process runner that writes placeholder
+//! output files, so no media tools are needed.
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{ExitStatus, Output};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+use crate::batch::BatchQueue;
+use crate::cache::ArtifactCache;
+use crate::config::{Cli, Config};
+use crate::events::{self, PipelineEvent};
+use crate::features::Features;
+use crate::jobs::JobQueue;
+use crate::library;
+use crate::llm::Chapter;
+use crate::openai::RetryPolicy;
+use crate::pipeline;
+use crate::scheduler::{scheduler, LlmScheduler};
+use crate::media::{BodyLimits, IngestOptions};
+use crate::process::{self, ProcessRunner};
+use crate::store::Store;
+use crate::webhooks::{self, WebhookSender};
+use crate::workspace::{Workspace, LAYOUT_VERSION};
+use crate::{router, AppState};
+
+/// Signs the harness's webhook deliveries
+const WEBHOOK_SECRET: &str = "test-secret";
+
+const WHISPER_JSON: &str = r#"{
+  "text": " Welcome back to Medman Sports. Today we work on the serve.",
+  "language": "en",
+  "segments": [
+    {"id": 0, "start": 0.0, "end": 2.5, "text": " Welcome back to Medman Sports.", "avg_logprob": -0.1,
+     "words": [{"word": " Welcome", "start": 0.0, "end": 0.6, "probability": 0.98},
+               {"word": " back", "start": 0.6, "end": 0.9, "probability": 0.97}]},
+    {"id": 1, "start": 2.5, "end": 5.0, "text": " Today we work\non the serve.", "avg_logprob": -0.9}
+  ]
+}"#;
+
+/// Stands in for ffmpeg, whisper and the diarization script
+struct FakeRunner;
+
+impl ProcessRunner for FakeRunner {
+    fn run(&self, program: &str, args: &[String]) -> Result<Output, std::io::Error> {
+        match program {
+            "ffmpeg" => Ok(fake_ffmpeg(args)),
+            "ffprobe" => Ok(fake_ffprobe(args)),
+            "whisper" => Ok(fake_whisper(args)),
+            "scripts/diarize.py" => Ok(fake_diarize(args)),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::NotFound, program.to_string())),
+        }
+    }
+}
+
+fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+    Output {
+        status: ExitStatus::from_raw(code << 8),
+        stdout: stdout.as_bytes().to_vec(),
+        stderr: stderr.as_bytes().to_vec(),
+    }
+}
+
+fn arg_after<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn fake_ffmpeg(args: &[String]) -> Output {
+    let inputs: Vec<&String> = args
+        .iter()
+        .zip(args.iter().skip(1))
+        .filter(|(flag, _)| *flag == "-i")
+        .map(|(_, input)| input)
+        .collect();
+    if let Some(missing) = inputs.iter().find(|input| !Path::new(input.as_str()).exists()) {
+        return output(1, "", &format!("{}: No such file or directory", missing));
+    }
+
+    // Dead air from 1.0s to 2.5s, and again from 4.0s to the end
+    if args.iter().any(|arg| arg.starts_with("silencedetect")) {
+        return output(
+            0,
+            "",
+            "[silencedetect @ 0x1] silence_start: 1\n\
+            [silencedetect @ 0x1] silence_end: 2.5 | silence_duration: 1.5\n\
+            [silencedetect @ 0x1] silence_start: 4\n",
+        );
+    }
+
+    // One second of mono PCM: half amplitude, then silence
+    if args.iter().any(|arg| arg == "s16le") {
+        let mut pcm = Vec::new();
+        for i in 0..8000 {
+            let sample: i16 = if i < 4000 { 16384 } else { 0 };
+            pcm.extend_from_slice(&sample.to_le_bytes());
+        }
+        return Output { stdout: pcm, ..output(0, "", "") };
+    }
+
+    // Measures the same whichever pass it is; a normalizing pass also writes its output
+    if args.iter().any(|arg| arg.starts_with("loudnorm")) {
+        let target = args.last().unwrap();
+        if target != "-" {
+            std::fs::write(target, "normalized").unwrap();
+        }
+        return output(
+            0,
+            "",
+            "[Parsed_loudnorm_0 @ 0x1] \n{\n\
+            \t\"input_i\" : \"-23.51\",\n\
+            \t\"input_tp\" : \"-4.20\",\n\
+            \t\"input_lra\" : \"6.30\",\n\
+            \t\"input_thresh\" : \"-33.90\",\n\
+            \t\"output_i\" : \"-14.02\",\n\
+            \t\"output_tp\" : \"-1.60\",\n\
+            \t\"output_lra\" : \"5.90\",\n\
+            \t\"output_thresh\" : \"-24.40\",\n\
+            \t\"normalization_type\" : \"linear\",\n\
+            \t\"target_offset\" : \"0.02\"\n}\n",
+        );
+    }
+
+    // A black leader for the first 1.5s
+    if args.iter().any(|arg| arg.starts_with("blackdetect")) {
+        return output(0, "", "[blackdetect @ 0x1] black_start:0 black_end:1.5 black_duration:1.5\n");
+    }
+
+    // Cuts at 0.5s (small) and 3.0s (big)
+    if args.iter().any(|arg| arg.contains("gt(scene,")) {
+        return output(
+            0,
+            "",
+            "[Parsed_metadata_1 @ 0x1] frame:0    pts:15      pts_time:0.5\n\
+            [Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.350000\n\
+            [Parsed_metadata_1 @ 0x1] frame:1    pts:90      pts_time:3\n\
+            [Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.910000\n",
+        );
+    }
+
+    // Three segments and a playlist listing them
+    if let Some(pattern) = arg_after(args, "-hls_segment_filename") {
+        let mut playlist = String::from("#EXTM3U\n#EXT-X-PLAYLIST-TYPE:VOD\n");
+        for i in 0..3 {
+            let segment = pattern.replace("%03d", &format!("{:03}", i));
+            std::fs::write(&segment, format!("segment {}", i)).unwrap();
+            let name = Path::new(&segment).file_name().unwrap().to_str().unwrap().to_string();
+            playlist.push_str(&format!("#EXTINF:2.0,\n{}\n", name));
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        std::fs::write(args.last().unwrap(), playlist).unwrap();
+        return output(0, "", "");
+    }
+
+    // Like ffmpeg, which won't replace an existing output without -y
+    let target = args.last().unwrap();
+    if Path::new(target).is_file() && !args.iter().any(|arg| arg == "-y") {
+        return output(1, "", &format!("File '{}' already exists. Exiting.", target));
+    }
+    if target.contains("%03d") {
+        for i in 0..3 {
+            std::fs::write(target.replace("%03d", &format!("{:03}", i)), format!("chunk {}", i)).unwrap();
+        }
+    } else {
+        // Tell outputs of different runs apart
+        std::fs::write(target, format!("fake ffmpeg output: {}", args.join(" "))).unwrap();
+    }
+
+    // Halfway through the 5 second probe duration, then done
+    if args.iter().any(|arg| arg == "-progress") {
+        return output(0, "frame=75\nout_time_us=2500000\nprogress=continue\nout_time_us=5000000\nprogress=end\n", "");
+    }
+
+    output(0, "", "")
+}
+
+/// Every file probes as 5 seconds of stereo MP3 audio; .mov and .mp4 files
+/// also have a 1080p30 video stream
+fn fake_ffprobe(args: &[String]) -> Output {
+    let input = args.last().unwrap();
+    if !Path::new(input).exists() {
+        return output(1, "", &format!("{}: No such file or directory", input));
+    }
+
+    let mut streams = vec![serde_json::json!({
+        "index": 0, "codec_type": "audio", "codec_name": "mp3", "sample_rate": "44100",
+        "channels": 2, "channel_layout": "stereo", "bit_rate": "128000", "duration": "5.000000"
+    })];
+    if input.ends_with(".mov") || input.ends_with(".mp4") {
+        streams.push(serde_json::json!({
+            "index": 1, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+            "pix_fmt": "yuv420p", "avg_frame_rate": "30/1", "r_frame_rate": "30/1", "duration": "5.000000",
+            "disposition": {"attached_pic": 0}, "tags": {"language": "eng"}
+        }));
+    }
+    let probe = serde_json::json!({
+        "streams": streams,
+        "format": {"format_name": "mp3", "duration": "5.000000", "size": "80000", "bit_rate": "128000"}
+    });
+    output(0, &probe.to_string(), "")
+}
+
+fn fake_whisper(args: &[String]) -> Output {
+    let input = Path::new(&args[0]);
+    if !input.exists() {
+        return output(1, "", "No such file");
+    }
+
+    let output_dir = Path::new(arg_after(args, "--output_dir").unwrap());
+    let stem = input.file_stem().unwrap().to_str().unwrap();
+    let mut json: Value = serde_json::from_str(WHISPER_JSON).unwrap();
+    if let Some(language) = arg_after(args, "--language") {
+        json["language"] = language.into();
+    }
+    // Two videos recorded back to back: a long pause between unrelated lines
+    if std::fs::read_to_string(input).is_ok_and(|content| content.contains("two videos")) {
+        json["segments"][0]["end"] = 1.0.into();
+        json["segments"][1]["start"] = 4.0.into();
+    }
+    if arg_after(args, "--word_timestamps") != Some("True") {
+        for segment in json["segments"].as_array_mut().unwrap() {
+            segment.as_object_mut().unwrap().remove("words");
+        }
+    }
+    std::fs::write(output_dir.join(format!("{}.json", stem)), json.to_string()).unwrap();
+    if arg_after(args, "--output_format") == Some("all") {
+        for extension in ["txt", "vtt", "srt", "tsv"] {
+            std::fs::write(output_dir.join(format!("{}.{}", stem, extension)), "").unwrap();
+        }
+    }
+
+    output(0, "", "")
+}
+
+fn fake_diarize(args: &[String]) -> Output {
+    if !Path::new(&args[0]).exists() {
+        return output(1, "", "No such file");
+    }
+    let turns = json!({"turns": [
+        {"start": 0.0, "end": 2.4, "speaker": "SPEAKER_01"},
+        {"start": 2.4, "end": 5.0, "speaker": "SPEAKER_00"}
+    ]});
+    output(0, &turns.to_string(), "")
+}
+
+struct Harness {
+    app: Router,
+    workspace: Workspace,
+    _dir: TempDir,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self::with_features(Features { llm: false, publish: false })
+    }
+
+    fn with_features(features: Features) -> Self {
+        process::set_runner(Arc::new(FakeRunner));
+
+        let dir = tempfile::tempdir().unwrap();
+        // Mark the layout current so no legacy migration runs against ./src
+        std::fs::write(
+            dir.path().join("layout.json"),
+            format!("{{\"version\": {}}}", LAYOUT_VERSION),
+        )
+        .unwrap();
+        let workspace = Workspace::new(dir.path()).open().unwrap();
+
+        let state = AppState {
+            cache: Arc::new(ArtifactCache::new(workspace.cache_dir())),
+            limits: BodyLimits {
+                max_upload_bytes: 1024,
+                max_request_bytes: 1024,
+            },
+            ingest: IngestOptions { trim_intro: false },
+            features,
+            jobs: Arc::new(JobQueue::new(
+                1,
+                WebhookSender::new(
+                    Some(WEBHOOK_SECRET.to_string()),
+                    RetryPolicy {
+                        timeout: Duration::from_secs(2),
+                        max_retries: 1,
+                        base_delay: Duration::from_millis(1),
+                    },
+                ),
+            )),
+            batches: Arc::new(BatchQueue::new(2)),
+            store: Arc::new(Store::open(&workspace.db_dir().join("processor.sqlite3")).unwrap()),
+            workspace: workspace.clone(),
+        };
+
+        Self {
+            app: router(state),
+            workspace,
+            _dir: dir,
+        }
+    }
+
+    /// Upload a placeholder file and return its media ID
+    async fn add_media(&self, name: &str) -> String {
+        let uri = format!("/media/upload/{}", name);
+        let (status, body) = self
+            .send(Request::put(uri.as_str()).body(Body::from(format!("fake media {}", name))).unwrap())
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        response["media_id"].as_str().unwrap().to_string()
+    }
+
+    async fn send(&self, request: Request<Body>) -> (StatusCode, Vec<u8>) {
+        let response = self.app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes().to_vec();
+        (status, body)
+    }
+
+    async fn get_json(&self, uri: &str) -> Value {
+        let (status, body) = self.send(Request::get(uri).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK, "GET {}", uri);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn post(&self, uri: &str, body: &Value) -> (StatusCode, Vec<u8>) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    async fn post_json(&self, uri: &str, body: Value) -> Value {
+        let (status, response) = self.post(uri, &body).await;
+        assert_eq!(status, StatusCode::OK, "POST {} {}", uri, body);
+        serde_json::from_slice(&response).unwrap()
+    }
+}
+
+fn paths(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str().unwrap().to_string())
+        .collect()
+}
+
+/// Serve `app` on a free local port, returning its base URL
+async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn split_then_merge() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    let split = harness.post_json("/split", json!({ "media_id": id })).await;
+    let chunks = paths(&split["chunks"]);
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks.iter().all(|chunk| Path::new(chunk).exists()));
+
+    let merged = harness.post_json("/merge", json!({ "media_id": id })).await;
+    let output = merged["output_file"].as_str().unwrap();
+    assert!(output.ends_with(&format!("{}.merged.mp3", id)));
+    assert!(Path::new(output).exists());
+
+    // Changed chunks miss the cache and are merged over the earlier output
+    std::fs::write(&chunks[0], "re-recorded").unwrap();
+    let again = harness.post_json("/merge", json!({ "media_id": id })).await;
+    assert_eq!(again["output_file"], output);
+}
+
+#[tokio::test]
+async fn merge_crossfades_chunks() {
+    let harness = Harness::new();
+    let video = harness.add_media("rally.mp4").await;
+    harness.post_json("/split", json!({ "media_id": video, "format": "mp4" })).await;
+
+    let merged = harness
+        .post_json("/merge", json!({ "media_id": video, "format": "mp4", "transition": "dissolve" }))
+        .await;
+    assert_eq!(merged["crossfade"], 0.5);
+    assert_eq!(merged["transition"], "dissolve");
+    assert!(Path::new(merged["output_file"].as_str().unwrap()).exists());
+
+    // The middle chunk (5s) can't give up 3s at both ends
+    let body = json!({ "media_id": video, "format": "mp4", "crossfade": 3 });
+    let (status, _) = harness.post("/merge", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let audio = harness.add_media("audio.mp3").await;
+    harness.post_json("/split", json!({ "media_id": audio })).await;
+    let merged = harness.post_json("/merge", json!({ "media_id": audio, "crossfade": 1 })).await;
+    assert_eq!(merged["crossfade"], 1.0);
+    assert!(merged["transition"].is_null());
+
+    let (status, _) = harness.post("/merge", &json!({ "media_id": audio, "transition": "fade" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn mutations_take_validated_json_bodies() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    // Checked before anything is queued
+    let body = json!({ "media_id": id, "chunk_duration": 0, "background": true });
+    let (status, response) = harness.post("/split", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response: Value = serde_json::from_slice(&response).unwrap();
+    assert_eq!(response["error"], "chunk_duration must be at least 1 second");
+
+    // Unreadable bodies get the usual error shape
+    let (status, response) = harness.post("/merge", &json!({ "crossfade": 1 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response: Value = serde_json::from_slice(&response).unwrap();
+    assert_eq!(response["kind"], "bad_request");
+    assert!(response["error"].as_str().unwrap().contains("media_id"), "{}", response);
+
+    let (status, _) = harness.send(Request::post("/split").body(Body::from("media_id=abc")).unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Nothing that writes files answers GET
+    for uri in [
+        format!("/split?media_id={}", id),
+        format!("/analysis/intro?media_id={}&apply=true", id),
+        format!("/media/normalize?media_id={}", id),
+    ] {
+        let (status, _) = harness.send(Request::get(uri.as_str()).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED, "{}", uri);
+    }
+
+    let (status, response) = harness.post("/media/loop", &json!({ "media_id": id })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response: Value = serde_json::from_slice(&response).unwrap();
+    assert_eq!(response["error"], "Either loops or duration is required");
+
+    for body in [
+        json!({ "media_id": id, "min_silence": 0 }),
+        json!({ "media_id": id, "window": -60 }),
+        json!({ "media_id": id, "min_part": -1 }),
+        json!({ "media_id": id, "max_similarity": 1.5 }),
+    ] {
+        let (status, _) = harness.post("/analysis/split-points", &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn split_points_apply_registers_parts_as_media() {
+    let harness = Harness::new();
+    let (status, body) = harness
+        .send(Request::put("/media/upload/double.mp4").body(Body::from("two videos")).unwrap())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let id = serde_json::from_slice::<Value>(&body).unwrap()["media_id"].as_str().unwrap().to_string();
+
+    // A file by hand under the name parts used to be written as
+    let by_hand = harness.workspace.media_dir().join(format!("{}_part01.mp4", id));
+    std::fs::write(&by_hand, "keep me").unwrap();
+
+    let body = json!({ "media_id": id, "min_silence": 1, "min_part": 1 });
+    let proposed = harness.post_json("/analysis/split-points", body.clone()).await;
+    assert_eq!(proposed["split_points"][0]["time"], 2.5);
+    assert!(proposed.get("parts").is_none());
+
+    let mut body = body;
+    body["apply"] = true.into();
+    let split = harness.post_json("/analysis/split-points", body.clone()).await;
+    let parts = split["parts"].as_array().unwrap();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0]["name"], "double_part01.mp4");
+    assert_eq!(parts[1]["name"], "double_part02.mp4");
+    for part in parts {
+        let entry = harness.get_json(&format!("/media/{}", part["media_id"].as_str().unwrap())).await;
+        assert_eq!(entry["name"], part["name"]);
+        assert!(harness.workspace.media_dir().join(entry["file"].as_str().unwrap()).is_file());
+    }
+    assert_eq!(std::fs::read_to_string(&by_hand).unwrap(), "keep me");
+
+    // Splitting again leaves the first parts in place
+    harness.post_json("/analysis/split-points", body).await;
+    for part in parts {
+        harness.get_json(&format!("/media/{}", part["media_id"].as_str().unwrap())).await;
+    }
+    let entries = std::fs::read_dir(harness.workspace.media_dir()).unwrap();
+    assert!(entries.filter_map(Result::ok).all(|entry| entry.path().is_file()), "scratch left in media/");
+}
+
+#[tokio::test]
+async fn split_is_restored_from_cache() {
+    let harness = Harness::new();
+    let body = json!({ "media_id": harness.add_media("audio.mp3").await });
+
+    let first = paths(&harness.post_json("/split", body.clone()).await["chunks"]);
+    for chunk in &first {
+        std::fs::remove_file(chunk).unwrap();
+    }
+
+    let second = paths(&harness.post_json("/split", body).await["chunks"]);
+    assert_eq!(first, second);
+    assert!(second.iter().all(|chunk| Path::new(chunk).exists()));
+}
+
+#[tokio::test]
+async fn restored_outputs_do_not_share_cache_files() {
+    fn cache_files(dir: &Path) -> Vec<(std::path::PathBuf, Vec<u8>)> {
+        let mut files = Vec::new();
+        for path in std::fs::read_dir(dir).unwrap().filter_map(Result::ok).map(|entry| entry.path()) {
+            if path.is_dir() {
+                files.extend(cache_files(&path));
+            } else {
+                files.push((path.clone(), std::fs::read(&path).unwrap()));
+            }
+        }
+        files
+    }
+
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+    harness.post_json("/split", json!({ "media_id": id })).await;
+
+    // Merged once, then restored from the cache into the same output path
+    harness.post_json("/merge", json!({ "media_id": id })).await;
+    let cached = cache_files(&harness.workspace.cache_dir());
+    harness.post_json("/merge", json!({ "media_id": id })).await;
+
+    // Other settings miss the cache and ffmpeg overwrites the restored file
+    let merged = harness.post_json("/merge", json!({ "media_id": id, "crossfade": 1 })).await;
+    let output = std::fs::read(merged["output_file"].as_str().unwrap()).unwrap();
+    for (path, bytes) in cached {
+        assert_ne!(output, bytes);
+        assert_eq!(std::fs::read(&path).unwrap(), bytes, "{:?}", path);
+    }
+}
+
+#[tokio::test]
+async fn split_region_produces_three_parts() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    let region = harness.post_json("/split-region", json!({ "media_id": id, "start": 1.0, "end": 1.5 })).await;
+    let chunks = paths(&region["chunks"]);
+    let names: Vec<&str> = chunks
+        .iter()
+        .map(|c| Path::new(c).file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["chunk_before.mp3", "chunk_selected.mp3", "chunk_after.mp3"]);
+
+    let (status, _) = harness.post("/split-region", &json!({ "media_id": id, "start": 2.0, "end": 1.0 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn precise_region_keeps_video_container() {
+    let harness = Harness::new();
+    let id = harness.add_media("rally.mp4").await;
+
+    let body = json!({ "media_id": id, "start": 1, "end": 2, "precise": true, "crf": 20 });
+    let chunks = paths(&harness.post_json("/split-region", body).await["chunks"]);
+    assert!(chunks.iter().all(|chunk| chunk.ends_with(".mp4")), "{:?}", chunks);
+
+    let body = json!({ "media_id": id, "start": 1, "end": 2, "precise": true, "preset": "warp" });
+    let (status, _) = harness.post("/split-region", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn split_honours_duration_and_format() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    let split = harness
+        .post_json("/split", json!({ "media_id": id, "chunk_duration": 10, "format": "wav" }))
+        .await;
+    let chunks = paths(&split["chunks"]);
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks.iter().all(|chunk| chunk.ends_with(".wav")));
+
+    let merged = harness.post_json("/merge", json!({ "media_id": id, "format": "wav" })).await;
+    assert!(merged["output_file"].as_str().unwrap().ends_with(&format!("{}.merged.wav", id)));
+}
+
+#[tokio::test]
+async fn subtitles_render_as_webvtt() {
+    let harness = Harness::new();
+    let id = harness.add_media("serve.mp4").await;
+
+    let request = Request::post("/transcribe/subtitles")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({ "media_id": id, "format": "vtt" }).to_string()))
+        .unwrap();
+    let response = harness.app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/vtt");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let vtt = String::from_utf8(body.to_vec()).unwrap();
+    assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nWelcome back to Medman Sports.\n"), "{}", vtt);
+
+    let burned = harness.post_json("/media/burn-subtitles", json!({ "media_id": id })).await;
+    assert_eq!(burned["subtitles"], format!("outputs/exports/{}.srt", id));
+    assert!(harness.workspace.resolve(burned["output_file"].as_str().unwrap()).unwrap().exists());
+}
+
+#[tokio::test]
+async fn trim_silence_cuts_detected_dead_air() {
+    let harness = Harness::new();
+    let id = harness.add_media("raw.mp3").await;
+
+    let trimmed = harness.post_json("/media/trim-silence", json!({ "media_id": id, "padding": 0.25 })).await;
+    let silences = trimmed["silences"].as_array().unwrap();
+    assert_eq!(silences.len(), 2);
+    assert_eq!(silences[0]["start"], 1.0);
+    assert_eq!(silences[0]["end"], 2.5);
+    // The trailing silence is closed at the end of the file
+    assert_eq!(silences[1]["end"], 5.0);
+    assert_eq!(trimmed["removed_seconds"], 1.5);
+    assert!(harness.workspace.resolve(trimmed["output_file"].as_str().unwrap()).unwrap().exists());
+}
+
+#[tokio::test]
+async fn loop_checks_duration_and_crossfade() {
+    let harness = Harness::new();
+    let id = harness.add_media("rain.mp3").await;
+
+    let looped = harness.post_json("/media/loop", json!({ "media_id": id, "crossfade": 1, "duration": 12 })).await;
+    assert_eq!(looped["loops"], 3);
+    assert_eq!(looped["output_file"], format!("outputs/exports/{}.loop.mp3", id));
+
+    // The track is 5 seconds long
+    for body in [
+        json!({ "media_id": id, "duration": 0 }),
+        json!({ "media_id": id, "duration": -60 }),
+        json!({ "media_id": id, "loops": 2, "crossfade": -1 }),
+        json!({ "media_id": id, "loops": 2, "crossfade": 5 }),
+    ] {
+        let (status, _) = harness.post("/media/loop", &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn gate_settings_are_range_checked() {
+    let harness = Harness::new();
+    let id = harness.add_media("voice.mp3").await;
+
+    let gated = harness.post_json("/media/gate", json!({ "media_id": id, "threshold_db": -45 })).await;
+    assert_eq!(gated["output_file"], format!("outputs/exports/{}.gated.mp3", id));
+
+    // The recording is 5 seconds long
+    for body in [
+        json!({ "media_id": id, "threshold_db": 6 }),
+        json!({ "media_id": id, "room_tone_start": -1 }),
+        json!({ "media_id": id, "room_tone_duration": 0 }),
+        json!({ "media_id": id, "room_tone_db": 40 }),
+        json!({ "media_id": id, "room_tone_start": 4.5, "room_tone_duration": 1 }),
+    ] {
+        let (status, _) = harness.post("/media/gate", &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn sponsor_insert_point_and_fade_are_checked() {
+    let harness = Harness::new();
+    let video = harness.add_media("rally.mp4").await;
+    let clip = harness.add_media("sponsor.mp4").await;
+
+    let body = json!({ "media_id": video, "clip": clip, "at": 2.5, "fade": 1 });
+    let inserted = harness.post_json("/edit/insert-sponsor", body).await;
+    assert_eq!(inserted["output_file"], format!("outputs/exports/{}.sponsored.mp4", video));
+
+    // Both files are 5 seconds long
+    for (at, fade) in [(-1.0, 0.5), (0.0, 0.5), (5.0, 0.5), (2.5, -0.5), (2.5, 3.0)] {
+        let body = json!({ "media_id": video, "clip": clip, "at": at, "fade": fade });
+        let (status, _) = harness.post("/edit/insert-sponsor", &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn extract_audio_defaults_to_whisper_pcm() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let extracted = harness.post_json("/media/extract-audio", json!({ "media_id": id })).await;
+    assert_eq!(extracted["codec"], "pcm");
+    assert_eq!(extracted["output_file"], format!("outputs/audio/{}.16000.wav", id));
+
+    let mp3 = harness
+        .post_json("/media/extract-audio", json!({ "media_id": id, "codec": "mp3", "sample_rate": 48000 }))
+        .await;
+    assert_eq!(mp3["output_file"], format!("outputs/audio/{}.48000.mp3", id));
+}
+
+#[tokio::test]
+async fn transcribe_returns_segments_and_registers_artifacts() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let transcript = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true, "formats": "srt,json" })).await;
+    let segments = transcript["segments"].as_array().unwrap();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0]["text"], "Welcome back to Medman Sports.");
+    assert_eq!(segments[1]["start"], 2.5);
+    assert_eq!(segments[1]["text"], "Today we work\non the serve.");
+    assert_eq!(transcript["language"], "en");
+    assert!(segments[0].get("words").is_none());
+    assert_eq!(
+        paths(&transcript["artifacts"]),
+        [format!("transcripts/{}.srt", id), format!("transcripts/{}.json", id)]
+    );
+    assert!(!harness.workspace.transcripts_dir().join(format!("{}.txt", id)).exists());
+
+    let records = harness.get_json("/transcripts/artifacts").await;
+    assert_eq!(records["transcripts"][0]["input"], format!("{}.mov", id));
+}
+
+#[tokio::test]
+async fn transcribe_includes_word_timings_on_request() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let transcript = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true, "words": true })).await;
+    let words = transcript["segments"][0]["words"].as_array().unwrap();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0]["word"], "Welcome");
+    assert_eq!(words[1]["start"], 0.6);
+}
+
+#[tokio::test]
+async fn transcribe_labels_speakers() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let transcript = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true, "diarize": true })).await;
+    assert_eq!(transcript["speakers"], json!(["Speaker 1", "Speaker 2"]));
+    assert_eq!(transcript["segments"][0]["speaker"], "Speaker 1");
+    assert_eq!(transcript["segments"][1]["speaker"], "Speaker 2");
+
+    let plain = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true })).await;
+    assert!(plain["segments"][0].get("speaker").is_none());
+
+    // Naming speakers needs an LLM, which the harness doesn't have
+    let body = json!({ "media_id": id, "diarize": true, "name_speakers": true, "wait": true });
+    let (status, _) = harness.post("/transcribe", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn transcribe_cleans_text_on_request() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let transcript = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true, "clean": true })).await;
+    let segments = transcript["segments"].as_array().unwrap();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[1]["start"], 2.5);
+    assert_eq!(segments[1]["text"], "Today we work on the serve.");
+    assert_eq!(segments[1]["raw_text"], "Today we work\non the serve.");
+    assert_eq!(transcript["text"], "Welcome back to Medman Sports. Today we work on the serve.");
+    assert_eq!(transcript["cleanup"]["fillers_removed"], 0);
+
+    let plain = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true })).await;
+    assert!(plain.get("raw_text").is_none());
+    assert!(plain["segments"][0].get("raw_text").is_none());
+
+    // Polishing needs an LLM, which the harness doesn't have
+    let (status, _) = harness.post("/transcribe", &json!({ "media_id": id, "wait": true, "polish": true })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let mut segments: Vec<crate::whisper::Segment> = [
+        "um, so i think the the serve is,",
+        "uh, everything. Uh. Well, I, I mean",
+        "Uh.",
+        "we won 15 15 , right?",
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, text)| crate::whisper::Segment {
+        start: i as f64,
+        end: i as f64 + 1.0,
+        text: text.to_string(),
+        confidence: None,
+        words: Vec::new(),
+        speaker: None,
+    })
+    .collect();
+    let report = crate::cleanup::clean_segments(&mut segments);
+    let texts: Vec<&str> = segments.iter().map(|segment| segment.text.as_str()).collect();
+    assert_eq!(texts, ["So I think the serve is,", "everything. Well, I mean", "", "we won 15 15, right?"]);
+    assert_eq!(report.fillers_removed, 4);
+    assert_eq!(report.repeats_removed, 2);
+}
+
+#[tokio::test]
+async fn search_finds_segments_across_stored_transcripts() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+    harness.post_json("/transcribe", json!({ "media_id": id, "wait": true })).await;
+
+    let found = harness.get_json("/search?q=serves").await;
+    assert_eq!(found["count"], 1);
+    let hit = &found["results"][0];
+    assert_eq!(hit["media_id"], id);
+    assert_eq!(hit["start"], 2.5);
+    assert_eq!(hit["end"], 5.0);
+    assert_eq!(hit["highlighted"], "Today we work\non the [serve].");
+    assert!(hit["audio_url"].as_str().unwrap().starts_with("/media/snippet?"));
+
+    // Every word must match, so nothing says both
+    let none = harness.get_json("/search?q=welcome+serve").await;
+    assert_eq!(none["count"], 0);
+
+    let (status, _) = harness.send(Request::get("/search?q=%22%22").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn transcribe_job_runs_in_background() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let (status, body) = harness.post("/transcribe", &json!({ "media_id": id })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let queued: Value = serde_json::from_slice(&body).unwrap();
+    let status_url = queued["status_url"].as_str().unwrap().to_string();
+
+    let mut job = harness.get_json(&status_url).await;
+    for _ in 0..100 {
+        if job["status"] == "completed" || job["status"] == "failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        job = harness.get_json(&status_url).await;
+    }
+    assert_eq!(job["status"], "completed", "{}", job);
+    assert_eq!(job["progress"], 1.0);
+    assert_eq!(job["result"]["segments"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn metrics_are_exported_for_prometheus() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    harness.post_json("/split", json!({ "media_id": id, "chunk_duration": 30 })).await;
+    let (status, body) = harness.post("/transcribe", &json!({ "media_id": id })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let queued: Value = serde_json::from_slice(&body).unwrap();
+    let status_url = queued["status_url"].as_str().unwrap().to_string();
+    for _ in 0..100 {
+        if harness.get_json(&status_url).await["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let (status, body) = harness.send(Request::get("/metrics").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    let metrics = String::from_utf8(body).unwrap();
+    // Other tests share the registry, so only check that each series exists
+    for series in [
+        r#"http_requests_total{method="POST",route="/split",status="200"}"#,
+        r#"http_request_duration_seconds_count{method="POST",route="/transcribe"}"#,
+        r#"jobs_processed_total{kind="transcribe",status="completed"}"#,
+        r#"stage_duration_seconds_count{stage="transcribe",success="true"}"#,
+        r#"subprocess_duration_seconds_count{program="ffmpeg"}"#,
+        r#"transcription_audio_seconds_total{backend="local"}"#,
+    ] {
+        assert!(metrics.contains(series), "{} missing from\n{}", series, metrics);
+    }
+}
+
+#[tokio::test]
+async fn unknown_job_is_not_found() {
+    let harness = Harness::new();
+
+    let (status, _) = harness.send(Request::get("/jobs/nope").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn transcribe_reports_missing_media() {
+    let harness = Harness::new();
+
+    let (status, body) = harness.post("/transcribe", &json!({ "media_id": "0123456789abcdef" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let response: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["error"], "Media not found: 0123456789abcdef");
+    assert_eq!(response["kind"], "not_found");
+}
+
+#[tokio::test]
+async fn invalid_parameters_are_bad_requests() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    let (status, body) = harness.post("/media/extract-audio", &json!({ "media_id": id, "codec": "flac" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["kind"], "bad_request");
+}
+
+#[tokio::test]
+async fn upload_then_download() {
+    let harness = Harness::new();
+
+    let (status, body) = harness
+        .send(Request::put("/media/upload/clip.mp3").body(Body::from("audio bytes")).unwrap())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let uploaded: Value = serde_json::from_slice(&body).unwrap();
+    let id = uploaded["media_id"].as_str().unwrap();
+    assert_eq!(uploaded["name"], "clip.mp3");
+
+    let uri = format!("/media/download?path=media/{}.mp3", id);
+    let (status, body) = harness.send(Request::get(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, b"audio bytes");
+}
+
+#[tokio::test]
+async fn upload_over_limit_is_rejected() {
+    let harness = Harness::new();
+
+    let (status, _) = harness
+        .send(Request::put("/media/upload/big.mp3").body(Body::from(vec![0u8; 2048])).unwrap())
+        .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    let leftovers = std::fs::read_dir(harness.workspace.media_dir()).unwrap().count();
+    assert_eq!(leftovers, 0);
+}
+
+#[tokio::test]
+async fn trimmed_uploads_record_the_trimmed_content() {
+    use sha2::{Digest, Sha256};
+
+    let harness = Harness::new();
+    let (status, body) = harness
+        .send(Request::put("/media/upload/slate.mp4?trim_intro=true").body(Body::from("raw footage")).unwrap())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let uploaded: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(uploaded["trimmed_intro"]["end"], 1.5);
+
+    let id = uploaded["media_id"].as_str().unwrap();
+    let record = harness.workspace.media_record(id).unwrap();
+    let content = std::fs::read(harness.workspace.media_dir().join(&record.file)).unwrap();
+    assert_ne!(content, b"raw footage");
+    assert_eq!(record.sha256, format!("{:x}", Sha256::digest(&content)));
+    assert_eq!(record.bytes, content.len() as u64);
+    assert_eq!(uploaded["bytes"], record.bytes);
+}
+
+#[tokio::test]
+async fn multipart_upload_returns_media_id() {
+    let harness = Harness::new();
+
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"serve.mp3\"\r\n\
+        Content-Type: audio/mpeg\r\n\r\n\
+        serve audio\r\n\
+        --BOUNDARY--\r\n";
+    let (status, body) = harness
+        .send(
+            Request::post("/media/upload")
+                .header("content-type", "multipart/form-data; boundary=BOUNDARY")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let uploaded: Value = serde_json::from_slice(&body).unwrap();
+    let id = uploaded["media_id"].as_str().unwrap();
+    assert_eq!(id.len(), 16);
+    assert_eq!(uploaded["bytes"], 11);
+
+    let info = harness.get_json(&format!("/media/info?media_id={}", id)).await;
+    assert!(info.get("error").is_none(), "{}", info);
+}
+
+#[tokio::test]
+async fn llm_routes_are_absent_when_disabled() {
+    let harness = Harness::new();
+
+    for uri in ["/chat", "/generate/chapters"] {
+        let (status, _) = harness.post(uri, &json!({ "text": "hi", "media_id": "abc" })).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+    }
+    let (status, _) = harness.send(Request::get("/batch/abc").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn bilingual_language_must_be_a_plain_name() {
+    let harness = Harness::with_features(Features { llm: true, publish: false });
+    let id = harness.add_media("serve.mp3").await;
+
+    for language in ["../../x", "pt/br", "fr.srt"] {
+        let (status, body) = harness
+            .post("/transcribe/bilingual", &json!({ "media_id": id, "language": language }))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", language);
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        assert!(response["error"].as_str().unwrap().starts_with("language may only contain"), "{}", response);
+    }
+}
+
+#[test]
+fn chapters_follow_youtube_rules() {
+    let picked = vec![
+        Chapter::at(4.0, "Welcome"),
+        Chapter::at(9.5, "Too soon"),
+        Chapter::at(135.2, "Serve technique"),
+        Chapter::at(3725.0, "Match play"),
+        Chapter::at(3995.0, "Outro"),
+    ];
+    let chapters = pipeline::arrange_chapters(picked, 4000.0);
+    let lines: Vec<String> = chapters.iter().map(Chapter::line).collect();
+    assert_eq!(lines, ["00:00 Welcome", "02:15 Serve technique", "1:02:05 Match play"]);
+
+    // A late first chapter gets an intro ahead of it
+    let chapters = pipeline::arrange_chapters(vec![Chapter::at(42.0, "Drills")], 600.0);
+    assert_eq!(chapters[0].line(), "00:00 Intro");
+    assert_eq!(chapters[1].seconds(), 42.0);
+}
+
+#[tokio::test]
+async fn benchmark_times_each_stage() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    let report = harness.post_json("/admin/benchmark", json!({ "media_id": id, "model": "tiny" })).await;
+    assert_eq!(report["media_seconds"], 5.0);
+    assert_eq!(report["whisper_model"], "tiny");
+    let stages: Vec<&str> = report["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["stage"].as_str().unwrap())
+        .collect();
+    assert_eq!(stages, ["probe", "split", "merge", "transcribe"]);
+    assert!(report["stages"].as_array().unwrap().iter().all(|s| s["success"] == true));
+}
+
+#[tokio::test]
+async fn transcripts_are_stored_by_media_hash() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let first = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true })).await;
+    assert!(!first["artifacts"].as_array().unwrap().is_empty());
+
+    // Served from the database: same segments, and whisper wrote nothing new
+    let second = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true })).await;
+    assert_eq!(second["segments"], first["segments"]);
+    assert!(second["artifacts"].as_array().unwrap().is_empty());
+
+    let stored = harness.get_json("/transcripts").await;
+    let transcripts = stored["transcripts"].as_array().unwrap();
+    assert_eq!(transcripts.len(), 1);
+    assert!(transcripts[0]["media_hash"].as_str().unwrap().starts_with(&id));
+    assert_eq!(transcripts[0]["options"], "model=base,words=false");
+    assert_eq!(transcripts[0]["segment_count"], 2);
+
+    let transcript = harness.get_json(&format!("/transcripts/{}", id)).await;
+    assert_eq!(transcript["transcripts"][0]["segments"][0]["text"], "Welcome back to Medman Sports.");
+
+    let uri = format!("/transcripts/{}", id);
+    let (status, _) = harness.send(Request::delete(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = harness.send(Request::get(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn templates_are_listed_and_validated() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let templates = harness.get_json("/templates").await;
+    assert_eq!(templates["default"], "med-man-sports");
+    let names: Vec<&str> = templates["templates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["mama-meditations", "med-man-sports"]);
+    assert_eq!(templates["templates"][1]["channel"], "Med Man Sports");
+
+    let (status, body) = harness.post("/transcribe-to-json", &json!({ "media_id": id, "template": "no-such-channel" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response: Value = serde_json::from_slice(&body).unwrap();
+    assert!(response["error"].as_str().unwrap().contains("mama-meditations"));
+}
+
+#[tokio::test]
+async fn thumbnails_at_times_and_most_distinct() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let body = json!({ "media_id": id, "t": [1.5, 4], "width": 1280, "height": 720, "format": "png" });
+    let frames = harness.post_json("/media/thumbnail", body).await;
+    assert_eq!(
+        frames["frames"][1]["path"],
+        format!("outputs/exports/{id}-thumbnails/{id}_4.000_1280x720.png", id = id)
+    );
+
+    // The strongest cut first, then the weaker one, then an evenly spaced
+    // time far enough from both
+    let distinct = harness.post_json("/media/thumbnail", json!({ "media_id": id, "count": 3 })).await;
+    let times: Vec<f64> = distinct["frames"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|frame| frame["time"].as_f64().unwrap())
+        .collect();
+    assert_eq!(times, [0.5, 3.0, 4.166666666666667]);
+
+    for body in [
+        json!({ "media_id": id, "t": [1.5], "count": 3 }),
+        json!({ "media_id": id, "width": 1280 }),
+        json!({ "media_id": id, "t": [-1] }),
+        json!({ "media_id": id, "count": 31 }),
+    ] {
+        let (status, _) = harness.post("/media/thumbnail", &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+
+    let audio = harness.add_media("audio.mp3").await;
+    let (status, _) = harness.post("/media/thumbnail", &json!({ "media_id": audio })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Candidates around the moment a quote was said
+    let body = json!({ "media_id": id, "quote": "welcome back to medman sports", "count": 3 });
+    let quoted = harness.post_json("/media/thumbnail-for-quote", body).await;
+    assert_eq!(quoted["match"]["text"], "Welcome back to Medman Sports.");
+    assert!(!quoted["frames"].as_array().unwrap().is_empty(), "{}", quoted);
+    let (status, _) = harness.post("/media/thumbnail-for-quote", &json!({ "media_id": id, "quote": " " })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Pipeline routes only answer POST
+    let uri = format!("/media/thumbnail?media_id={}", id);
+    let (status, _) = harness.send(Request::get(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn waveform_reports_envelope_and_loudness() {
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    let analysis = harness.get_json(&format!("/media/waveform?media_id={}&samples_per_second=2", id)).await;
+    assert_eq!(analysis["duration"], 1.0);
+    assert_eq!(analysis["peaks"], serde_json::json!([0.5, 0.0]));
+    assert_eq!(analysis["rms"], serde_json::json!([0.5, 0.0]));
+    assert_eq!(analysis["loudness"]["integrated_lufs"], -23.51);
+    assert_eq!(analysis["loudness"]["true_peak_dbtp"], -4.2);
+
+    let uri = format!("/media/waveform?media_id={}&samples_per_second=0", id);
+    let (status, _) = harness.send(Request::get(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn chunked_transcription_stitches_timestamps() {
+    let harness = Harness::new();
+    let id = harness.add_media("podcast.mp3").await;
+
+    // The fake splits into three chunks, each probing as 5 seconds long
+    let transcript = harness
+        .post_json("/transcribe", json!({ "media_id": id, "wait": true, "chunk_minutes": 10, "concurrency": 2, "words": true }))
+        .await;
+    let segments = transcript["segments"].as_array().unwrap();
+    assert_eq!(segments.len(), 6);
+    assert_eq!(segments[2]["start"], 5.0);
+    assert_eq!(segments[2]["words"][1]["start"], 5.6);
+    assert_eq!(segments[5]["end"], 15.0);
+    assert!(!harness.workspace.transcripts_dir().join(format!("{}.chunks", id)).exists());
+}
+
+#[tokio::test]
+async fn transcription_backend_is_selectable() {
+    let harness = Harness::new();
+    let id = harness.add_media("podcast.mp3").await;
+
+    let transcript = harness.post_json("/transcribe-to-json", json!({ "media_id": id, "backend": "local" })).await;
+    assert_eq!(transcript["backend"], "local");
+
+    // Chunking splits work across local whisper runs, so the API can't do it
+    let (status, body) = harness
+        .post("/transcribe", &json!({ "media_id": id, "wait": true, "backend": "api", "chunk_minutes": 10 }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["error"], "chunk_minutes is only supported by the local backend");
+
+    let (status, _) = harness.post("/transcribe", &json!({ "media_id": id, "backend": "cloud" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn whisper_options_are_set_per_request() {
+    let harness = Harness::new();
+    let id = harness.add_media("match.mp3").await;
+
+    let transcript = harness
+        .post_json("/transcribe", json!({ "media_id": id, "wait": true, "model": "medium", "language": "fr", "initial_prompt": "Nadal" }))
+        .await;
+    assert_eq!(transcript["language"], "fr");
+
+    // Stored apart from the default transcript, which is still made fresh
+    let transcript = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true })).await;
+    assert_eq!(transcript["language"], "en");
+    let stored = harness.get_json("/transcripts").await;
+    let mut options: Vec<&str> = stored["transcripts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["options"].as_str().unwrap())
+        .collect();
+    options.sort();
+    let tuned = format!("model=medium,words=false,language=fr,prompt={}", crate::whisper::prompt_digest("Nadal"));
+    assert_eq!(options, ["model=base,words=false", tuned.as_str()]);
+
+    for body in [json!({ "media_id": id, "model": "--help" }), json!({ "media_id": id, "language": "en;fr" })] {
+        let (status, _) = harness.post("/transcribe-to-json", &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn split_job_reports_ffmpeg_progress() {
+    let harness = Harness::new();
+    let id = harness.add_media("match.mp4").await;
+    let mut events = events::bus().subscribe();
+
+    let body = json!({ "media_id": id, "chunk_duration": 2, "background": true });
+    let (status, body) = harness.post("/split", &body).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let queued: Value = serde_json::from_slice(&body).unwrap();
+    let status_url = queued["status_url"].as_str().unwrap().to_string();
+
+    let mut job = harness.get_json(&status_url).await;
+    for _ in 0..100 {
+        if job["status"] == "completed" || job["status"] == "failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        job = harness.get_json(&status_url).await;
+    }
+    assert_eq!(job["status"], "completed", "{}", job);
+    assert_eq!(job["kind"], "split");
+    assert_eq!(job["result"]["chunks"].as_array().unwrap().len(), 3);
+
+    // Other tests share the bus, so only look at this media file's events
+    let media_dir = harness.workspace.media_dir();
+    let mut percents = Vec::new();
+    while let Ok(envelope) = events.try_recv() {
+        if let PipelineEvent::Progress { input, percent } = envelope.event {
+            if Path::new(&input).starts_with(&media_dir) {
+                percents.push(percent);
+            }
+        }
+    }
+    assert_eq!(percents, vec![50, 100]);
+}
+
+#[tokio::test]
+async fn media_library_lists_and_deletes() {
+    let harness = Harness::new();
+    let id = harness.add_media("serve.mp3").await;
+    std::fs::write(harness.workspace.media_dir().join("by-hand.wav"), "audio").unwrap();
+
+    let library = harness.get_json("/media").await;
+    let media = library["media"].as_array().unwrap();
+    assert_eq!(media.len(), 2);
+    let upload = media.iter().find(|entry| entry["id"] == id.as_str()).unwrap();
+    assert_eq!(upload["name"], "serve.mp3");
+    assert_eq!(upload["info"]["duration"], 5.0);
+    assert!(media.iter().any(|entry| entry["id"] == "by-hand.wav"));
+
+    harness.post_json("/split", json!({ "media_id": id })).await;
+    let uri = format!("/media/{}", id);
+    let (status, body) = harness.send(Request::delete(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    let deleted: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(deleted["chunks"], true);
+
+    let (status, _) = harness.send(Request::get(uri.as_str()).body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(harness.get_json("/media").await["media"].as_array().unwrap().len(), 1);
+
+    // Orphaned and interrupted chunk directories go; fresh ones for live media stay
+    let chunks_dir = harness.workspace.chunks_dir();
+    std::fs::create_dir_all(chunks_dir.join("gone.mp3")).unwrap();
+    std::fs::create_dir_all(chunks_dir.join("by-hand.wav")).unwrap();
+    std::fs::create_dir_all(harness.workspace.transcripts_dir().join("long.chunks")).unwrap();
+    let hour = std::time::Duration::from_secs(60 * 60);
+    assert_eq!(library::cleanup_chunks(&harness.workspace, hour).unwrap(), 1);
+    assert!(chunks_dir.join("by-hand.wav").is_dir());
+    assert_eq!(library::cleanup_chunks(&harness.workspace, std::time::Duration::ZERO).unwrap(), 2);
+}
+
+#[tokio::test]
+async fn media_info_is_typed_from_ffprobe() {
+    let harness = Harness::new();
+    let id = harness.add_media("rally.mov").await;
+
+    let info = harness.get_json(&format!("/media/info?media_id={}", id)).await;
+    assert_eq!(info["duration"], 5.0);
+    assert_eq!(info["bitrate"], 128000);
+    assert_eq!(info["video_codec"], "h264");
+    assert_eq!(info["audio_codec"], "mp3");
+    assert_eq!(info["width"], 1920);
+    assert_eq!(info["height"], 1080);
+    assert_eq!(info["fps"], 30.0);
+    assert_eq!(info["channels"], 2);
+    let streams = info["streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 2);
+    assert_eq!(streams[1]["kind"], "video");
+    assert_eq!(streams[1]["language"], "eng");
+}
+
+#[tokio::test]
+async fn normalize_measures_then_applies_loudness() {
+    let harness = Harness::new();
+    let id = harness.add_media("interview.mp4").await;
+
+    let result = harness.post_json("/media/normalize", json!({ "media_id": id })).await;
+    assert_eq!(result["output_file"], format!("outputs/exports/{}.normalized.mp4", id));
+    assert_eq!(result["target_lufs"], -14.0);
+    assert_eq!(result["before"]["integrated_lufs"], -23.51);
+    assert_eq!(result["after"]["integrated_lufs"], -14.02);
+    assert!(harness.workspace.root().join(result["output_file"].as_str().unwrap()).is_file());
+
+    // Transcoding replaces the intermediate normalized file
+    let result = harness
+        .post_json(
+            "/media/normalize",
+            json!({ "media_id": id, "format": "mp3", "bitrate": 128, "sample_rate": 44100 }),
+        )
+        .await;
+    let exports = harness.workspace.exports_dir();
+    assert_eq!(result["output_file"], format!("outputs/exports/{}.normalized.128k.44100hz.mp3", id));
+    assert!(!exports.join(format!("{}.normalized.mp4", id)).exists());
+
+    // Asking for the source's own container needs no transcode
+    let result = harness.post_json("/media/normalize", json!({ "media_id": id, "format": "mp4" })).await;
+    assert_eq!(result["output_file"], format!("outputs/exports/{}.normalized.mp4", id));
+    assert!(harness.workspace.root().join(result["output_file"].as_str().unwrap()).is_file());
+
+    // A failed transcode leaves no intermediate behind
+    std::fs::remove_file(exports.join(format!("{}.normalized.mp4", id))).unwrap();
+    let (status, _) = harness.post("/media/normalize", &json!({ "media_id": id, "bitrate": 8 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(!exports.join(format!("{}.normalized.mp4", id)).exists());
+
+    let (status, _) = harness.post("/media/normalize", &json!({ "media_id": id, "target_lufs": 3 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn config_file_is_overridden_by_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("layout.json"), format!("{{\"version\": {}}}", LAYOUT_VERSION)).unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        format!(
+            "port = 8080\nffmpeg_path = \"/opt/ffmpeg/bin/ffmpeg\"\nwhisper_model = \"small\"\nmedia_dir = {:?}\n",
+            dir.path().join("footage")
+        ),
+    )
+    .unwrap();
+
+    let config = Config::load(Cli {
+        config: Some(path.clone()),
+        port: Some(9000),
+        data_dir: Some(dir.path().to_path_buf()),
+        ..Cli::default()
+    })
+    .unwrap();
+    assert_eq!(config.addr().to_string(), "127.0.0.1:9000");
+    assert_eq!(config.ffmpeg_path, "/opt/ffmpeg/bin/ffmpeg");
+    assert_eq!(config.ffprobe_path, "ffprobe");
+    assert_eq!(config.whisper_model, "small");
+
+    let workspace = Workspace::from_config(&config).open().unwrap();
+    assert_eq!(workspace.media_dir(), dir.path().join("footage"));
+    assert!(workspace.media_dir().is_dir());
+
+    std::fs::write(&path, "prot = 8080\n").unwrap();
+    let error = Config::load(Cli { config: Some(path), ..Cli::default() }).unwrap_err();
+    assert!(error.contains("prot"), "{}", error);
+}
+
+#[tokio::test]
+async fn openai_requests_retry_with_backoff() {
+    use axum::http::HeaderMap;
+    use axum::routing::get;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Unavailable, then rate limited, then fine
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let flaky = move || {
+        let hit = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            let mut headers = HeaderMap::new();
+            match hit {
+                0 => {
+                    headers.insert("retry-after-ms", "10".parse().unwrap());
+                    (StatusCode::SERVICE_UNAVAILABLE, headers, "busy")
+                }
+                1 => {
+                    headers.insert("retry-after", "0".parse().unwrap());
+                    (StatusCode::TOO_MANY_REQUESTS, headers, "rate_limit_exceeded")
+                }
+                _ => (StatusCode::OK, headers, "ok"),
+            }
+        }
+    };
+    let base = serve(
+        Router::new()
+            .route("/flaky", get(flaky))
+            .route("/quota", get(|| async { (StatusCode::TOO_MANY_REQUESTS, "insufficient_quota") }))
+            .route("/slow", get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "late"
+            })),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let policy = crate::openai::RetryPolicy {
+        timeout: Duration::from_millis(200),
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+    };
+
+    let response = policy.send("Flaky", || Ok(client.get(format!("{}/flaky", base)))).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "ok");
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    // Out of credit: waiting won't help, so no retries
+    let error = policy.send("Quota", || Ok(client.get(format!("{}/quota", base)))).await.unwrap_err();
+    assert!(error.to_string().ends_with("429 Too Many Requests: insufficient_quota"), "{}", error);
+
+    let error = policy.send("Slow", || Ok(client.get(format!("{}/slow", base)))).await.unwrap_err();
+    assert!(error.to_string().contains("Slow failed after 3 attempts: no response within 200ms"), "{}", error);
+
+    // Throttled, each attempt takes its own permit; the last settles on real usage
+    let scheduler = LlmScheduler::new(100, 100_000);
+    hits.store(0, Ordering::SeqCst);
+    let (_, mut permit) = policy
+        .send_throttled("Flaky", &scheduler, 50, || Ok(client.get(format!("{}/flaky", base))))
+        .await
+        .unwrap();
+    permit.record_usage(20);
+    assert_eq!(scheduler.usage(), (3, 150));
+    drop(permit);
+    assert_eq!(scheduler.usage(), (3, 120));
+
+    // Failed requests keep their estimate
+    let error = policy.send_throttled("Quota", &scheduler, 50, || Ok(client.get(format!("{}/quota", base)))).await;
+    assert!(error.is_err());
+    assert_eq!(scheduler.usage(), (4, 170));
+}
+
+#[tokio::test]
+async fn finished_jobs_are_posted_to_callback_url() {
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use tokio::sync::mpsc;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let receiver = serve(Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: String| {
+            let tx = tx.clone();
+            async move {
+                tx.send((headers, body)).unwrap();
+                StatusCode::NO_CONTENT
+            }
+        }),
+    ))
+    .await;
+
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let body = json!({ "media_id": id, "callback_url": format!("{}/hook", receiver) });
+    let (status, body) = harness.post("/transcribe", &body).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let queued: Value = serde_json::from_slice(&body).unwrap();
+
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+    let timestamp: u64 = headers[webhooks::TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+    assert_eq!(
+        headers[webhooks::SIGNATURE_HEADER].to_str().unwrap(),
+        webhooks::sign(WEBHOOK_SECRET, timestamp, &body)
+    );
+    let payload: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["event"], "job.completed");
+    assert_eq!(payload["job"]["id"], queued["job_id"]);
+    assert_eq!(payload["job"]["result"]["segments"].as_array().unwrap().len(), 2);
+
+    // The job records the delivery once the receiver has answered
+    let mut job = harness.get_json(queued["status_url"].as_str().unwrap()).await;
+    for _ in 0..100 {
+        if job["webhook"]["delivered"] == true {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        job = harness.get_json(queued["status_url"].as_str().unwrap()).await;
+    }
+    assert_eq!(job["webhook"]["delivered"], true, "{}", job);
+
+    let body = json!({ "media_id": id, "callback_url": "ftp://example.com/hook" });
+    let (status, _) = harness.post("/transcribe", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn batch_reports_each_file_and_writes_summary() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let dir = TempDir::new().unwrap();
+    let queue = BatchQueue::new(2);
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let (counter, peak) = (running.clone(), most.clone());
+    let inputs = ["a.mov", "b.mov", "broken.mov", "c.mov"].map(String::from).to_vec();
+    let id = queue.submit("test", inputs, dir.path().to_path_buf(), "reports", move |input| {
+        let (running, most) = (counter.clone(), peak.clone());
+        async move {
+            most.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            match input.as_str() {
+                "broken.mov" => Err("whisper failed".to_string()),
+                _ => Ok(serde_json::json!({ "title": input })),
+            }
+        }
+    });
+
+    let mut batch = queue.get(&id).unwrap();
+    for _ in 0..100 {
+        if batch.finished_at.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        batch = queue.get(&id).unwrap();
+    }
+    assert_eq!(most.load(Ordering::SeqCst), 2);
+    assert_eq!(batch.summary.total, 4);
+    assert_eq!(batch.summary.completed, 3);
+    assert_eq!(batch.summary.failed, 1);
+    assert_eq!(batch.files[2].error.as_deref(), Some("whisper failed"));
+    assert_eq!(batch.files[3].result.as_ref().unwrap()["title"], "c.mov");
+    assert_eq!(batch.output_file, Some(format!("reports/batch-{}.json", id)));
+
+    let report = dir.path().join(format!("batch-{}.json", id));
+    let written: Value = serde_json::from_slice(&std::fs::read(report).unwrap()).unwrap();
+    assert_eq!(written["summary"]["failed"], 1);
+    assert_eq!(written["files"][0]["input"], "a.mov");
+    assert!(queue.get("nope").is_none());
+}
+
+#[tokio::test]
+async fn hls_preview_is_packaged_and_served() {
+    let harness = Harness::new();
+    let id = harness.add_media("video.mov").await;
+
+    let hls = harness.post_json("/media/hls", json!({ "media_id": id, "segment_duration": 2 })).await;
+    assert_eq!(hls["segments"], 3, "{}", hls);
+    assert_eq!(hls["cached"], false);
+    let playlist_url = hls["playlist_url"].as_str().unwrap().to_string();
+    assert!(playlist_url.ends_with("-2s/index.m3u8"), "{}", playlist_url);
+
+    let response = harness.app.clone().oneshot(Request::get(playlist_url.as_str()).body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/vnd.apple.mpegurl");
+    let playlist = String::from_utf8(response.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap();
+    assert!(playlist.contains("segment_001.ts"), "{}", playlist);
+
+    let segment_url = playlist_url.replace("index.m3u8", "segment_001.ts");
+    let response = harness.app.clone().oneshot(Request::get(segment_url.as_str()).body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.headers()["content-type"], "video/mp2t");
+    assert!(response.headers().get("content-disposition").is_none());
+
+    let again = harness.post_json("/media/hls", json!({ "media_id": id, "segment_duration": 2.0000001 })).await;
+    assert_eq!(again["cached"], true);
+    assert_eq!(again["playlist_url"], playlist_url.as_str());
+
+    let (status, _) = harness.send(Request::get("/hls/x/..%2Fsecret.ts").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    for segment_duration in [0.5, 61.0] {
+        let (status, _) = harness.post("/media/hls", &json!({ "media_id": id, "segment_duration": segment_duration })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn requests_work_in_private_scratch_space() {
+    use crate::process::ProcessLimiter;
+
+    /// Every leftover scratch directory below `dir`
+    fn scratch_dirs(dir: &Path) -> Vec<std::path::PathBuf> {
+        let mut found = Vec::new();
+        for path in std::fs::read_dir(dir).unwrap().filter_map(Result::ok).map(|entry| entry.path()) {
+            if path.is_dir() {
+                if path.file_name().unwrap().to_string_lossy().starts_with(".scratch-") {
+                    found.push(path.clone());
+                }
+                found.extend(scratch_dirs(&path));
+            }
+        }
+        found
+    }
+
+    let harness = Harness::new();
+    let id = harness.add_media("audio.mp3").await;
+
+    // Two splits of the same media at once
+    let splits: Vec<_> = [2, 3]
+        .map(|seconds| {
+            let app = harness.app.clone();
+            let body = json!({ "media_id": id, "chunk_duration": seconds });
+            tokio::spawn(async move {
+                let request = Request::post("/split")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                serde_json::from_slice::<Value>(&body).unwrap()
+            })
+        })
+        .into_iter()
+        .collect();
+    let chunks_dir = harness.workspace.chunks_dir().join(&id);
+    for split in splits {
+        let split = split.await.unwrap();
+        let chunks = paths(&split["chunks"]);
+        assert_eq!(chunks.len(), 3, "{}", split);
+        assert!(chunks.iter().all(|chunk| Path::new(chunk).parent() == Some(chunks_dir.as_path())));
+    }
+    let merged = harness.post_json("/merge", json!({ "media_id": id })).await;
+    assert!(merged.get("error").is_none(), "{}", merged);
+    let transcript = harness.post_json("/transcribe", json!({ "media_id": id, "wait": true, "chunk_minutes": 1 })).await;
+    assert!(transcript.get("error").is_none(), "{}", transcript);
+
+    for dir in [harness.workspace.outputs_dir(), harness.workspace.transcripts_dir()] {
+        assert_eq!(scratch_dirs(&dir), Vec::<std::path::PathBuf>::new());
+    }
+
+    // Past the cap, callers wait for a slot and give up as busy
+    let limiter = ProcessLimiter::new(1);
+    let slot = limiter.acquire(Duration::from_millis(10)).unwrap();
+    let error = limiter.acquire(Duration::from_millis(10)).unwrap_err();
+    assert_eq!(error.status(), StatusCode::SERVICE_UNAVAILABLE);
+    drop(slot);
+    assert!(limiter.acquire(Duration::from_millis(10)).is_ok());
+}
+
+#[tokio::test]
+async fn process_waits_leave_the_runtime_free() {
+    let harness = Harness::new();
+
+    // Work stuck waiting (for a slot, or on its process) on the only runtime
+    // thread would keep every other request from being served
+    let (release, waiting) = std::sync::mpsc::channel::<()>();
+    let work = tokio::spawn(crate::process::blocking(move || {
+        waiting.recv().ok();
+        Ok(42)
+    }));
+    tokio::task::yield_now().await;
+    let (status, _) = harness.send(Request::get("/hello").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    release.send(()).unwrap();
+    assert_eq!(work.await.unwrap().unwrap(), 42);
+}
+
+#[tokio::test]
+async fn llm_providers_share_one_client() {
+    use crate::anthropic::AnthropicClient;
+    use crate::llm::{LlmClient, Provider};
+    use crate::openai::OpenAIClient;
+    use crate::templates::PromptTemplate;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Json;
+
+    // Anthropic: its own headers, and JSON replies continue a prefilled "{"
+    let anthropic = |headers: HeaderMap, Json(body): Json<Value>| async move {
+        assert_eq!(headers["x-api-key"], "test-key");
+        assert_eq!(headers["anthropic-version"], "2023-06-01");
+        assert!(headers.get("authorization").is_none());
+        let last = body["messages"].as_array().unwrap().last().unwrap().clone();
+        let text = if last["role"] == "assistant" {
+            assert_eq!(last["content"], "{");
+            r#""title": "Serve Basics", "description": "Three drills"}"#
+        } else {
+            "Hello from Claude"
+        };
+        Json(json!({
+            "content": [{ "type": "text", "text": text }],
+            "usage": { "input_tokens": 12, "output_tokens": 5 }
+        }))
+    };
+    // Ollama: the OpenAI API, without a key
+    let ollama = |headers: HeaderMap, Json(body): Json<Value>| async move {
+        assert!(headers.get("authorization").is_none());
+        assert_eq!(body["model"], "llama3.1");
+        let content = if body["response_format"]["type"] == "json_object" {
+            r#"{"title": "Serve Basics", "description": "Three drills", "tags": ["tennis"]}"#
+        } else {
+            "Hello from llama"
+        };
+        Json(json!({ "choices": [{ "message": { "content": content } }] }))
+    };
+    let base = serve(
+        Router::new()
+            .route("/anthropic/v1/messages", post(anthropic))
+            .route("/ollama/v1/chat/completions", post(ollama)),
+    )
+    .await;
+
+    let claude = LlmClient::with_backend(Box::new(
+        AnthropicClient::builder()
+            .base_url(&format!("{}/anthropic/v1", base))
+            .api_key("test-key")
+            .model("claude-test")
+            .build()
+            .unwrap(),
+    ));
+    let llama = LlmClient::with_backend(Box::new(
+        OpenAIClient::ollama()
+            .base_url(&format!("{}/ollama/v1", base))
+            .model("llama3.1")
+            .build()
+            .unwrap(),
+    ));
+    assert_eq!(claude.provider(), Provider::Anthropic);
+    assert_eq!(llama.provider(), Provider::Ollama);
+
+    let before = scheduler(Provider::Anthropic).usage();
+    let completion = claude.complete("Hi").await.unwrap();
+    assert_eq!(completion.content, "Hello from Claude");
+    assert_eq!(completion.usage.unwrap().total_tokens, 17);
+    assert_eq!(llama.complete("Hi").await.unwrap().content, "Hello from llama");
+
+    let template = PromptTemplate::from_name(None).unwrap();
+    for client in [&claude, &llama] {
+        let content = client.generate_youtube_content("We work on the serve.", "Serve", &template).await.unwrap();
+        assert_eq!(content.title, "Serve Basics");
+        assert_eq!(content.description, "Three drills");
+    }
+
+    // Each provider draws on its own budget; local models aren't throttled
+    assert!(scheduler(Provider::Anthropic).usage().0 > before.0);
+    assert!(!std::ptr::eq(scheduler(Provider::Anthropic), scheduler(Provider::OpenAI)));
+    assert_eq!(scheduler(Provider::Ollama).usage(), (0, 0));
+}
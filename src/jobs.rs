@@ -0,0 +1,188 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::telemetry;
+use crate::webhooks::{WebhookDelivery, WebhookSender};
+
+/// Finished jobs are forgotten this long after they complete
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A unit of background work and what is known about it so far
+#[derive(Clone, Debug, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub status: JobStatus,
+    /// Fraction done in [0, 1], when the work reports it
+    pub progress: Option<f64>,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    /// Delivery of the job's callback, when it was submitted with one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<WebhookDelivery>,
+}
+
+/// Handed to running work so it can report progress
+#[derive(Clone)]
+pub struct JobHandle {
+    id: String,
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+}
+
+impl JobHandle {
+    pub fn set_progress(&self, progress: f64) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&self.id) {
+            job.progress = Some(progress.clamp(0.0, 1.0));
+        }
+    }
+}
+
+/// In-memory queue for long-running work (transcriptions and the like).
+///
+/// Jobs run on tokio's blocking pool, at most `workers` at a time; the rest
+/// wait in submission order. State is lost on restart.
+pub struct JobQueue {
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+    workers: Arc<Semaphore>,
+    next_id: AtomicU64,
+    webhooks: WebhookSender,
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl JobQueue {
+    pub fn new(workers: usize, webhooks: WebhookSender) -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+            next_id: AtomicU64::new(1),
+            webhooks,
+        }
+    }
+
+    /// Worker count from JOB_WORKERS (default 2), webhooks from
+    /// `WebhookSender::from_env`
+    pub fn from_env() -> Self {
+        let workers = std::env::var("JOB_WORKERS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(2);
+        Self::new(workers, WebhookSender::from_env())
+    }
+
+    /// Queue `work` and return the new job's ID. `work` runs on a blocking
+    /// thread; its `Ok` value becomes the job result and its `Err` the error.
+    /// With a `callback` URL, the finished job is POSTed there.
+    pub fn submit<F>(&self, kind: &str, callback: Option<String>, work: F) -> String
+    where
+        F: FnOnce(&JobHandle) -> Result<Value, String> + Send + 'static,
+    {
+        let id = format!("{:x}-{}", now(), self.next_id.fetch_add(1, Ordering::Relaxed));
+        let job = Job {
+            id: id.clone(),
+            kind: kind.to_string(),
+            status: JobStatus::Queued,
+            progress: None,
+            result: None,
+            error: None,
+            created_at: now(),
+            started_at: None,
+            finished_at: None,
+            webhook: callback.as_ref().map(|url| WebhookDelivery {
+                url: url.clone(),
+                delivered: false,
+                error: None,
+            }),
+        };
+
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let cutoff = now().saturating_sub(FINISHED_JOB_TTL.as_secs());
+            jobs.retain(|_, job| job.finished_at.is_none_or(|at| at >= cutoff));
+            jobs.insert(id.clone(), job);
+        }
+        tracing::info!("Queued {} job {}", kind, id);
+
+        let handle = JobHandle { id: id.clone(), jobs: self.jobs.clone() };
+        let workers = self.workers.clone();
+        let webhooks = self.webhooks.clone();
+        let kind = kind.to_string();
+        // The job's logs stay under the request that queued it
+        let span = tracing::info_span!("job", job_id = %id, kind = %kind);
+        tokio::spawn(async move {
+            let permit = workers.acquire_owned().await.expect("job semaphore closed");
+            update(&handle, |job| {
+                job.status = JobStatus::Running;
+                job.started_at = Some(now());
+            });
+
+            let running = handle.clone();
+            let blocking_span = tracing::Span::current();
+            let outcome = tokio::task::spawn_blocking(move || blocking_span.in_scope(|| work(&running)))
+                .await
+                .unwrap_or_else(|e| Err(format!("Job panicked: {}", e)));
+            telemetry::metrics().job_finished(&kind, outcome.is_ok());
+
+            update(&handle, |job| {
+                job.finished_at = Some(now());
+                match outcome {
+                    Ok(result) => {
+                        job.status = JobStatus::Completed;
+                        job.progress = Some(1.0);
+                        job.result = Some(result);
+                    }
+                    Err(error) => {
+                        job.status = JobStatus::Failed;
+                        job.error = Some(error);
+                    }
+                }
+            });
+            tracing::info!("Finished job {}", handle.id);
+            // A slow receiver must not hold up the next job
+            drop(permit);
+
+            let finished = handle.jobs.lock().unwrap().get(&handle.id).cloned();
+            if let (Some(url), Some(job)) = (callback, finished) {
+                let delivery = webhooks.deliver(&url, &job).await;
+                update(&handle, |job| job.webhook = Some(delivery));
+            }
+        }.instrument(span));
+
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+}
+
+fn update(handle: &JobHandle, change: impl FnOnce(&mut Job)) {
+    if let Some(job) = handle.jobs.lock().unwrap().get_mut(&handle.id) {
+        change(job);
+    }
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::ProcessorError;
+use crate::ffmpeg::{FFmpegClient, MediaInfo};
+use crate::workspace::Workspace;
+
+/// Suffix of the record file written next to each transcript's artifacts
+const RECORD_SUFFIX: &str = ".record.json";
+
+/// A transcription run and the whisper files it left in the transcripts
+/// directory, stored as `<stem>.record.json` beside them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    /// Source media file name
+    pub input: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Artifact file names, relative to the transcripts directory
+    pub artifacts: Vec<String>,
+}
+
+/// A file in the media directory, uploaded or placed there by hand
+#[derive(Debug, Serialize)]
+pub struct MediaEntry {
+    /// What routes take as `media_id`: the upload's ID, or the file name for
+    /// files placed by hand
+    pub id: String,
+    /// File name as uploaded
+    pub name: String,
+    /// File name within `media/`
+    pub file: String,
+    pub bytes: u64,
+    /// Seconds since the Unix epoch: upload time, else the file's mtime
+    pub created_at: u64,
+    /// Probed stream details; absent when ffmpeg can't read the file
+    pub info: Option<MediaInfo>,
+}
+
+/// How long generated files are kept
+#[derive(Clone, Copy, Debug)]
+pub struct Retention {
+    /// WHISPER_ARTIFACT_RETENTION_DAYS; unset keeps whisper artifacts forever
+    pub whisper_artifacts: Option<Duration>,
+    /// CHUNK_RETENTION_HOURS (default 24); `0` keeps chunk directories forever
+    pub chunks: Option<Duration>,
+}
+
+impl Retention {
+    pub fn from_env() -> Self {
+        let whisper_artifacts = std::env::var("WHISPER_ARTIFACT_RETENTION_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        let chunks = std::env::var("CHUNK_RETENTION_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map_or(Some(24), |hours| (hours > 0).then_some(hours))
+            .map(|hours| Duration::from_secs(hours * 60 * 60));
+        Self { whisper_artifacts, chunks }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn record_path(dir: &Path, input: &Path) -> PathBuf {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("transcript");
+    dir.join(format!("{}{}", stem, RECORD_SUFFIX))
+}
+
+/// Record the artifacts of a transcription, replacing any earlier record for
+/// the same input
+pub fn register_transcript(dir: &Path, input: &Path, artifacts: &[PathBuf]) -> Result<(), std::io::Error> {
+    let record = TranscriptRecord {
+        input: input
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        created_at: now(),
+        artifacts: artifacts
+            .iter()
+            .filter_map(|path| path.file_name().and_then(|s| s.to_str()))
+            .map(|name| name.to_string())
+            .collect(),
+    };
+
+    std::fs::write(record_path(dir, input), serde_json::to_string_pretty(&record)?)
+}
+
+/// Every transcript record in `dir`, newest first
+pub fn transcript_records(dir: &Path) -> Vec<TranscriptRecord> {
+    let mut records: Vec<TranscriptRecord> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|name| name.ends_with(RECORD_SUFFIX))
+            })
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+    records
+}
+
+/// Delete the artifacts and records of transcripts older than `max_age`.
+/// Returns the number of records removed.
+pub fn cleanup_transcripts(dir: &Path, max_age: Duration) -> Result<usize, std::io::Error> {
+    let cutoff = now().saturating_sub(max_age.as_secs());
+    let mut removed = 0;
+
+    for record in transcript_records(dir) {
+        if record.created_at >= cutoff {
+            continue;
+        }
+        for artifact in &record.artifacts {
+            let path = dir.join(artifact);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        std::fs::remove_file(record_path(dir, Path::new(&record.input)))?;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+/// Delete chunk directories not touched for `max_age`: `/split` output
+/// under `outputs/chunks`, and the `<stem>.chunks` directories a chunked
+/// transcription leaves in `transcripts/` if it dies mid-way. Chunks of
+/// media that no longer exists go whatever their age. Returns the number of
+/// directories removed.
+pub fn cleanup_chunks(workspace: &Workspace, max_age: Duration) -> Result<usize, std::io::Error> {
+    let cutoff = SystemTime::now().checked_sub(max_age).unwrap_or(UNIX_EPOCH);
+    let stale = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified < cutoff)
+    };
+
+    let mut removed = 0;
+    for (dir, orphaned) in chunk_dirs(workspace) {
+        if orphaned || stale(&dir) {
+            std::fs::remove_dir_all(&dir)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Every chunk directory, and whether its media is gone
+fn chunk_dirs(workspace: &Workspace) -> Vec<(PathBuf, bool)> {
+    let subdirs = |dir: PathBuf| -> Vec<PathBuf> {
+        match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    };
+
+    let mut dirs: Vec<(PathBuf, bool)> = subdirs(workspace.chunks_dir())
+        .into_iter()
+        .map(|dir| {
+            let id = dir.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            let orphaned = workspace.media_path(id).is_none();
+            (dir, orphaned)
+        })
+        .collect();
+    // Transcription chunks are removed as soon as the run finishes, so any
+    // left over belong to a run that was interrupted
+    dirs.extend(
+        subdirs(workspace.transcripts_dir())
+            .into_iter()
+            .filter(|dir| dir.extension().and_then(|s| s.to_str()) == Some("chunks"))
+            .map(|dir| (dir, false)),
+    );
+    dirs
+}
+
+/// Apply the retention policy now and then hourly
+pub fn spawn_retention(workspace: Workspace, retention: Retention) {
+    if retention.whisper_artifacts.is_none() && retention.chunks.is_none() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            if let Some(max_age) = retention.whisper_artifacts {
+                match cleanup_transcripts(&workspace.transcripts_dir(), max_age) {
+                    Ok(0) => {}
+                    Ok(removed) => tracing::info!("Retention: removed {} expired transcript(s)", removed),
+                    Err(e) => tracing::warn!("Retention cleanup failed: {}", e),
+                }
+            }
+            if let Some(max_age) = retention.chunks {
+                match cleanup_chunks(&workspace, max_age) {
+                    Ok(0) => {}
+                    Ok(removed) => tracing::info!("Retention: removed {} stale chunk director(ies)", removed),
+                    Err(e) => tracing::warn!("Chunk cleanup failed: {}", e),
+                }
+            }
+        }
+    });
+}
+
+/// Every file in the media directory, newest first. Hand-placed files and
+/// uploads alike; partial uploads and subdirectories (ambient beds) are left out.
+pub fn list_media(workspace: &Workspace) -> Result<Vec<MediaEntry>, ProcessorError> {
+    let records: HashMap<String, _> = workspace
+        .media_records()
+        .into_iter()
+        .map(|record| (record.file.clone(), record))
+        .collect();
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(workspace.media_dir())?.filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(file) = path.file_name().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if !path.is_file() || file.starts_with('.') {
+            continue;
+        }
+        let id = records.get(&file).map_or_else(|| file.clone(), |record| record.id.clone());
+        entries.push(media_entry(workspace, &id, &path)?);
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+    Ok(entries)
+}
+
+/// One media file by ID
+pub fn get_media(workspace: &Workspace, id: &str) -> Result<MediaEntry, ProcessorError> {
+    let path = workspace.require_media(id)?;
+    media_entry(workspace, id, &path)
+}
+
+fn media_entry(workspace: &Workspace, id: &str, path: &Path) -> Result<MediaEntry, ProcessorError> {
+    let metadata = std::fs::metadata(path)?;
+    let file = path.file_name().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    let record = workspace.media_record(id);
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let info = match FFmpegClient::new().with_input(path).get_info() {
+        Ok(info) => Some(info),
+        Err(e) => {
+            tracing::warn!("Could not probe {:?}: {}", path, e);
+            None
+        }
+    };
+
+    Ok(MediaEntry {
+        id: id.to_string(),
+        name: record.as_ref().map_or_else(|| file.clone(), |record| record.name.clone()),
+        file,
+        bytes: metadata.len(),
+        created_at: record.map_or(mtime, |record| record.uploaded_at),
+        info,
+    })
+}
+
+/// What `delete_media` removed
+#[derive(Debug, Serialize)]
+pub struct DeletedMedia {
+    pub id: String,
+    pub file: String,
+    /// Whether a `/split` chunk directory went with it
+    pub chunks: bool,
+}
+
+/// Delete a media file, its upload record and its chunks. Transcripts and
+/// generated content stored for it are kept; `DELETE /transcripts/:hash`
+/// removes those.
+pub fn delete_media(workspace: &Workspace, id: &str) -> Result<DeletedMedia, ProcessorError> {
+    let path = workspace.require_media(id)?;
+    std::fs::remove_file(&path)?;
+    workspace.delete_media_record(id)?;
+
+    let chunks_dir = workspace.chunks_dir().join(id);
+    let chunks = chunks_dir.is_dir();
+    if chunks {
+        std::fs::remove_dir_all(&chunks_dir)?;
+    }
+
+    tracing::info!("Deleted media {} ({:?})", id, path);
+    Ok(DeletedMedia {
+        id: id.to_string(),
+        file: path.file_name().and_then(|s| s.to_str()).unwrap_or_default().to_string(),
+        chunks,
+    })
+}
@@ -0,0 +1,714 @@
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::anthropic::AnthropicClient;
+use crate::config::config;
+use crate::error::ProcessorError;
+use crate::events::Stage;
+use crate::glossary::Glossary;
+use crate::openai::OpenAIClient;
+use crate::templates::PromptTemplate;
+
+/// Which LLM service content generation runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// OpenAI's chat completions API (OPENAI_API_KEY)
+    #[default]
+    #[value(name = "openai")]
+    OpenAI,
+    /// Anthropic's Messages API (ANTHROPIC_API_KEY)
+    Anthropic,
+    /// A local Ollama server, or any other server speaking the OpenAI
+    /// chat completions API without a key
+    Ollama,
+}
+
+impl Provider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai",
+            Provider::Anthropic => "anthropic",
+            Provider::Ollama => "ollama",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "openai" => Some(Provider::OpenAI),
+            "anthropic" => Some(Provider::Anthropic),
+            "ollama" => Some(Provider::Ollama),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// One turn of a conversation with the model
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self { role: Role::User, content: content.into() }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self { role: Role::Assistant, content: content.into() }
+    }
+}
+
+/// Tokens a request was billed for, as the API reports them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+/// A model reply and what it cost
+#[derive(Debug, Clone, Serialize)]
+pub struct Completion {
+    pub content: String,
+    /// `None` when the API didn't report usage
+    pub usage: Option<TokenUsage>,
+}
+
+/// A model the content pipelines can talk to. Implementations own their
+/// HTTP client, credentials and model settings, and report to their
+/// provider's LLM scheduler.
+pub trait CompletionBackend: Send + Sync {
+    fn provider(&self) -> Provider;
+
+    fn model(&self) -> &str;
+
+    /// Send a single prompt and return the reply with its token usage
+    fn complete<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Completion, ProcessorError>>;
+
+    /// Continue a conversation (ending with the user's turn) whose reply
+    /// must be a JSON object, using the provider's JSON mode if it has one.
+    /// The prompt must still ask for JSON.
+    fn generate_structured<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<Completion, ProcessorError>>;
+
+    /// Like `complete`, but yields the reply piece by piece as the model
+    /// produces it. Errors before the first piece are returned directly.
+    /// Backends that can't stream send the whole reply as one piece.
+    fn complete_stream<'a>(
+        &'a self,
+        prompt: &'a str,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String, ProcessorError>>, ProcessorError>> {
+        Box::pin(async move {
+            let completion = self.complete(prompt).await?;
+            let pieces: BoxStream<'static, Result<String, ProcessorError>> =
+                Box::pin(stream::once(async move { Ok(completion.content) }));
+            Ok(pieces)
+        })
+    }
+}
+
+/// Configures an `LlmClient`: the provider (LLM_PROVIDER when unset) and
+/// overrides of that provider's configured model settings
+#[derive(Default)]
+pub struct LlmClientBuilder {
+    provider: Option<Provider>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+impl LlmClientBuilder {
+    pub fn provider(&mut self, provider: Provider) -> &mut Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    pub fn model(&mut self, model: &str) -> &mut Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    pub fn temperature(&mut self, temperature: f32) -> &mut Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(&mut self, max_tokens: u32) -> &mut Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn build(&self) -> Result<LlmClient, ProcessorError> {
+        let provider = self.provider.unwrap_or(config().llm_provider);
+        let backend: Box<dyn CompletionBackend> = match provider {
+            Provider::OpenAI | Provider::Ollama => {
+                let mut builder = if provider == Provider::Ollama {
+                    OpenAIClient::ollama()
+                } else {
+                    OpenAIClient::builder()
+                };
+                if let Some(model) = &self.model {
+                    builder.model(model);
+                }
+                if let Some(temperature) = self.temperature {
+                    builder.temperature(temperature);
+                }
+                if let Some(max_tokens) = self.max_tokens {
+                    builder.max_tokens(max_tokens);
+                }
+                Box::new(builder.build()?)
+            }
+            Provider::Anthropic => {
+                let mut builder = AnthropicClient::builder();
+                if let Some(model) = &self.model {
+                    builder.model(model);
+                }
+                if let Some(temperature) = self.temperature {
+                    builder.temperature(temperature);
+                }
+                if let Some(max_tokens) = self.max_tokens {
+                    builder.max_tokens(max_tokens);
+                }
+                Box::new(builder.build()?)
+            }
+        };
+        Ok(LlmClient { backend })
+    }
+}
+
+/// Content generation on top of whichever `CompletionBackend` is configured
+pub struct LlmClient {
+    backend: Box<dyn CompletionBackend>,
+}
+
+/// A proposed fix for a low-confidence transcript segment, for a reviewer to
+/// accept or reject
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorrectionSuggestion {
+    pub segment: usize,
+    pub original: String,
+    pub suggestion: String,
+    pub reason: String,
+}
+
+impl LlmClient {
+    /// A client for `provider` (LLM_PROVIDER when unset) with its
+    /// configured model settings
+    pub fn new(provider: Option<Provider>) -> Result<Self, ProcessorError> {
+        let mut builder = Self::builder();
+        if let Some(provider) = provider {
+            builder.provider(provider);
+        }
+        builder.build()
+    }
+
+    pub fn builder() -> LlmClientBuilder {
+        LlmClientBuilder::default()
+    }
+
+    /// A client on an already configured backend
+    #[cfg(test)]
+    pub fn with_backend(backend: Box<dyn CompletionBackend>) -> Self {
+        Self { backend }
+    }
+
+    pub fn provider(&self) -> Provider {
+        self.backend.provider()
+    }
+
+    pub fn model(&self) -> &str {
+        self.backend.model()
+    }
+
+    /// Send a single prompt and return the reply with its token usage
+    pub async fn complete(&self, prompt: &str) -> Result<Completion, ProcessorError> {
+        let stage = Stage::start("llm_completion");
+        stage.finish(self.backend.complete(prompt).await)
+    }
+
+    /// `complete`, yielding the reply piece by piece as it arrives
+    pub async fn complete_stream(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'static, Result<String, ProcessorError>>, ProcessorError> {
+        self.backend.complete_stream(prompt).await
+    }
+
+    /// `complete` for the pipelines here, which only need the text
+    async fn complete_text(&self, prompt: &str) -> Result<String, ProcessorError> {
+        Ok(self.backend.complete(prompt).await?.content)
+    }
+
+    /// Generate a title, description, tags and chapters for a video. The
+    /// model is held to JSON output; a reply that still doesn't fit
+    /// `YouTubeContent` is sent back with the error, up to
+    /// `YOUTUBE_CONTENT_ATTEMPTS` times.
+    pub async fn generate_youtube_content(
+        &self, 
+        transcript: &str,
+        video_title: &str,
+        template: &PromptTemplate
+    ) -> Result<YouTubeContent, ProcessorError> {
+        tracing::info!("Using template: {}", template.name());
+
+        // Fill in the transcript, title and channel
+        let mut prompt = template.render(transcript, video_title);
+        prompt.push_str(&Glossary::load(template).prompt_section());
+        prompt.push_str(YOUTUBE_CONTENT_FORMAT);
+
+        let stage = Stage::start("llm_youtube_content");
+        stage.finish(self.request_youtube_content(prompt).await)
+    }
+
+    async fn request_youtube_content(&self, prompt: String) -> Result<YouTubeContent, ProcessorError> {
+        let mut messages = vec![Message::user(prompt)];
+
+        let mut attempt = 1;
+        loop {
+            tracing::info!("Sending prompt to {} (attempt {})...", self.provider().name(), attempt);
+            let reply = self.backend.generate_structured(&messages).await?.content;
+
+            let error = match parse_json_reply::<YouTubeContent>(&reply) {
+                Ok(content) if !content.title.trim().is_empty() => return Ok(content),
+                Ok(_) => "the title is empty".to_string(),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= YOUTUBE_CONTENT_ATTEMPTS {
+                return Err(ProcessorError::OpenAIError(format!(
+                    "No usable YouTube content after {} attempts: {}",
+                    attempt, error
+                )));
+            }
+
+            tracing::warn!("Invalid YouTube content ({}), asking again", error);
+            messages.push(Message::assistant(reply));
+            messages.push(Message::user(format!(
+                "That reply could not be used: {}.{}",
+                error, YOUTUBE_CONTENT_FORMAT
+            )));
+            attempt += 1;
+        }
+    }
+
+    /// Write short promotional posts for social platforms from a video's
+    /// title and description. Returns the model's JSON object of platform -> post.
+    pub async fn generate_social_copy(
+        &self,
+        title: &str,
+        description: &str,
+        template: &PromptTemplate,
+    ) -> Result<String, ProcessorError> {
+        let prompt = format!(
+            "You write social media posts for {}. A new YouTube video is going up:\n\n\
+            Title: {}\n\nDescription:\n{}\n\n\
+            Write one post each for X/Twitter (under 280 characters), Instagram (with hashtags) \
+            and a community tab post, in the channel's voice, each pointing people to the video.{}\n\n\
+            Respond with only a JSON object with keys \"twitter\", \"instagram\" and \"community\".",
+            template.domain(),
+            title,
+            description,
+            Glossary::load(template).prompt_section()
+        );
+
+        self.complete_text(&prompt).await
+    }
+
+    /// Write a sponsor-read script in the channel's voice from the sponsor's
+    /// talking points, tied into what the video is about
+    pub async fn generate_sponsor_script(
+        &self,
+        sponsor: &str,
+        talking_points: &str,
+        transcript: &str,
+        template: &PromptTemplate,
+    ) -> Result<String, ProcessorError> {
+        let prompt_template = fs::read_to_string("prompt_dev/sponsor.md")?;
+
+        let mut prompt = prompt_template
+            .replace("{{CHANNEL}}", template.domain())
+            .replace("{{SPONSOR_NAME}}", sponsor)
+            .replace("{{TALKING_POINTS}}", talking_points)
+            .replace("{{TRANSCRIPT_TEXT}}", transcript);
+        prompt.push_str(&Glossary::load(template).prompt_section());
+
+        self.complete_text(&prompt).await
+    }
+
+    /// Ask the model for corrections to low-confidence segments. Suggestions are
+    /// constrained to misheard homophones and domain terms, and anything that
+    /// looks like a rewrite is discarded.
+    pub async fn suggest_corrections(
+        &self,
+        segments: &[(usize, String)],
+        template: &PromptTemplate,
+    ) -> Result<Vec<CorrectionSuggestion>, ProcessorError> {
+        if segments.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let numbered: Vec<serde_json::Value> = segments
+            .iter()
+            .map(|(index, text)| serde_json::json!({ "segment": index, "text": text }))
+            .collect();
+
+        let prompt = format!(
+            "These lines come from an automatic speech transcript of a video on {}. \
+            The speech recognizer had low confidence in them. \
+            For each line, only propose a correction if a word was likely misheard: \
+            a homophone, a near-sounding word, or a misspelled name or domain term. \
+            Do not rephrase, fix grammar, or change meaning. Skip lines that look right.\n\n\
+            Respond with only a JSON array of objects with keys \
+            \"segment\" (number), \"original\", \"suggestion\" and \"reason\".{}\n\n{:#}",
+            template.domain(),
+            Glossary::load(template).prompt_section(),
+            serde_json::Value::Array(numbered)
+        );
+
+        let response = self.complete_text(&prompt).await?;
+        let suggestions: Vec<CorrectionSuggestion> = parse_json_reply(&response)?;
+
+        Ok(suggestions
+            .into_iter()
+            .filter(|s| {
+                let known = segments.iter().any(|(index, _)| *index == s.segment);
+                let changed = s.suggestion.trim() != s.original.trim();
+                let delta = s.suggestion.split_whitespace().count()
+                    .abs_diff(s.original.split_whitespace().count());
+                known && changed && delta <= MAX_CORRECTION_WORD_DELTA
+            })
+            .collect())
+    }
+
+    /// Ask the model for the most exciting moments of a recording, given its
+    /// transcript as `(start, end, text)` segments. Highlights outside the
+    /// transcript or with an empty range are dropped.
+    pub async fn find_highlights(
+        &self,
+        segments: &[(f64, f64, String)],
+        max_clips: usize,
+        template: &PromptTemplate,
+    ) -> Result<Vec<Highlight>, ProcessorError> {
+        if segments.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let transcript: Vec<serde_json::Value> = segments
+            .iter()
+            .map(|(start, end, text)| serde_json::json!({ "start": start, "end": end, "text": text.trim() }))
+            .collect();
+
+        let prompt_template = fs::read_to_string("prompt_dev/highlights.md")?;
+        let mut prompt = prompt_template
+            .replace("{{CHANNEL}}", template.domain())
+            .replace("{{MAX_CLIPS}}", &max_clips.to_string())
+            .replace("{{TRANSCRIPT_SEGMENTS}}", &format!("{:#}", serde_json::Value::Array(transcript)));
+        prompt.push_str(&Glossary::load(template).prompt_section());
+
+        let stage = Stage::start("llm_highlights");
+        let response = stage.finish(self.complete_text(&prompt).await)?;
+        let highlights: Vec<Highlight> = parse_json_reply(&response)?;
+
+        let transcript_end = segments.iter().map(|(_, end, _)| *end).fold(0.0, f64::max);
+        Ok(highlights
+            .into_iter()
+            .filter(|h| h.start >= 0.0 && h.end > h.start && h.start < transcript_end)
+            .take(max_clips)
+            .collect())
+    }
+
+    /// Ask the model where a video's sections start, given its transcript as
+    /// `(start, end, text)` segments. Each segment goes in as one line led by
+    /// its start time, so the model can only pick times that were spoken.
+    /// Chapters outside the transcript are dropped; the rest come back in
+    /// playback order.
+    pub async fn find_chapters(
+        &self,
+        segments: &[(f64, f64, String)],
+        template: &PromptTemplate,
+    ) -> Result<Vec<Chapter>, ProcessorError> {
+        if segments.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let transcript: Vec<String> = segments
+            .iter()
+            .map(|(start, _, text)| format!("[{:.1}] {}", start, text.trim()))
+            .collect();
+
+        let prompt_template = fs::read_to_string("prompt_dev/chapters.md")?;
+        let mut prompt = prompt_template
+            .replace("{{CHANNEL}}", template.domain())
+            .replace("{{TRANSCRIPT_SEGMENTS}}", &transcript.join("\n"));
+        prompt.push_str(&Glossary::load(template).prompt_section());
+
+        let stage = Stage::start("llm_chapters");
+        let response = stage.finish(self.complete_text(&prompt).await)?;
+        let replies: Vec<ChapterReply> = parse_json_reply(&response)?;
+
+        let transcript_end = segments.iter().map(|(_, end, _)| *end).fold(0.0, f64::max);
+        let mut chapters: Vec<Chapter> = replies
+            .into_iter()
+            .filter(|c| c.start >= 0.0 && c.start < transcript_end && !c.title.trim().is_empty())
+            .map(|c| Chapter::at(c.start, c.title.trim()))
+            .collect();
+        chapters.sort_by(|a, b| a.seconds().total_cmp(&b.seconds()));
+        Ok(chapters)
+    }
+
+    /// Ask the model who the diarized speakers are, given `(speaker, text)`
+    /// lines. Only the start of long transcripts is sent, since that is where
+    /// people usually get introduced. Returns a name for each label the model
+    /// could identify; unknown labels and duplicate names are left out.
+    pub async fn name_speakers(
+        &self,
+        lines: &[(String, String)],
+        template: &PromptTemplate,
+    ) -> Result<HashMap<String, String>, ProcessorError> {
+        let mut transcript = Vec::new();
+        let mut length = 0;
+        for (speaker, text) in lines {
+            let line = format!("[{}] {}", speaker, text.trim());
+            length += line.len() + 1;
+            if length > MAX_SPEAKER_CONTEXT_CHARS {
+                break;
+            }
+            transcript.push(line);
+        }
+        if transcript.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let prompt_template = fs::read_to_string("prompt_dev/speakers.md")?;
+        let mut prompt = prompt_template
+            .replace("{{CHANNEL}}", template.domain())
+            .replace("{{TRANSCRIPT_SEGMENTS}}", &transcript.join("\n"));
+        prompt.push_str(&Glossary::load(template).prompt_section());
+
+        let stage = Stage::start("llm_speakers");
+        let response = stage.finish(self.complete_text(&prompt).await)?;
+        let replies: HashMap<String, Option<String>> = parse_json_reply(&response)?;
+
+        let mut names: HashMap<String, String> = HashMap::new();
+        let mut labels: Vec<&String> = replies.keys().collect();
+        labels.sort();
+        for label in labels {
+            let Some(name) = replies[label].as_deref().map(str::trim).filter(|name| !name.is_empty()) else {
+                continue;
+            };
+            let known = lines.iter().any(|(speaker, _)| speaker == label);
+            if known && !names.values().any(|taken| taken == name) {
+                names.insert(label.clone(), name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Translate caption lines into `language`, preserving line count and order
+    pub async fn translate_lines(
+        &self,
+        lines: &[String],
+        language: &str,
+    ) -> Result<Vec<String>, ProcessorError> {
+        let mut translations = Vec::with_capacity(lines.len());
+
+        // Translate in batches so long transcripts stay within the context window
+        for batch in lines.chunks(40) {
+            let prompt = format!(
+                "Translate each of the following subtitle lines into {}. \
+                Keep the meaning and tone, and keep each line short enough to read as a caption. \
+                Respond with only a JSON array of {} strings, one translation per input line, in the same order.\n\n{:#}",
+                language,
+                batch.len(),
+                serde_json::json!(batch)
+            );
+
+            let response = self.complete_text(&prompt).await?;
+            let translated: Vec<String> = parse_json_reply(&response)?;
+
+            if translated.len() != batch.len() {
+                return Err(ProcessorError::OpenAIError(format!(
+                    "Expected {} translated lines, got {}",
+                    batch.len(),
+                    translated.len()
+                )));
+            }
+
+            translations.extend(translated);
+        }
+
+        Ok(translations)
+    }
+
+    /// Polish cleaned transcript lines: punctuation, casing and misheard
+    /// names or domain terms. Lines come back in the same order; any the
+    /// model rewrote rather than polished keep their input text.
+    pub async fn polish_lines(
+        &self,
+        lines: &[String],
+        template: &PromptTemplate,
+    ) -> Result<Vec<String>, ProcessorError> {
+        let mut polished = Vec::with_capacity(lines.len());
+        let glossary = Glossary::load(template).prompt_section();
+
+        for batch in lines.chunks(40) {
+            let prompt = format!(
+                "These lines come from an automatic speech transcript of a video on {}, \
+                with filler words already removed. Fix punctuation, capitalization and \
+                misheard names or domain terms in each line. Do not rephrase, summarize \
+                or merge lines, and keep empty lines empty.{}\n\n\
+                Respond with only a JSON array of {} strings, one per input line, in the same order.\n\n{:#}",
+                template.domain(),
+                glossary,
+                batch.len(),
+                serde_json::json!(batch)
+            );
+
+            let stage = Stage::start("llm_polish");
+            let response = stage.finish(self.complete_text(&prompt).await)?;
+            let replies: Vec<String> = parse_json_reply(&response)?;
+
+            if replies.len() != batch.len() {
+                return Err(ProcessorError::OpenAIError(format!(
+                    "Expected {} polished lines, got {}",
+                    batch.len(),
+                    replies.len()
+                )));
+            }
+
+            polished.extend(batch.iter().zip(replies).map(|(line, reply)| {
+                let delta = reply.split_whitespace().count().abs_diff(line.split_whitespace().count());
+                if delta <= MAX_POLISH_WORD_DELTA {
+                    reply.trim().to_string()
+                } else {
+                    line.clone()
+                }
+            }));
+        }
+
+        Ok(polished)
+    }
+}
+
+/// Tries at YouTube content before giving up on replies that don't parse
+const YOUTUBE_CONTENT_ATTEMPTS: usize = 3;
+
+/// Appended to YouTube content prompts so every template yields the same shape
+const YOUTUBE_CONTENT_FORMAT: &str = "\n\nRespond with only a JSON object with keys \"title\" (string), \
+    \"description\" (string), \"tags\" (array of strings) and \"chapters\" (array of objects with \
+    \"timestamp\", e.g. \"02:15\", and \"title\"; empty if the video has no clear sections).";
+
+/// Title, description and metadata the model wrote for a video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YouTubeContent {
+    pub title: String,
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+/// A YouTube chapter marker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    /// Start time as shown in the description, e.g. `02:15`
+    pub timestamp: String,
+    pub title: String,
+    /// Start time in seconds, when it was taken from transcript timing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<f64>,
+}
+
+impl Chapter {
+    /// A chapter starting `start` seconds in, shown as `MM:SS`, or
+    /// `H:MM:SS` from the first hour on
+    pub fn at(start: f64, title: &str) -> Self {
+        let total = start.max(0.0) as u64;
+        let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+        let timestamp = if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, minutes, seconds)
+        } else {
+            format!("{:02}:{:02}", minutes, seconds)
+        };
+        Self {
+            timestamp,
+            title: title.to_string(),
+            start: Some(start),
+        }
+    }
+
+    /// Start time in seconds, parsed from the timestamp if it wasn't timed
+    pub fn seconds(&self) -> f64 {
+        self.start.unwrap_or_else(|| {
+            self.timestamp
+                .split(':')
+                .try_fold(0.0, |total, part| part.trim().parse::<f64>().map(|n| total * 60.0 + n))
+                .unwrap_or(0.0)
+        })
+    }
+
+    /// The line that goes in a video description, e.g. `02:15 Serve technique`
+    pub fn line(&self) -> String {
+        format!("{} {}", self.timestamp, self.title)
+    }
+}
+
+/// One chapter as the model returns it
+#[derive(Deserialize)]
+struct ChapterReply {
+    start: f64,
+    title: String,
+}
+
+/// A moment the model picked for a highlight reel, in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highlight {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Largest change in word count we accept from a correction; anything bigger
+/// is a rewrite, not a misheard word
+const MAX_CORRECTION_WORD_DELTA: usize = 2;
+
+/// Largest change in a line's word count we accept from a polish
+const MAX_POLISH_WORD_DELTA: usize = 3;
+
+/// Transcript characters sent when naming speakers
+const MAX_SPEAKER_CONTEXT_CHARS: usize = 12_000;
+
+/// Parse a model reply that should be JSON, tolerating a code fence around it
+fn parse_json_reply<T: serde::de::DeserializeOwned>(response: &str) -> Result<T, ProcessorError> {
+    serde_json::from_str(strip_code_fence(response))
+        .map_err(|e| ProcessorError::OpenAIError(format!("Unexpected reply from the model: {}", e)))
+}
+
+/// Strip a surrounding Markdown code fence (```json ... ```) from a model response
+fn strip_code_fence(response: &str) -> &str {
+    let trimmed = response.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => rest
+            .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+            .trim_end()
+            .trim_end_matches("```")
+            .trim(),
+        None => trimmed,
+    }
+}
@@ -1,70 +1,254 @@
+mod analysis;
+mod anonymize;
+mod anthropic;
+mod batch;
+mod benchmark;
+mod cache;
+mod cleanup;
+mod config;
+mod diarization;
+mod edl;
+mod error;
+mod events;
+mod features;
 mod ffmpeg;
+mod glossary;
+#[cfg(test)]
+mod integration_tests;
+mod jobs;
+mod library;
+mod llm;
+mod media;
 mod whisper;
+mod workspace;
 mod openai;
+mod openai_whisper;
+mod pipeline;
+mod process;
+mod publish;
+mod quotes;
+mod request;
+mod scheduler;
+mod store;
+mod subtitles;
+mod telemetry;
+mod templates;
+mod topics;
+mod transcription;
+mod webhooks;
 
 use axum::{
-    routing::get,
+    routing::{get, post, put},
     Router,
-    response::Json,
-    extract::Query,
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Json, Response},
+    http::StatusCode,
+    extract::{DefaultBodyLimit, Query, State},
 };
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::HashMap;
+use std::convert::Infallible;
 use serde_json::{json, Value};
-use ffmpeg::FFmpegClient;
+use ffmpeg::{AudioCodec, FFmpegClient, GateOptions, ImageFormat, ImageMotion, MergeOptions, OutputFormat, ProgressCallback, ReencodeOptions, Transition};
 use whisper::WhisperClient;
-use std::path::PathBuf;
-use openai::OpenAIClient;
-use serde::Deserialize;
+use jobs::JobQueue;
+use batch::BatchQueue;
+use diarization::{Diarizer, SpeakerTurn};
+use error::ProcessorError;
+use std::path::{Path, PathBuf};
+use llm::{LlmClient, Provider, YouTubeContent};
+use serde::{Deserialize, Serialize};
 use dotenv::dotenv;
-use openai::PromptTemplate;
+use templates::PromptTemplate;
+use glossary::Glossary;
+use anonymize::{Anonymizer, Redaction};
+use subtitles::SubtitleFormat;
+use cache::ArtifactCache;
+use clap::Parser;
+use config::{Cli, Config};
+use media::{BodyLimits, IngestOptions};
+use features::Features;
+use workspace::Workspace;
+use std::sync::Arc;
+use store::Store;
+use request::{JsonBody, Validate};
+use openai_whisper::OpenAIWhisperClient;
+use transcription::{BackendKind, TranscriptionBackend, TranscriptionOptions};
+
+#[derive(Clone)]
+struct AppState {
+    cache: Arc<ArtifactCache>,
+    limits: BodyLimits,
+    ingest: IngestOptions,
+    features: Features,
+    jobs: Arc<JobQueue>,
+    batches: Arc<BatchQueue>,
+    workspace: Workspace,
+    store: Arc<Store>,
+}
+
+/// A whisper client writing into the workspace and reusing stored transcripts
+fn whisper_client(state: &AppState) -> WhisperClient {
+    let mut whisper = WhisperClient::new();
+    whisper
+        .with_output_dir(state.workspace.transcripts_dir())
+        .with_store(state.store.clone());
+    whisper
+}
+
+/// The requested transcription backend, or TRANSCRIPTION_BACKEND's, set up
+/// like `whisper_client` with the request's overrides
+fn transcriber(
+    state: &AppState,
+    backend: Option<BackendKind>,
+    words: bool,
+    options: &TranscriptionOptions,
+) -> Result<Box<dyn TranscriptionBackend>, ProcessorError> {
+    options.validate()?;
+    match backend.unwrap_or_else(BackendKind::from_env) {
+        BackendKind::Local => {
+            let mut whisper = whisper_client(state);
+            whisper.with_word_timestamps(words).with_options(options);
+            Ok(Box::new(whisper))
+        }
+        BackendKind::Api => {
+            let mut api = OpenAIWhisperClient::new()?;
+            api.with_word_timestamps(words)
+                .with_options(options)
+                .with_work_dir(state.workspace.transcripts_dir())
+                .with_store(state.store.clone());
+            Ok(Box::new(api))
+        }
+    }
+}
 
 async fn hello() -> Json<Value> {
-    println!("Hello, World!");
+    tracing::debug!("Hello, World!");
     Json(json!({ "message": "Hello, World!" }))
 }
 
-async fn media_info() -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let audio_path = current_dir.join("src").join("audio.mp3");
-    
-    let mut ffmpeg = FFmpegClient::new();
-    let info = ffmpeg
-        .with_input(audio_path.to_str().unwrap())
-        .get_info()
-        .expect("Failed to execute ffmpeg");
-
-    println!("Request Success");
-    println!("Media Info: {:?}", info);
-
-    Json(json!({ 
-        "file": audio_path.to_str().unwrap(),
-        "duration": info.duration,
-        "format": info.format,
-        "bitrate": info.bitrate,
-        "sample_rate": "44100 Hz",
-        "channels": "stereo"
+async fn features(State(state): State<AppState>) -> Json<Value> {
+    Json(json!(state.features))
+}
+
+/// Prompt templates found in the templates directory
+async fn list_templates() -> Json<Value> {
+    let registry = templates::registry();
+    Json(json!({
+        "directory": registry.dir(),
+        "default": registry.default_name(),
+        "placeholders": templates::PLACEHOLDERS,
+        "templates": registry.list()
     }))
 }
 
-async fn split_video() -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let chunks_dir = current_dir.join("src").join("chunks");
-    let audio_path = current_dir.join("src").join("audio.mp3");
+/// Every media file on the server, newest first
+async fn list_media(State(state): State<AppState>) -> Result<Json<Value>, ProcessorError> {
+    let media = library::list_media(&state.workspace)?;
+    Ok(Json(json!({ "media": media })))
+}
+
+async fn get_media(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<library::MediaEntry>, ProcessorError> {
+    library::get_media(&state.workspace, &id).map(Json)
+}
+
+async fn delete_media(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<library::DeletedMedia>, ProcessorError> {
+    library::delete_media(&state.workspace, &id).map(Json)
+}
+
+#[derive(Deserialize)]
+struct MediaQuery {
+    media_id: String,
+}
+
+async fn media_info(State(state): State<AppState>, Query(params): Query<MediaQuery>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let audio_path = state.workspace.require_media(&params.media_id)?;
+    
+        let mut ffmpeg = FFmpegClient::new();
+        let info = ffmpeg
+            .with_input(audio_path.to_str().unwrap())
+            .get_info()?;
+
+        tracing::debug!("Request Success");
+        tracing::debug!("Media Info: {:?}", info);
+
+        let mut response = json!(info);
+        response["media_id"] = json!(params.media_id);
+        response["file"] = json!(audio_path.to_str().unwrap());
+        Ok(Json(response))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct SplitRequest {
+    media_id: String,
+    /// Seconds per chunk (default 2)
+    chunk_duration: Option<u64>,
+    format: Option<OutputFormat>,
+    #[serde(flatten)]
+    job: JobOptions,
+}
+
+impl Validate for SplitRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.chunk_duration == Some(0) {
+            return Err(ProcessorError::BadRequest("chunk_duration must be at least 1 second".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Split a media file into chunks; as a background job, its progress
+/// follows ffmpeg's
+async fn split_video(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<SplitRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    if !request.job.background() {
+        return process::blocking(move || split_media(&state, &request, None))
+            .await
+            .map(|result| (StatusCode::OK, Json(result)));
+    }
+    state.workspace.require_media(&request.media_id)?;
+    let job_state = state.clone();
+    queue_job(&state, "split", request.job.callback_url.clone(), move |job| {
+        split_media(&job_state, &request, Some(job_progress(job))).map_err(|e| e.to_string())
+    })
+}
 
-    // Create chunks directory if it doesn't exist
-    std::fs::create_dir_all(&chunks_dir).expect("Failed to create chunks directory");
+/// Split a media file into fixed-length chunks under the chunks directory
+fn split_media(state: &AppState, params: &SplitRequest, progress: Option<ProgressCallback>) -> Result<Value, ProcessorError> {
+    let chunk_duration = params.chunk_duration.unwrap_or(2);
+    let chunks_dir = state.workspace.chunks_dir().join(&params.media_id);
+    let audio_path = state.workspace.require_media(&params.media_id)?;
 
+    // Split into a scratch directory of this request's own, then swap the
+    // chunks in for those of any earlier split so `/merge` only sees this one
+    let scratch = workspace::scratch_dir(&chunks_dir)?;
     let mut ffmpeg = FFmpegClient::new();
+    if let Some(progress) = progress {
+        ffmpeg.with_progress(progress);
+    }
     let chunks = ffmpeg
+        .with_cache(state.cache.clone())
         .with_input(audio_path.to_str().unwrap())
-        .with_output_dir(chunks_dir.to_str().unwrap())
-        .with_chunk_duration(2)  // 2 seconds
-        .split_into_chunks()
-        .expect("Failed to split video");
+        .with_output_dir(scratch.path())
+        .with_chunk_duration(chunk_duration)
+        .with_output_format(params.format.unwrap_or_default())
+        .split_into_chunks()?;
+    let chunks = workspace::publish(&chunks, &chunks_dir, |name| name.starts_with("chunk_"))?;
 
-    println!("Request Success");
-    println!("chunks {:?}", chunks);
+    tracing::debug!("Request Success");
+    tracing::debug!("chunks {:?}", chunks);
 
-    Json(json!({
+    Ok(json!({
         "message": "Audio split successfully",
         "chunks": chunks
             .iter()
@@ -73,45 +257,152 @@ async fn split_video() -> Json<Value> {
     }))
 }
 
-async fn merge_chunks() -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let chunks_dir = current_dir.join("src").join("chunks");
-    let output_path = current_dir.join("src").join("merged.mp3");
+#[derive(Deserialize)]
+struct MergeRequest {
+    media_id: String,
+    /// Format the chunks were split into (default mp3)
+    format: Option<OutputFormat>,
+    /// Seconds each chunk overlaps the next (default 0, a hard cut; 0.5 with a `transition`)
+    crossfade: Option<f64>,
+    /// Video transition over the overlap (default fade)
+    transition: Option<Transition>,
+}
 
-    // Get list of chunks (you might want to specify order differently)
-    let chunks: Vec<PathBuf> = std::fs::read_dir(&chunks_dir)
-        .expect("Failed to read chunks directory")
-        .filter_map(Result::ok)
-        .map(|entry| entry.path())
-        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("mp3"))
-        .collect();
+impl Validate for MergeRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        merge_options(self.crossfade, self.transition).map(|_| ())
+    }
+}
 
-    let ffmpeg = FFmpegClient::new();
-    let merged_file = ffmpeg
-        .merge_chunks(chunks, output_path)
-        .expect("Failed to merge chunks");
+/// Seconds of overlap when a transition is asked for without a crossfade
+const DEFAULT_TRANSITION_SECONDS: f64 = 0.5;
 
-    Json(json!({
-        "message": "Chunks merged successfully",
-        "output_file": merged_file.to_str().unwrap()
-    }))
+/// Merge settings from a request's `crossfade` and `transition`
+fn merge_options(crossfade: Option<f64>, transition: Option<Transition>) -> Result<MergeOptions, ProcessorError> {
+    let crossfade = crossfade.unwrap_or(if transition.is_some() { DEFAULT_TRANSITION_SECONDS } else { 0.0 });
+    if !(0.0..=ffmpeg::MAX_CROSSFADE).contains(&crossfade) {
+        return Err(ProcessorError::BadRequest(format!(
+            "crossfade must be between 0 and {} seconds",
+            ffmpeg::MAX_CROSSFADE
+        )));
+    }
+    Ok(MergeOptions { crossfade, transition })
+}
+
+/// Merge the chunks produced by `/split` for a media file back together
+async fn merge_chunks(State(state): State<AppState>, JsonBody(params): JsonBody<MergeRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        state.workspace.require_media(&params.media_id)?;
+        let chunks_dir = state.workspace.chunks_dir().join(&params.media_id);
+        if !chunks_dir.is_dir() {
+            return Err(ProcessorError::NotFound("No chunks found; split the media first".to_string()));
+        }
+        let extension = params.format.unwrap_or_default().extension();
+        let output_path = state.workspace.outputs_dir().join(format!("{}.merged.{}", params.media_id, extension));
+        let options = merge_options(params.crossfade, params.transition)?;
+
+        // Chunk names are numbered, so sorting restores their order
+        let mut chunks: Vec<PathBuf> = std::fs::read_dir(&chunks_dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some(extension))
+            .collect();
+        chunks.sort();
+        if chunks.is_empty() {
+            return Err(ProcessorError::NotFound(format!("No {} chunks found; split the media first", extension)));
+        }
+
+        let mut ffmpeg = FFmpegClient::new();
+        let merged_file = ffmpeg
+            .with_cache(state.cache.clone())
+            .merge_chunks(chunks, output_path, &options)?;
+
+        Ok(Json(json!({
+            "message": "Chunks merged successfully",
+            "output_file": merged_file.to_str().unwrap(),
+            "crossfade": options.crossfade,
+            "transition": options.transition
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct RegionRequest {
+    media_id: String,
+    /// Region bounds in seconds
+    start: f64,
+    end: f64,
+    /// Defaults to the input's own format when it's one we can write, else mp3
+    format: Option<OutputFormat>,
+    /// Re-encode for frame-accurate cuts instead of snapping to keyframes
+    precise: Option<bool>,
+    /// x264 preset for precise cuts (default veryfast)
+    preset: Option<String>,
+    /// x264 CRF for precise cuts (default 18)
+    crf: Option<u8>,
+    #[serde(flatten)]
+    job: JobOptions,
+}
+
+impl Validate for RegionRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if !(self.start >= 0.0 && self.end > self.start) {
+            return Err(ProcessorError::BadRequest("Region needs 0 <= start < end".to_string()));
+        }
+        Ok(())
+    }
 }
 
-async fn split_region() -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let chunks_dir = current_dir.join("src").join("chunks");
-    let audio_path = current_dir.join("src").join("audio.mp3");
+/// Split around a region. Precise cuts re-encode, which is slow enough on
+/// long videos to want a background job and its progress bar.
+async fn split_region(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<RegionRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    if !request.job.background() {
+        return process::blocking(move || split_media_region(&state, &request, None))
+            .await
+            .map(|result| (StatusCode::OK, Json(result)));
+    }
+    state.workspace.require_media(&request.media_id)?;
+    let job_state = state.clone();
+    queue_job(&state, "split_region", request.job.callback_url.clone(), move |job| {
+        split_media_region(&job_state, &request, Some(job_progress(job))).map_err(|e| e.to_string())
+    })
+}
 
-    std::fs::create_dir_all(&chunks_dir).expect("Failed to create chunks directory");
+/// Cut a media file into the parts before, inside and after a region
+fn split_media_region(state: &AppState, params: &RegionRequest, progress: Option<ProgressCallback>) -> Result<Value, ProcessorError> {
+    let chunks_dir = state.workspace.chunks_dir().join(&params.media_id).join("region");
+    let audio_path = state.workspace.require_media(&params.media_id)?;
+    let format = params.format
+        .or_else(|| OutputFormat::of(&audio_path))
+        .unwrap_or_default();
 
+    let scratch = workspace::scratch_dir(&chunks_dir)?;
     let mut ffmpeg = FFmpegClient::new();
-    let chunks = ffmpeg
+    ffmpeg
+        .with_cache(state.cache.clone())
         .with_input(audio_path.to_str().unwrap())
-        .with_output_dir(chunks_dir.to_str().unwrap())
-        .split_at_region(1.0, 1.5)  // Back to hardcoded values
-        .expect("Failed to split region");
+        .with_output_dir(scratch.path())
+        .with_output_format(format);
+    if let Some(progress) = progress {
+        ffmpeg.with_progress(progress);
+    }
+    if params.precise.unwrap_or(false) {
+        let defaults = ReencodeOptions::default();
+        let options = ReencodeOptions {
+            preset: params.preset.clone().unwrap_or(defaults.preset),
+            crf: params.crf.unwrap_or(defaults.crf),
+        };
+        options.validate()?;
+        ffmpeg.with_reencode(options);
+    }
+    let chunks = ffmpeg.split_at_region(params.start, params.end)?;
+    let chunks = workspace::publish(&chunks, &chunks_dir, |_| false)?;
 
-    Json(json!({
+    Ok(json!({
         "message": "Audio split by region successfully",
         "chunks": chunks
             .iter()
@@ -120,229 +411,2475 @@ async fn split_region() -> Json<Value> {
     }))
 }
 
-async fn transcribe() -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let input_path = current_dir.join("src").join("video.mov");
+#[derive(Clone, Deserialize)]
+struct TranscribeParams {
+    media_id: String,
+    snippets: Option<bool>,
+    template: Option<String>,
+    /// Redact names, phone numbers and addresses
+    anonymize: Option<bool>,
+    /// With `anonymize`, also render a copy of the media with the redactions beeped
+    beep: Option<bool>,
+    /// Whisper files to keep, e.g. `srt,json`; defaults to WHISPER_OUTPUT_FORMATS
+    formats: Option<String>,
+    /// Include per-word timings in each segment
+    words: Option<bool>,
+    /// Transcribe in pieces of this many minutes, several at once
+    chunk_minutes: Option<u64>,
+    /// With `chunk_minutes`, most pieces transcribed at once (default WHISPER_CONCURRENCY, or 2)
+    concurrency: Option<usize>,
+    /// `local` or `api`; defaults to TRANSCRIPTION_BACKEND
+    backend: Option<BackendKind>,
+    /// Label each segment with who is speaking
+    diarize: Option<bool>,
+    /// With `diarize`, how many people talk, when known
+    speakers: Option<u32>,
+    /// With `diarize`, ask the LLM to replace `Speaker N` labels with names
+    name_speakers: Option<bool>,
+    /// Drop filler words and stutters and tidy punctuation, returning the raw
+    /// text alongside
+    clean: Option<bool>,
+    /// Also have the LLM fix punctuation and misheard terms; implies `clean`
+    polish: Option<bool>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+}
 
-    println!("Looking for file at: {:?}", input_path);
-    
-    // Verify file exists
-    if !input_path.exists() {
-        println!("File not found!");
-        return Json(json!({
-            "error": "File not found",
-            "path": input_path.to_str()
-        }));
-    }
-
-    let whisper = WhisperClient::new();
-    let transcription = match whisper.transcribe(&input_path) {
-        Ok(t) => t,
-        Err(e) => {
-            return Json(json!({
-                "error": "Transcription failed",
-                "details": e.to_string()
-            }));
-        }
-    };
+/// Redact personal information from a transcript before it goes to an
+/// external LLM provider (or back to the caller)
+fn anonymize_segments(template: &PromptTemplate, segments: &mut [whisper::Segment]) -> Vec<Redaction> {
+    let redactions = Anonymizer::new(&Glossary::load(template)).anonymize(segments);
+    tracing::info!("Redacted {} items of personal information", redactions.len());
+    redactions
+}
 
-    Json(json!({
-        "segments": transcription.segments.iter().map(|segment| {
-            json!({
-                "start": segment.start,
-                "end": segment.end,
-                "text": segment.text
-            })
-        }).collect::<Vec<_>>()
-    }))
+/// URL that serves the audio behind one transcript segment
+fn snippet_url(media_id: &str, start: f64, end: f64) -> String {
+    let query = serde_urlencoded::to_string([
+        ("media_id", media_id.to_string()),
+        ("start", format!("{:.3}", start)),
+        ("end", format!("{:.3}", end)),
+    ]).unwrap_or_default();
+    format!("/media/snippet?{}", query)
 }
 
-async fn transcribe_to_json() -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let input_path = current_dir.join("src").join("video.mov");
+/// Chunked transcriptions' default concurrency, from WHISPER_CONCURRENCY (default 2)
+fn whisper_concurrency() -> usize {
+    std::env::var("WHISPER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2)
+}
 
-    println!("Looking for file at: {:?}", input_path);
-    
-    if !input_path.exists() {
-        println!("File not found!");
-        return Json(json!({
-            "error": "File not found",
-            "path": input_path.to_str()
-        }));
+/// `/transcribe`'s body: its own parameters and the whisper settings
+#[derive(Deserialize)]
+struct TranscribeRequest {
+    #[serde(flatten)]
+    params: TranscribeParams,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+    #[serde(flatten)]
+    job: JobOptions,
+    /// Answer with the transcript instead of a job; transcriptions run in
+    /// the background by default
+    #[serde(default)]
+    wait: bool,
+}
+
+impl Validate for TranscribeRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.wait && self.job.callback_url.is_some() {
+            return Err(ProcessorError::BadRequest("wait can't be combined with callback_url".to_string()));
+        }
+        self.options.validate()?;
+        PromptTemplate::from_name(self.params.template.as_deref()).map(|_| ())
     }
+}
 
-    let whisper = WhisperClient::new();
-    let transcription = whisper
-        .transcribe(&input_path)
-        .expect("Failed to transcribe audio");
+/// Transcribe a media file into timed segments. The answer is a job ID
+/// straight away; poll `/jobs/:id` for the result, or pass `wait` to get
+/// the transcript itself.
+async fn transcribe(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<TranscribeRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    let input_path = state.workspace.require_media(&request.params.media_id)?;
+    if request.wait {
+        let result = transcribe_media(&state, &input_path, &request.params, &request.options, &|_| {}).await?;
+        return Ok((StatusCode::OK, Json(result)));
+    }
 
-    // Concatenate all segments into one text
-    let full_text: String = transcription.segments
-        .iter()
-        .map(|segment| segment.text.clone())
-        .collect::<Vec<String>>()
-        .join(" ");
+    let job_state = state.clone();
+    let TranscribeRequest { params, options, job, .. } = request;
+    queue_job(&state, "transcribe", job.callback_url, move |job| {
+        block_on(transcribe_media(&job_state, &input_path, &params, &options, &|progress| job.set_progress(progress)))
+    })
+}
 
-    let word_count = full_text.split_whitespace().count();
+/// Whether a POST is answered when its work is done, or straight away with
+/// a job to poll
+#[derive(Clone, Default, Deserialize)]
+struct JobOptions {
+    /// Run as a background job and answer 202 with where to poll for it
+    #[serde(default)]
+    background: bool,
+    /// POST the finished job here; implies `background`
+    callback_url: Option<String>,
+}
 
-    Json(json!({
-        "text": full_text,
-        "word_count": word_count,
-        "duration_seconds": transcription.segments.last().map(|s| s.end).unwrap_or(0.0)
-    }))
+impl JobOptions {
+    fn background(&self) -> bool {
+        self.background || self.callback_url.is_some()
+    }
 }
 
-#[derive(Deserialize)]
-struct TranscribeQuery {
-    template: Option<String>,
+/// Submit `work` to the job queue and answer with where to poll for it.
+/// With a `callback` URL, the finished job is also POSTed there.
+fn queue_job<F>(
+    state: &AppState,
+    kind: &str,
+    callback: Option<String>,
+    work: F,
+) -> Result<(StatusCode, Json<Value>), ProcessorError>
+where
+    F: FnOnce(&jobs::JobHandle) -> Result<Value, String> + Send + 'static,
+{
+    if let Some(url) = &callback {
+        webhooks::validate_callback_url(url)?;
+    }
+    let job_id = state.jobs.submit(kind, callback, work);
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "job_id": job_id,
+            "status": jobs::JobStatus::Queued,
+            "status_url": format!("/jobs/{}", job_id)
+        })),
+    ))
 }
 
-async fn transcribe_and_optimize(Query(params): Query<TranscribeQuery>) -> Json<Value> {
-    let current_dir = std::env::current_dir().expect("Failed to get current directory");
-    let input_path = current_dir.join("src").join("video.mov");
+/// Run async `work` as a job; jobs run on a blocking thread, which may wait
+/// on async work
+fn block_on<T: Serialize>(work: impl std::future::Future<Output = Result<T, ProcessorError>>) -> Result<Value, String> {
+    tokio::runtime::Handle::current()
+        .block_on(work)
+        .map(|result| json!(result))
+        .map_err(|e| e.to_string())
+}
 
-    println!("Looking for file at: {:?}", input_path);
-    
-    if !input_path.exists() {
-        println!("File not found!");
-        return Json(json!({
-            "error": "File not found",
-            "path": input_path.to_str()
-        }));
-    }
+/// An ffmpeg progress callback that updates a job's progress
+fn job_progress(job: &jobs::JobHandle) -> ProgressCallback {
+    let job = job.clone();
+    Arc::new(move |fraction| job.set_progress(fraction))
+}
 
-    // First, transcribe the video
-    let whisper = WhisperClient::new();
-    let transcription = match whisper.transcribe(&input_path) {
-        Ok(t) => t,
-        Err(e) => {
-            return Json(json!({
-                "error": "Transcription failed",
-                "details": e.to_string()
-            }));
+async fn get_job(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<jobs::Job>, ProcessorError> {
+    state.jobs
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| ProcessorError::NotFound(format!("Job not found: {}", id)))
+}
+
+/// Transcribe, clean up and optionally anonymize one media file, reporting
+/// rough progress in [0, 1]
+async fn transcribe_media(
+    state: &AppState,
+    input_path: &Path,
+    params: &TranscribeParams,
+    options: &TranscriptionOptions,
+    progress: &(dyn Fn(f64) + Sync),
+) -> Result<Value, ProcessorError> {
+    options.validate()?;
+    let polish = params.polish.unwrap_or(false);
+    if polish && !state.features.llm {
+        return Err(ProcessorError::BadRequest(
+            "polish needs an LLM provider; set FEATURE_LLM and its API key".to_string(),
+        ));
+    }
+    let words = params.words.unwrap_or(false);
+    let transcription = match params.backend.unwrap_or_else(BackendKind::from_env) {
+        BackendKind::Local => {
+            let mut whisper = whisper_client(state);
+            whisper.with_options(options);
+            if let Some(formats) = params.formats.as_deref() {
+                let formats: Vec<whisper::OutputFormat> = formats
+                    .split(',')
+                    .filter_map(|name| whisper::OutputFormat::from_name(name.trim()))
+                    .collect();
+                whisper.with_output_formats(&formats);
+            }
+            whisper.with_word_timestamps(words);
+            match params.chunk_minutes {
+                Some(minutes) => {
+                    let options = whisper::ChunkOptions {
+                        chunk_seconds: minutes * 60,
+                        concurrency: params.concurrency.unwrap_or_else(whisper_concurrency),
+                    };
+                    whisper.transcribe_chunked(input_path, options, &|done| progress(done * 0.9)).await?
+                }
+                None => TranscriptionBackend::transcribe(&whisper, input_path).await?,
+            }
+        }
+        BackendKind::Api => {
+            if params.chunk_minutes.is_some() {
+                return Err(ProcessorError::BadRequest("chunk_minutes is only supported by the local backend".to_string()));
+            }
+            transcriber(state, Some(BackendKind::Api), words, options)?.transcribe(input_path).await?
         }
     };
+    progress(0.9);
 
-    // Combine all segments into one text
-    let full_text: String = transcription.segments
+    let artifacts: Vec<String> = transcription.artifacts
         .iter()
-        .map(|segment| segment.text.clone())
-        .collect::<Vec<String>>()
-        .join(" ");
+        .map(|path| state.workspace.relative(path))
+        .collect();
 
-    // Then optimize the content using OpenAI
-    let openai = OpenAIClient::new()
-        .expect("Failed to create OpenAI client");
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    let mut segments = transcription.segments;
+    Glossary::load(&template).apply_to_segments(&mut segments);
 
-    let template = match params.template.as_deref() {
-        Some("mama-meditations") => PromptTemplate::MamaMeditations,
-        Some("med-man-sports") => PromptTemplate::MedManSports,
-        _ => PromptTemplate::MedManSports,
+    let redactions = if params.anonymize.unwrap_or(false) {
+        anonymize_segments(&template, &mut segments)
+    } else {
+        Vec::new()
     };
+    // Word timings carry the original text, so drop them where it was redacted
+    for redaction in &redactions {
+        segments[redaction.segment].words.clear();
+    }
+
+    let mut beeped = None;
+    if params.beep.unwrap_or(false) && !redactions.is_empty() {
+        let exports_dir = state.workspace.exports_dir();
+        std::fs::create_dir_all(&exports_dir)?;
+        let extension = input_path.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+        let output = exports_dir.join(format!("{}.anonymized.{}", params.media_id, extension));
+        let ranges: Vec<(f64, f64)> = redactions.iter().map(|r| (r.start, r.end)).collect();
 
-    match openai.generate_youtube_content(&full_text, template).await {
-        Ok(response) => {
-            match serde_json::from_str::<Value>(&response) {
-                Ok(content) => Json(content),
-                Err(e) => {
-                    println!("Failed to parse response as JSON: {}", e);
-                    Json(json!({
-                        "error": "Failed to parse YouTube content",
-                        "raw_response": response
-                    }))
+        let input = input_path.to_path_buf();
+        let path = process::blocking(move || FFmpegClient::new().with_input(&input).beep(&ranges, &output)).await?;
+        beeped = Some(state.workspace.relative(&path));
+    }
+
+    // Cleanup runs on the redacted text, so raw_text never brings back what
+    // anonymize took out
+    let mut cleanup = None;
+    let mut raw_texts = Vec::new();
+    if params.clean.unwrap_or(false) || polish {
+        raw_texts = segments.iter().map(|segment| segment.text.clone()).collect();
+        let mut report = cleanup::clean_segments(&mut segments);
+        if polish {
+            let lines: Vec<String> = segments.iter().map(|segment| segment.text.clone()).collect();
+            let polished = LlmClient::new(params.provider)?.polish_lines(&lines, &template).await?;
+            for (segment, text) in segments.iter_mut().zip(polished) {
+                if segment.text != text {
+                    segment.text = text;
+                    report.polished += 1;
                 }
             }
-        },
-        Err(e) => {
-            println!("YouTube content generation failed: {}", e);
-            Json(json!({
-                "error": format!("YouTube content generation failed: {}", e)
-            }))
+        }
+        tracing::info!(
+            "Cleaned transcript: {} filler words and {} repeats removed, {} segments polished",
+            report.fillers_removed, report.repeats_removed, report.polished
+        );
+        cleanup = Some(report);
+    }
+
+    let speakers = if params.diarize.unwrap_or(false) {
+        Some(label_speakers(state, input_path, params, &template, &mut segments).await?)
+    } else {
+        None
+    };
+
+    let include_snippets = params.snippets.unwrap_or(false);
+
+    let mut result = json!({
+        "language": transcription.language,
+        "artifacts": artifacts,
+        "redactions": redactions,
+        "beeped": beeped,
+        "speakers": speakers,
+        "segments": segments.iter().enumerate().map(|(index, segment)| {
+            let mut value = json!({
+                "start": segment.start,
+                "end": segment.end,
+                "text": segment.text
+            });
+            if let Some(raw_text) = raw_texts.get(index) {
+                value["raw_text"] = json!(raw_text);
+            }
+            if let Some(speaker) = &segment.speaker {
+                value["speaker"] = json!(speaker);
+            }
+            if !segment.words.is_empty() {
+                value["words"] = json!(segment.words);
+            }
+            if include_snippets {
+                value["audio_url"] = json!(snippet_url(&params.media_id, segment.start, segment.end));
+            }
+            value
+        }).collect::<Vec<_>>()
+    });
+    if let Some(report) = cleanup {
+        let joined = |texts: Vec<&str>| texts.into_iter().filter(|text| !text.is_empty()).collect::<Vec<_>>().join(" ");
+        result["text"] = json!(joined(segments.iter().map(|segment| segment.text.as_str()).collect()));
+        result["raw_text"] = json!(joined(raw_texts.iter().map(|text| text.trim()).collect()));
+        result["cleanup"] = json!(report);
+    }
+    Ok(result)
+}
+
+/// Diarize the media and label `segments` with their speakers, named by the
+/// LLM when asked. Returns every speaker, in order of first appearance.
+async fn label_speakers(
+    state: &AppState,
+    input_path: &Path,
+    params: &TranscribeParams,
+    template: &PromptTemplate,
+    segments: &mut [whisper::Segment],
+) -> Result<Vec<String>, ProcessorError> {
+    let name_speakers = params.name_speakers.unwrap_or(false);
+    if name_speakers && !state.features.llm {
+        return Err(ProcessorError::BadRequest(
+            "name_speakers needs an LLM provider; set FEATURE_LLM and its API key".to_string(),
+        ));
+    }
+
+    // Speaker turns found for this media before are reused
+    let media_hash = state.store.media_hash(input_path)?;
+    let content_key = match params.speakers {
+        Some(speakers) => format!("speakers={}", speakers),
+        None => "speakers=auto".to_string(),
+    };
+    let stored = state.store
+        .content(&media_hash, "diarization", &content_key)?
+        .and_then(|stored| serde_json::from_str::<Vec<SpeakerTurn>>(&stored).ok());
+    let turns = match stored {
+        Some(turns) => {
+            tracing::info!("Using stored speaker turns for {} ({})", params.media_id, content_key);
+            turns
+        }
+        None => {
+            let mut diarizer = Diarizer::new();
+            diarizer.with_work_dir(state.workspace.transcripts_dir());
+            if let Some(speakers) = params.speakers {
+                diarizer.with_speakers(speakers);
+            }
+            let input = input_path.to_path_buf();
+            let turns = process::blocking(move || diarizer.diarize(&input)).await?;
+            let serialized = serde_json::to_string(&turns)
+                .map_err(|e| ProcessorError::Database(e.to_string()))?;
+            state.store.save_content(&media_hash, "diarization", &content_key, &serialized)?;
+            turns
+        }
+    };
+
+    let mut speakers = diarization::assign_speakers(segments, &turns);
+    if name_speakers && !speakers.is_empty() {
+        let lines: Vec<(String, String)> = segments
+            .iter()
+            .filter_map(|segment| Some((segment.speaker.clone()?, segment.text.clone())))
+            .collect();
+        let names = LlmClient::new(params.provider)?.name_speakers(&lines, template).await?;
+        tracing::info!("Named {} of {} speakers", names.len(), speakers.len());
+        diarization::apply_names(segments, &names);
+        for speaker in speakers.iter_mut() {
+            if let Some(name) = names.get(speaker) {
+                *speaker = name.clone();
+            }
         }
     }
+    Ok(speakers)
 }
 
 #[derive(Deserialize)]
-struct PromptQuery {
-    text: String,
+struct ReviewParams {
+    media_id: String,
+    threshold: Option<f64>,
+    template: Option<String>,
+    anonymize: Option<bool>,
+    /// `local` or `api`; defaults to TRANSCRIPTION_BACKEND
+    backend: Option<BackendKind>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
 }
 
-async fn chat(Query(params): Query<PromptQuery>) -> Json<Value> {
-    let client = OpenAIClient::new()
-        .expect("Failed to create OpenAI client");
+/// `/transcribe/review`'s body: its own parameters and the whisper settings
+#[derive(Deserialize)]
+struct ReviewRequest {
+    #[serde(flatten)]
+    params: ReviewParams,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+}
 
-    match client.complete(&params.text).await {
-        Ok(response) => Json(json!({
-            "response": response
-        })),
-        Err(e) => Json(json!({
-            "error": e.to_string()
-        }))
+impl Validate for ReviewRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options.validate()?;
+        if self.params.threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err(ProcessorError::BadRequest("threshold must be between 0 and 1".to_string()));
+        }
+        PromptTemplate::from_name(self.params.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Transcribe, then ask the LLM for corrections to low-confidence segments.
+/// Suggestions are returned alongside the untouched transcript for review.
+async fn transcribe_review(
+    State(state): State<AppState>,
+    JsonBody(ReviewRequest { params, options }): JsonBody<ReviewRequest>,
+) -> Result<Json<Value>, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
+
+    let mut transcription = transcriber(&state, params.backend, false, &options)?.transcribe(&input_path).await?;
+
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    Glossary::load(&template).apply_to_segments(&mut transcription.segments);
+    if params.anonymize.unwrap_or(false) {
+        anonymize_segments(&template, &mut transcription.segments);
+    }
+
+    let threshold = params.threshold.unwrap_or(0.6);
+    let low_confidence: Vec<(usize, String)> = transcription.segments
+        .iter()
+        .enumerate()
+        .filter(|(_, segment)| segment.confidence.is_some_and(|c| c < threshold))
+        .map(|(i, segment)| (i, segment.text.trim().to_string()))
+        .collect();
+
+    let llm = LlmClient::new(params.provider)?;
+    let suggestions = llm.suggest_corrections(&low_confidence, &template).await?;
+
+    Ok(Json(json!({
+        "threshold": threshold,
+        "low_confidence_segments": low_confidence.len(),
+        "segments": transcription.segments.iter().map(|segment| {
+            json!({
+                "start": segment.start,
+                "end": segment.end,
+                "text": segment.text,
+                "confidence": segment.confidence
+            })
+        }).collect::<Vec<_>>(),
+        "suggestions": suggestions
+    })))
+}
+
+/// Transcription runs and the whisper files kept for each
+async fn transcript_artifacts(State(state): State<AppState>) -> Json<Value> {
+    Json(json!({
+        "transcripts": library::transcript_records(&state.workspace.transcripts_dir())
+    }))
+}
+
+/// Every transcript in the database, newest first
+async fn list_stored_transcripts(State(state): State<AppState>) -> Result<Json<Value>, ProcessorError> {
+    Ok(Json(json!({
+        "transcripts": state.store.list_transcripts()?
+    })))
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    /// Most hits returned (default 50, at most 500)
+    limit: Option<usize>,
+}
+
+/// Find stored transcript segments mentioning every word of `q`, across all
+/// media. Hits carry the media ID when the file is still in the media
+/// directory, plus an audio_url for the segment.
+async fn search_transcripts(
+    State(state): State<AppState>,
+    Query(params): Query<SearchQuery>,
+) -> Result<Json<Value>, ProcessorError> {
+    let hits = state.store.search(&params.q, params.limit.unwrap_or(50))?;
+
+    // Stored transcripts remember the media file name; it only maps back to
+    // a media ID while that file is unchanged
+    let records: HashMap<String, String> = state.workspace
+        .media_records()
+        .into_iter()
+        .map(|record| (record.file, record.id))
+        .collect();
+    let mut media_ids: HashMap<String, Option<String>> = HashMap::new();
+    for hit in &hits {
+        if media_ids.contains_key(&hit.media_hash) {
+            continue;
+        }
+        let path = state.workspace.media_dir().join(&hit.media_name);
+        let current = path.is_file() && state.store.media_hash(&path).is_ok_and(|hash| hash == hit.media_hash);
+        let id = current.then(|| records.get(&hit.media_name).cloned().unwrap_or_else(|| hit.media_name.clone()));
+        media_ids.insert(hit.media_hash.clone(), id);
+    }
+
+    tracing::info!("Search for {:?} found {} segments", params.q, hits.len());
+    Ok(Json(json!({
+        "query": params.q,
+        "count": hits.len(),
+        "results": hits.iter().map(|hit| {
+            let media_id = media_ids.get(&hit.media_hash).cloned().flatten();
+            let mut value = json!({
+                "media_id": media_id,
+                "media_hash": hit.media_hash,
+                "media_name": hit.media_name,
+                "start": hit.start,
+                "end": hit.end,
+                "text": hit.text,
+                "highlighted": hit.highlighted
+            });
+            if let Some(id) = &media_id {
+                value["audio_url"] = json!(snippet_url(id, hit.start, hit.end));
+            }
+            value
+        }).collect::<Vec<_>>()
+    })))
+}
+
+/// Stored transcripts and generated content for one media file, looked up by
+/// (a prefix of) its SHA-256
+async fn get_stored_transcript(
+    State(state): State<AppState>,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+) -> Result<Json<Value>, ProcessorError> {
+    let transcripts = state.store.find_transcripts(&hash)?;
+    if transcripts.is_empty() {
+        return Err(ProcessorError::NotFound(format!("No stored transcript for {}", hash)));
     }
+
+    Ok(Json(json!({
+        "transcripts": transcripts,
+        "content": state.store.find_content(&hash)?
+    })))
+}
+
+async fn delete_stored_transcript(
+    State(state): State<AppState>,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+) -> Result<Json<Value>, ProcessorError> {
+    let (transcripts, content) = state.store.delete(&hash)?;
+    if transcripts == 0 && content == 0 {
+        return Err(ProcessorError::NotFound(format!("No stored transcript for {}", hash)));
+    }
+
+    Ok(Json(json!({
+        "message": "Stored transcript deleted",
+        "transcripts_deleted": transcripts,
+        "content_deleted": content
+    })))
 }
 
 #[derive(Deserialize)]
-struct GenerateQuery {
+struct ThumbnailQuoteRequest {
+    quote: String,
+    media_id: String,
     template: Option<String>,
+    /// Number of candidate frames (default 5, at most 30)
+    count: Option<usize>,
+    /// Seconds either side of the quote to spread candidates over (default 1.0)
+    spread: Option<f64>,
+}
+
+impl Validate for ThumbnailQuoteRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.quote.trim().is_empty() {
+            return Err(ProcessorError::BadRequest("quote is required".to_string()));
+        }
+        if self.count.is_some_and(|count| !(1..=30).contains(&count)) {
+            return Err(ProcessorError::BadRequest("count must be between 1 and 30".to_string()));
+        }
+        if self.spread.is_some_and(|seconds| !(seconds.is_finite() && seconds >= 0.0)) {
+            return Err(ProcessorError::BadRequest("spread must not be negative".to_string()));
+        }
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
 }
 
-async fn generate_test_content(Query(params): Query<GenerateQuery>) -> Json<Value> {
-    let client = OpenAIClient::new()
-        .expect("Failed to create OpenAI client");
+/// Find when a transcript quote was said and grab candidate thumbnail frames
+/// around that moment
+async fn thumbnail_for_quote(State(state): State<AppState>, JsonBody(params): JsonBody<ThumbnailQuoteRequest>) -> Result<Json<Value>, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
 
-    println!("Starting YouTube content generation..."); 
+    let mut transcription = transcriber(&state, None, false, &TranscriptionOptions::default())?.transcribe(&input_path).await?;
+    Glossary::load(&PromptTemplate::from_name(params.template.as_deref())?)
+        .apply_to_segments(&mut transcription.segments);
 
-    let test_transcript = "Okay, what is up Medman Sports? Today we're talking about tennis serves...";
+    let found = quotes::find_quote(&transcription.segments, &params.quote, 0.5)
+        .ok_or_else(|| ProcessorError::NotFound(format!("Quote not found in transcript: {}", params.quote)))?;
 
-    let template = match params.template.as_deref() {
-        Some("mama-meditations") => PromptTemplate::MamaMeditations,
-        Some("med-man-sports") => PromptTemplate::MedManSports,
-        _ => PromptTemplate::MedManSports,
-    };
+    let times = quotes::candidate_times(found.time, params.spread.unwrap_or(1.0), params.count.unwrap_or(5));
+    let output_dir = thumbnails_dir(&state, &input_path);
+    let frame_times = times.clone();
+    let paths = process::blocking(move || {
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg
+            .with_input(&input_path)
+            .with_output_dir(output_dir);
+        ffmpeg.extract_frames(&frame_times, None)
+    })
+    .await?;
 
-    match client.generate_youtube_content(test_transcript, template).await {
-        Ok(response) => {
-            match serde_json::from_str::<Value>(&response) {
-                Ok(content) => Json(content),
-                Err(e) => {
-                    println!("Failed to parse response as JSON: {}", e);
-                    Json(json!({
-                        "error": "Failed to parse YouTube content",
-                        "raw_response": response
-                    }))
-                }
+    Ok(Json(json!({
+        "match": found,
+        "frames": frame_entries(&state, &times, &paths)
+    })))
+}
+
+/// Where thumbnails for a media file are written
+fn thumbnails_dir(state: &AppState, input_path: &Path) -> PathBuf {
+    let stem = input_path.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+    state.workspace.exports_dir().join(format!("{}-thumbnails", stem))
+}
+
+/// Extracted frames with their times and download links
+fn frame_entries(state: &AppState, times: &[f64], paths: &[PathBuf]) -> Vec<Value> {
+    times
+        .iter()
+        .zip(paths)
+        .map(|(time, path)| {
+            let relative = state.workspace.relative(path);
+            json!({
+                "time": time,
+                "path": relative,
+                "download_url": format!(
+                    "/media/download?{}",
+                    serde_urlencoded::to_string([("path", &relative)]).unwrap_or_default()
+                )
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ThumbnailRequest {
+    media_id: String,
+    /// Times in seconds, e.g. `[12.5]` or `[3, 12.5, 40]`
+    t: Option<Vec<f64>>,
+    /// Pick this many visually distinct frames instead (at most 30)
+    count: Option<usize>,
+    /// Frame size; both or neither (default: the video's own size)
+    width: Option<u32>,
+    height: Option<u32>,
+    format: Option<ImageFormat>,
+    /// Scene score in [0, 1] a cut must exceed to count as distinct (default 0.3)
+    scene_threshold: Option<f64>,
+}
+
+impl ThumbnailRequest {
+    fn size(&self) -> Option<(u32, u32)> {
+        self.width.zip(self.height)
+    }
+}
+
+impl Validate for ThumbnailRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.width.is_some() != self.height.is_some() {
+            return Err(ProcessorError::BadRequest("Give both width and height, or neither".to_string()));
+        }
+        if self.t.is_some() && self.count.is_some() {
+            return Err(ProcessorError::BadRequest("Give either t or count, not both".to_string()));
+        }
+        if let Some(times) = &self.t {
+            if !(1..=30).contains(&times.len()) {
+                return Err(ProcessorError::BadRequest("Give between 1 and 30 times".to_string()));
             }
-        },
-        Err(e) => {
-            println!("YouTube content generation failed: {}", e);
-            Json(json!({
-                "error": format!("YouTube content generation failed: {}", e)
-            }))
+            if times.iter().any(|time| !(time.is_finite() && *time >= 0.0)) {
+                return Err(ProcessorError::BadRequest("Times must not be negative".to_string()));
+            }
+        }
+        if self.count.is_some_and(|count| !(1..=30).contains(&count)) {
+            return Err(ProcessorError::BadRequest("count must be between 1 and 30".to_string()));
         }
+        if self.scene_threshold.is_some_and(|threshold| !(0.0..=1.0).contains(&threshold)) {
+            return Err(ProcessorError::BadRequest("scene_threshold must be between 0 and 1".to_string()));
+        }
+        Ok(())
     }
 }
 
-#[tokio::main]
-async fn main() {
-    // Load environment variables from .env file
-    dotenv().ok();
+/// Grab still frames for thumbnails, either at given times or the most
+/// visually distinct ones. Without either, one frame 10% into the video.
+async fn thumbnail(State(state): State<AppState>, JsonBody(params): JsonBody<ThumbnailRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input_path = state.workspace.require_media(&params.media_id)?;
 
-    // Build our router
-    let app = Router::new()
-        .route("/hello", get(hello))
-        .route("/media/info", get(media_info))
-        .route("/split", get(split_video))
-        .route("/merge", get(merge_chunks))
-        .route("/split-region", get(split_region))
-        .route("/transcribe", get(transcribe))
-        .route("/transcribe-to-json", get(transcribe_to_json))
-        .route("/transcribe-and-optimize", get(transcribe_and_optimize))
-        .route("/chat", get(chat))
-        .route("/generate", get(generate_test_content));
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg
+            .with_input(&input_path)
+            .with_output_dir(thumbnails_dir(&state, &input_path))
+            .with_image_format(params.format.unwrap_or_default())
+            .with_cache(state.cache.clone());
+        if !ffmpeg.get_info()?.has_video() {
+            return Err(ProcessorError::BadRequest(format!("{} has no video stream", params.media_id)));
+        }
+
+        let times: Vec<f64> = match (&params.t, params.count) {
+            (Some(times), _) => times.clone(),
+            (None, Some(count)) => {
+                let changes = ffmpeg.scene_changes(params.scene_threshold.unwrap_or(0.3))?;
+                ffmpeg::pick_distinct_times(&changes, count, ffmpeg.duration_seconds()?)
+            }
+            (None, None) => vec![ffmpeg.duration_seconds()? * 0.1],
+        };
+
+        let paths = ffmpeg.extract_frames(&times, params.size())?;
+
+        Ok(Json(json!({
+            "media_id": params.media_id,
+            "frames": frame_entries(&state, &times, &paths)
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct WaveformQuery {
+    media_id: String,
+    /// Values per second of media (default 10)
+    samples_per_second: Option<u32>,
+    /// Also measure EBU R128 loudness (default true)
+    loudness: Option<bool>,
+}
+
+/// Peak/RMS envelope and loudness stats for drawing and checking a track
+async fn waveform(State(state): State<AppState>, Query(params): Query<WaveformQuery>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input_path = state.workspace.require_media(&params.media_id)?;
+
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg.with_input(&input_path);
+        let waveform = ffmpeg.waveform(params.samples_per_second.unwrap_or(10))?;
+        let loudness = if params.loudness.unwrap_or(true) {
+            Some(ffmpeg.loudness()?)
+        } else {
+            None
+        };
+
+        Ok(Json(json!({
+            "media_id": params.media_id,
+            "duration": waveform.duration,
+            "samples_per_second": waveform.samples_per_second,
+            "peaks": waveform.peaks,
+            "rms": waveform.rms,
+            "loudness": loudness
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct IntroRequest {
+    media_id: String,
+    apply: Option<bool>,
+}
+
+/// Detect leading black frames or a static slate; `apply` trims them off
+/// the media file in place
+async fn detect_intro(State(state): State<AppState>, Json(params): Json<IntroRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input_path = state.workspace.require_media(&params.media_id)?;
+
+        if params.apply.unwrap_or(false) {
+            let intro = match state.workspace.media_record(&params.media_id) {
+                Some(mut record) => media::trim_uploaded_intro(&state.workspace, &mut record)?,
+                None => media::trim_intro(&input_path)?,
+            };
+            return Ok(Json(json!({ "trimmed": intro.is_some(), "intro": intro })));
+        }
+
+        let mut ffmpeg = FFmpegClient::new();
+        let intro = ffmpeg.with_input(&input_path).detect_intro(media::intro_options())?;
+        Ok(Json(json!({ "intro": intro })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct SplitPointsRequest {
+    media_id: String,
+    min_silence: Option<f64>,
+    max_similarity: Option<f64>,
+    window: Option<f64>,
+    min_part: Option<f64>,
+    apply: Option<bool>,
+}
+
+impl Validate for SplitPointsRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        let positive = |value: Option<f64>| value.is_none_or(|v| v.is_finite() && v > 0.0);
+        if !positive(self.min_silence) {
+            return Err(ProcessorError::BadRequest("min_silence must be greater than 0".to_string()));
+        }
+        if !positive(self.window) {
+            return Err(ProcessorError::BadRequest("window must be greater than 0".to_string()));
+        }
+        if self.min_part.is_some_and(|v| !v.is_finite() || v < 0.0) {
+            return Err(ProcessorError::BadRequest("min_part must not be negative".to_string()));
+        }
+        if self.max_similarity.is_some_and(|v| !(0.0..=1.0).contains(&v)) {
+            return Err(ProcessorError::BadRequest("max_similarity must be between 0 and 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Propose split points where a long recording seems to switch videos, and
+/// optionally cut it into parts registered as media of their own
+async fn detect_split_points(State(state): State<AppState>, JsonBody(params): JsonBody<SplitPointsRequest>) -> Result<Json<Value>, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
+
+    let transcription = transcriber(&state, None, false, &TranscriptionOptions::default())?.transcribe(&input_path).await?;
+
+    let options = topics::SplitOptions {
+        min_silence: params.min_silence.unwrap_or(3.0),
+        max_similarity: params.max_similarity.unwrap_or(0.15),
+        window: params.window.unwrap_or(60.0),
+        min_part: params.min_part.unwrap_or(60.0),
+    };
+    let points = topics::detect_split_points(&transcription.segments, &options);
+
+    if !params.apply.unwrap_or(false) || points.is_empty() {
+        return Ok(Json(json!({ "split_points": points })));
+    }
+
+    let times: Vec<f64> = points.iter().map(|p| p.time).collect();
+    let parts = process::blocking(move || {
+        let scratch = workspace::scratch_dir(&state.workspace.media_dir())?;
+        let parts = FFmpegClient::new()
+            .with_input(&input_path)
+            .with_output_dir(scratch.path())
+            .split_at_points(&times)?;
+
+        // Each part becomes media of its own, named after the recording
+        let name = match state.workspace.media_record(&params.media_id) {
+            Some(record) => record.name,
+            None => params.media_id.clone(),
+        };
+        let stem = Path::new(&name).file_stem().and_then(|s| s.to_str()).unwrap_or("part");
+        parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                let extension = part.extension().and_then(|s| s.to_str()).unwrap_or("mp3");
+                media::register_file(&state.workspace, part, &format!("{}_part{:02}.{}", stem, i + 1, extension))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await?;
+
+    Ok(Json(json!({
+        "split_points": points,
+        "parts": parts
+            .iter()
+            .map(|record| json!({ "media_id": record.id, "name": record.name }))
+            .collect::<Vec<_>>()
+    })))
+}
+
+#[derive(Deserialize)]
+struct SnippetQuery {
+    media_id: String,
+    start: f64,
+    end: f64,
+}
+
+/// Lazily cut (or fetch from cache) and stream the audio for one segment
+async fn snippet(State(state): State<AppState>, Query(params): Query<SnippetQuery>) -> Result<Response, ProcessorError> {
+    let input = state.workspace.require_media(&params.media_id)?;
+
+    let path = process::blocking(move || {
+        FFmpegClient::new()
+            .with_cache(state.cache.clone())
+            .with_input(&input)
+            .with_output_dir(state.workspace.snippets_dir())
+            .snippet(params.start, params.end)
+    })
+    .await?;
+
+    Ok(media::stream_file(&path).await)
+}
+
+#[derive(Deserialize)]
+struct ExtractAudioRequest {
+    media_id: String,
+    /// mp3, wav or pcm (16-bit mono WAV for whisper); defaults to pcm
+    codec: Option<String>,
+    /// Hz; defaults to 16000 for pcm and 44100 otherwise
+    sample_rate: Option<u32>,
+}
+
+impl ExtractAudioRequest {
+    fn codec(&self) -> Result<AudioCodec, ProcessorError> {
+        match self.codec.as_deref() {
+            None => Ok(AudioCodec::Pcm),
+            Some(name) => AudioCodec::from_name(name)
+                .ok_or_else(|| ProcessorError::BadRequest(format!("Unknown codec: {}", name))),
+        }
+    }
+}
+
+impl Validate for ExtractAudioRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.codec().map(|_| ())
+    }
+}
+
+/// Extract the audio track of a video (or re-encode an audio file)
+async fn extract_audio(State(state): State<AppState>, JsonBody(params): JsonBody<ExtractAudioRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = state.workspace.require_media(&params.media_id)?;
+        let codec = params.codec()?;
+        let sample_rate = params.sample_rate.unwrap_or(codec.default_sample_rate());
+
+        let mut ffmpeg = FFmpegClient::new();
+        let path = ffmpeg
+            .with_cache(state.cache.clone())
+            .with_input(&input)
+            .with_output_dir(state.workspace.audio_dir())
+            .extract_audio(codec, sample_rate)?;
+
+        Ok(Json(json!({
+            "media_id": params.media_id,
+            "output_file": state.workspace.relative(&path),
+            "codec": codec.name(),
+            "sample_rate": sample_rate
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct NormalizeRequest {
+    media_id: String,
+    /// Integrated loudness to aim for (default -14 LUFS, what YouTube plays back at)
+    target_lufs: Option<f64>,
+    /// Re-encode the result into this format; defaults to the source's container
+    format: Option<OutputFormat>,
+    /// Audio bitrate in kbps for lossy formats (default 192)
+    bitrate: Option<u32>,
+    /// Output sample rate in Hz; defaults to the source's
+    sample_rate: Option<u32>,
+}
+
+impl Validate for NormalizeRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.target_lufs.is_some_and(|lufs| !(-70.0..=-5.0).contains(&lufs)) {
+            return Err(ProcessorError::BadRequest("target_lufs must be between -70 and -5".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Normalize a file's loudness (two-pass EBU R128) into the exports
+/// directory, optionally transcoding it as well
+async fn normalize(State(state): State<AppState>, JsonBody(params): JsonBody<NormalizeRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = state.workspace.require_media(&params.media_id)?;
+        let exports_dir = state.workspace.exports_dir();
+
+        let mut ffmpeg = FFmpegClient::new();
+        let normalization = ffmpeg
+            .with_input(&input)
+            .with_output_dir(&exports_dir)
+            .normalize_loudness(params.target_lufs.unwrap_or(-14.0))?;
+
+        // The loudnorm pass already wrote the source's container at its own rate
+        let written = OutputFormat::of(&normalization.output);
+        let format = params.format.or(written).unwrap_or_default();
+        let transcode = Some(format) != written || params.bitrate.is_some() || params.sample_rate.is_some();
+        let output = if transcode {
+            let mut ffmpeg = FFmpegClient::new();
+            let transcoded = ffmpeg
+                .with_cache(state.cache.clone())
+                .with_input(&normalization.output)
+                .with_output_dir(&exports_dir)
+                .transcode(format, params.bitrate, params.sample_rate);
+            // The intermediate goes whether or not the transcode worked
+            std::fs::remove_file(&normalization.output)?;
+            transcoded?
+        } else {
+            normalization.output.clone()
+        };
+
+        Ok(Json(json!({
+            "media_id": params.media_id,
+            "output_file": state.workspace.relative(&output),
+            "target_lufs": normalization.target_lufs,
+            "before": normalization.before,
+            "after": normalization.after
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct HlsRequest {
+    /// The media to preview, or
+    media_id: Option<String>,
+    /// a processed file, relative to the data directory (e.g. `outputs/exports/...`)
+    path: Option<String>,
+    /// Target segment length in seconds (default 6)
+    segment_duration: Option<f64>,
+}
+
+impl HlsRequest {
+    /// Rounded to the millisecond, so 6, 6.0 and 6.000001 share a playlist
+    fn segment_duration(&self) -> f64 {
+        (self.segment_duration.unwrap_or(6.0) * 1000.0).round() / 1000.0
+    }
+}
+
+impl Validate for HlsRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.media_id.is_some() == self.path.is_some() {
+            return Err(ProcessorError::BadRequest("Give either media_id or path".to_string()));
+        }
+        if !(1.0..=60.0).contains(&self.segment_duration()) {
+            return Err(ProcessorError::BadRequest("segment_duration must be between 1 and 60 seconds".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Package media for in-browser preview over HLS. Playlists are kept per
+/// content and segment length, so asking again is instant.
+async fn hls(State(state): State<AppState>, JsonBody(params): JsonBody<HlsRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = match (&params.media_id, &params.path) {
+            (Some(id), _) => state.workspace.require_media(id)?,
+            (None, Some(path)) => state.workspace
+                .resolve(path)
+                .filter(|path| path.is_file())
+                .ok_or_else(|| ProcessorError::NotFound(format!("File not found: {}", path)))?,
+            (None, None) => return Err(ProcessorError::BadRequest("Give either media_id or path".to_string())),
+        };
+        let segment_duration = params.segment_duration();
+
+        let hash = state.store.media_hash(&input)?;
+        let name = format!("{}-{}s", &hash[..16.min(hash.len())], segment_duration);
+        let output_dir = state.workspace.hls_dir().join(&name);
+        let playlist = output_dir.join(ffmpeg::HLS_PLAYLIST);
+
+        let mut segments = std::fs::read_dir(&output_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().extension().and_then(|s| s.to_str()) == Some("ts"))
+                    .count()
+            })
+            .unwrap_or(0);
+        let cached = playlist.is_file() && segments > 0;
+        let playlist = if cached {
+            playlist
+        } else {
+            let mut ffmpeg = FFmpegClient::new();
+            let output = ffmpeg.with_input(&input).to_hls(segment_duration, &output_dir)?;
+            segments = output.segments.len();
+            output.playlist
+        };
+
+        Ok(Json(json!({
+            "playlist_url": format!("/hls/{}/{}", name, playlist.file_name().and_then(|s| s.to_str()).unwrap_or_default()),
+            "output_dir": state.workspace.relative(&output_dir),
+            "segment_duration": segment_duration,
+            "segments": segments,
+            "cached": cached
+        })))
+    })
+    .await
+}
+
+/// Serve HLS playlists and segments for a web player
+async fn hls_file(
+    State(state): State<AppState>,
+    axum::extract::Path((name, file)): axum::extract::Path<(String, String)>,
+) -> Response {
+    let plain = |part: &str| {
+        let mut components = Path::new(part).components();
+        matches!((components.next(), components.next()), (Some(std::path::Component::Normal(_)), None))
+    };
+    let servable = matches!(Path::new(&file).extension().and_then(|s| s.to_str()), Some("m3u8" | "ts"));
+    if !plain(&name) || !plain(&file) || !servable {
+        return ProcessorError::NotFound("File not found".to_string()).into_response();
+    }
+    media::stream_inline(&state.workspace.hls_dir().join(name).join(file)).await
+}
+
+#[derive(Deserialize)]
+struct SubtitlesParams {
+    media_id: String,
+    /// `srt` (default) or `vtt`
+    format: Option<SubtitleFormat>,
+    template: Option<String>,
+    anonymize: Option<bool>,
+    /// `local` or `api`; defaults to TRANSCRIPTION_BACKEND
+    backend: Option<BackendKind>,
+}
+
+/// Transcribe and write a caption file for a media file into the exports
+/// directory, returning its path
+async fn write_subtitles(
+    state: &AppState,
+    input_path: &Path,
+    params: &SubtitlesParams,
+    options: &TranscriptionOptions,
+) -> Result<PathBuf, ProcessorError> {
+    let mut transcription = transcriber(state, params.backend, false, options)?.transcribe(input_path).await?;
+
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    Glossary::load(&template).apply_to_segments(&mut transcription.segments);
+    if params.anonymize.unwrap_or(false) {
+        anonymize_segments(&template, &mut transcription.segments);
+    }
+
+    let format = params.format.unwrap_or_default();
+    let exports_dir = state.workspace.exports_dir();
+    std::fs::create_dir_all(&exports_dir)?;
+    let path = exports_dir.join(format!("{}.{}", params.media_id, format.extension()));
+    std::fs::write(&path, format.render(&transcription.segments))?;
+    Ok(path)
+}
+
+/// Download captions for a media file as SRT or WebVTT
+async fn transcribe_subtitles(
+    State(state): State<AppState>,
+    JsonBody(SubtitlesRequest { params, options }): JsonBody<SubtitlesRequest>,
+) -> Result<Response, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
+    let path = write_subtitles(&state, &input_path, &params, &options).await?;
+    Ok(media::stream_file(&path).await)
+}
+
+/// `/transcribe/subtitles`' and `/media/burn-subtitles`' body: the caption
+/// settings and the whisper settings
+#[derive(Deserialize)]
+struct SubtitlesRequest {
+    #[serde(flatten)]
+    params: SubtitlesParams,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+}
+
+impl Validate for SubtitlesRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options.validate()?;
+        PromptTemplate::from_name(self.params.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Render a copy of a video with its captions drawn into the picture
+async fn burn_subtitles(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<SubtitlesRequest>,
+) -> Result<Json<Value>, ProcessorError> {
+    let SubtitlesRequest { params, options } = request;
+    let input_path = state.workspace.require_media(&params.media_id)?;
+    let subtitles = write_subtitles(&state, &input_path, &params, &options).await?;
+
+    let extension = input_path.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+    let output = state.workspace.exports_dir().join(format!("{}.captioned.{}", params.media_id, extension));
+    let captions = subtitles.clone();
+    let video = process::blocking(move || FFmpegClient::new().with_input(&input_path).burn_subtitles(&captions, &output)).await?;
+
+    Ok(Json(json!({
+        "message": "Subtitles burned in successfully",
+        "subtitles": state.workspace.relative(&subtitles),
+        "output_file": state.workspace.relative(&video)
+    })))
+}
+
+/// `/transcribe-to-json`'s body: the transcript settings and the whisper
+/// settings
+#[derive(Deserialize)]
+struct TranscribeTextRequest {
+    #[serde(flatten)]
+    params: TranscribeQuery,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+}
+
+impl Validate for TranscribeTextRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options.validate()?;
+        PromptTemplate::from_name(self.params.template.as_deref()).map(|_| ())
+    }
+}
+
+async fn transcribe_to_json(
+    State(state): State<AppState>,
+    JsonBody(TranscribeTextRequest { params, options }): JsonBody<TranscribeTextRequest>,
+) -> Result<Json<Value>, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
+
+    let transcriber = transcriber(&state, params.backend, false, &options)?;
+    let mut transcription = transcriber.transcribe(&input_path).await?;
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    Glossary::load(&template).apply_to_segments(&mut transcription.segments);
+    if params.anonymize.unwrap_or(false) {
+        anonymize_segments(&template, &mut transcription.segments);
+    }
+
+    // Concatenate all segments into one text
+    let full_text: String = transcription.segments
+        .iter()
+        .map(|segment| segment.text.clone())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let word_count = full_text.split_whitespace().count();
+
+    Ok(Json(json!({
+        "text": full_text,
+        "language": transcription.language,
+        "backend": transcriber.name(),
+        "word_count": word_count,
+        "duration_seconds": transcription.segments.last().map(|s| s.end).unwrap_or(0.0)
+    })))
+}
+
+#[derive(Deserialize)]
+struct TranscribeQuery {
+    media_id: String,
+    template: Option<String>,
+    anonymize: Option<bool>,
+    /// Working title for `{{VIDEO_TITLE}}`; defaults to the uploaded file name
+    title: Option<String>,
+    /// `local` or `api`; defaults to TRANSCRIPTION_BACKEND
+    backend: Option<BackendKind>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+}
+
+/// `/transcribe-and-optimize`'s body: its own parameters and the whisper
+/// settings
+#[derive(Deserialize)]
+struct OptimizeRequest {
+    #[serde(flatten)]
+    params: TranscribeQuery,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+    #[serde(flatten)]
+    job: JobOptions,
+}
+
+impl Validate for OptimizeRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options.validate()?;
+        PromptTemplate::from_name(self.params.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Transcribe a video and write its YouTube title, description, tags and
+/// chapters, optionally as a background job
+async fn transcribe_and_optimize(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<OptimizeRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    if !request.job.background() {
+        let content = optimize_media(&state, &request.params, &request.options).await?;
+        return Ok((StatusCode::OK, Json(json!(content))));
+    }
+    state.workspace.require_media(&request.params.media_id)?;
+    let job_state = state.clone();
+    queue_job(&state, "transcribe_and_optimize", request.job.callback_url.clone(), move |_| {
+        block_on(optimize_media(&job_state, &request.params, &request.options))
+    })
+}
+
+async fn optimize_media(
+    state: &AppState,
+    params: &TranscribeQuery,
+    options: &TranscriptionOptions,
+) -> Result<YouTubeContent, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
+    let title = media_title(state, &params.media_id);
+    optimize_file(state, &input_path, &title, params, options).await
+}
+
+/// Transcribe a video and have the LLM write its YouTube title, description,
+/// tags and chapters. `default_title` stands in when the request has none.
+async fn optimize_file(
+    state: &AppState,
+    input_path: &Path,
+    default_title: &str,
+    params: &TranscribeQuery,
+    options: &TranscriptionOptions,
+) -> Result<YouTubeContent, ProcessorError> {
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    let anonymize = params.anonymize.unwrap_or(false);
+
+    // Content generated for this media and template before is reused
+    let media_hash = state.store.media_hash(input_path)?;
+    let mut content_key = template.name().to_string();
+    if anonymize {
+        content_key.push_str("+anonymized");
+    }
+    if let Some(title) = &params.title {
+        content_key.push_str(&format!("+title={}", title));
+    }
+    if let Some(provider) = params.provider {
+        content_key.push_str(&format!("+provider={}", provider.name()));
+    }
+    let stored = state.store
+        .content(&media_hash, "youtube", &content_key)?
+        .and_then(|stored| serde_json::from_str::<YouTubeContent>(&stored).ok());
+    if let Some(content) = stored {
+        tracing::info!("Using stored YouTube content for {:?} ({})", input_path, content_key);
+        return Ok(content);
+    }
+
+    // First, transcribe the video
+    let mut transcription = transcriber(state, params.backend, false, options)?.transcribe(input_path).await?;
+
+    Glossary::load(&template).apply_to_segments(&mut transcription.segments);
+    if anonymize {
+        anonymize_segments(&template, &mut transcription.segments);
+    }
+
+    // Combine all segments into one text
+    let full_text: String = transcription.segments
+        .iter()
+        .map(|segment| segment.text.clone())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    // Then optimize the content using the LLM
+    let llm = LlmClient::new(params.provider)?;
+
+    let video_title = match &params.title {
+        Some(title) => title.clone(),
+        None => default_title.to_string(),
+    };
+    let content = llm.generate_youtube_content(&full_text, &video_title, &template).await?;
+    let serialized = serde_json::to_string(&content)
+        .map_err(|e| ProcessorError::Database(e.to_string()))?;
+    state.store.save_content(&media_hash, "youtube", &content_key, &serialized)?;
+    Ok(content)
+}
+
+/// A media file's name as uploaded, without its extension
+fn media_title(state: &AppState, media_id: &str) -> String {
+    state.workspace
+        .media_record(media_id)
+        .map(|record| record.name)
+        .as_deref()
+        .map(|name| Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or(name).to_string())
+        .unwrap_or_else(|| media_id.to_string())
+}
+
+#[derive(Deserialize)]
+struct BatchRequest {
+    /// Media IDs to process
+    media_ids: Option<Vec<String>>,
+    /// Or every file in this directory under the media directory
+    directory: Option<String>,
+    template: Option<String>,
+    anonymize: Option<bool>,
+    backend: Option<BackendKind>,
+    provider: Option<Provider>,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+}
+
+impl Validate for BatchRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options.validate()?;
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Transcribe and optimize several videos at once. Answers with a batch ID
+/// straight away; `/batch/:id` reports each file as it finishes.
+async fn batch_process(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<BatchRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    // Resolve every input up front so a typo fails the whole request
+    let inputs: Vec<(String, PathBuf, String)> = match (&request.media_ids, &request.directory) {
+        (Some(ids), None) if !ids.is_empty() => ids
+            .iter()
+            .map(|id| Ok((id.clone(), state.workspace.require_media(id)?, media_title(&state, id))))
+            .collect::<Result<_, ProcessorError>>()?,
+        (None, Some(directory)) => batch_directory(&state, directory)?,
+        _ => {
+            return Err(ProcessorError::BadRequest(
+                "Give either a non-empty media_ids list or a directory".to_string(),
+            ))
+        }
+    };
+
+    let paths: std::collections::HashMap<String, (PathBuf, String)> = inputs
+        .iter()
+        .map(|(input, path, title)| (input.clone(), (path.clone(), title.clone())))
+        .collect();
+    let labels: Vec<String> = inputs.into_iter().map(|(input, _, _)| input).collect();
+    let total = labels.len();
+
+    let exports = state.workspace.exports_dir();
+    std::fs::create_dir_all(&exports)?;
+    let label = state.workspace.relative(&exports);
+
+    let request = Arc::new(request);
+    let job_state = state.clone();
+    let batch_id = state.batches.submit("transcribe_and_optimize", labels, exports, &label, move |input| {
+        let state = job_state.clone();
+        let request = request.clone();
+        let (path, title) = paths[&input].clone();
+        async move {
+            let params = TranscribeQuery {
+                media_id: input,
+                template: request.template.clone(),
+                anonymize: request.anonymize,
+                title: None,
+                backend: request.backend,
+                provider: request.provider,
+            };
+            optimize_file(&state, &path, &title, &params, &request.options)
+                .await
+                .map(|content| json!(content))
+                .map_err(|e| e.to_string())
+        }
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "batch_id": batch_id,
+            "files": total,
+            "status_url": format!("/batch/{}", batch_id)
+        })),
+    ))
+}
+
+/// Every media file directly inside `directory` (relative to the media
+/// directory), by name, as (input, path, title)
+fn batch_directory(state: &AppState, directory: &str) -> Result<Vec<(String, PathBuf, String)>, ProcessorError> {
+    let relative = Path::new(directory);
+    if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir)) {
+        return Err(ProcessorError::BadRequest(format!("Invalid directory {:?}", directory)));
+    }
+    let dir = state.workspace.media_dir().join(relative);
+    if !dir.is_dir() {
+        return Err(ProcessorError::NotFound(format!("Directory not found in media: {}", directory)));
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.file_name().and_then(|s| s.to_str()).is_some_and(|name| !name.starts_with('.')))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(ProcessorError::BadRequest(format!("No files in {:?}", directory)));
+    }
+
+    let media_dir = state.workspace.media_dir();
+    Ok(files
+        .into_iter()
+        .map(|path| {
+            let input = path.strip_prefix(&media_dir).unwrap_or(&path).to_string_lossy().to_string();
+            let title = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+            (input, path, title)
+        })
+        .collect())
+}
+
+async fn get_batch(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<batch::Batch>, ProcessorError> {
+    state.batches
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| ProcessorError::NotFound(format!("Batch not found: {}", id)))
+}
+
+#[derive(Deserialize)]
+struct BilingualRequest {
+    language: String,
+    media_id: String,
+    template: Option<String>,
+    anonymize: Option<bool>,
+    /// `local` or `api`; defaults to TRANSCRIPTION_BACKEND
+    backend: Option<BackendKind>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+}
+
+impl Validate for BilingualRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.language.trim().is_empty() {
+            return Err(ProcessorError::BadRequest("language is required".to_string()));
+        }
+        // The slug names the export files, so it must stay a plain name
+        let slug = self.language_slug();
+        if !slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+            return Err(ProcessorError::BadRequest(
+                "language may only contain ASCII letters, digits, spaces and dashes".to_string(),
+            ));
+        }
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
+}
+
+impl BilingualRequest {
+    /// `language` as it appears in export file names, e.g. `brazilian-portuguese`
+    fn language_slug(&self) -> String {
+        self.language.trim().to_lowercase().replace(' ', "-")
+    }
+}
+
+async fn transcribe_bilingual(State(state): State<AppState>, JsonBody(params): JsonBody<BilingualRequest>) -> Result<Json<Value>, ProcessorError> {
+    let input_path = state.workspace.require_media(&params.media_id)?;
+
+    let mut transcription = transcriber(&state, params.backend, false, &TranscriptionOptions::default())?.transcribe(&input_path).await?;
+
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    Glossary::load(&template).apply_to_segments(&mut transcription.segments);
+    if params.anonymize.unwrap_or(false) {
+        anonymize_segments(&template, &mut transcription.segments);
+    }
+
+    let llm = LlmClient::new(params.provider)?;
+
+    let lines: Vec<String> = transcription.segments
+        .iter()
+        .map(|segment| segment.text.trim().to_string())
+        .collect();
+
+    let translations = llm.translate_lines(&lines, &params.language).await?;
+
+    let exports_dir = state.workspace.exports_dir();
+    std::fs::create_dir_all(&exports_dir)?;
+
+    let stem = input_path.file_stem().and_then(|s| s.to_str()).unwrap_or("transcript");
+    let language_slug = params.language_slug();
+    let files = [
+        (
+            format!("{}.{}.srt", stem, language_slug),
+            subtitles::to_srt(&transcription.segments.iter().zip(&translations)
+                .map(|(segment, translation)| whisper::Segment {
+                    text: translation.clone(),
+                    ..segment.clone()
+                })
+                .collect::<Vec<_>>()),
+        ),
+        (
+            format!("{}.{}.bilingual.srt", stem, language_slug),
+            subtitles::to_bilingual_srt(&transcription.segments, &translations),
+        ),
+        (
+            format!("{}.{}.side-by-side.md", stem, language_slug),
+            subtitles::to_side_by_side(&transcription.segments, &translations, &params.language),
+        ),
+    ];
+
+    for (name, content) in &files {
+        std::fs::write(exports_dir.join(name), content)?;
+    }
+
+    Ok(Json(json!({
+        "language": params.language,
+        "files": files
+            .iter()
+            .map(|(name, _)| state.workspace.relative(&exports_dir.join(name)))
+            .collect::<Vec<_>>(),
+        "pairs": transcription.segments.iter().zip(&translations).map(|(segment, translation)| {
+            json!({
+                "start": segment.start,
+                "end": segment.end,
+                "original": segment.text.trim(),
+                "translation": translation
+            })
+        }).collect::<Vec<_>>()
+    })))
+}
+
+#[derive(Deserialize)]
+struct StillVideoRequest {
+    audio: String,
+    image: String,
+    motion: Option<String>,
+}
+
+impl StillVideoRequest {
+    fn motion(&self) -> Result<ImageMotion, ProcessorError> {
+        match self.motion.as_deref() {
+            None | Some("static") => Ok(ImageMotion::Static),
+            Some("ken-burns") => Ok(ImageMotion::KenBurns),
+            Some(other) => Err(ProcessorError::BadRequest(format!("Unknown motion: {}", other))),
+        }
+    }
+}
+
+impl Validate for StillVideoRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.motion().map(|_| ())
+    }
+}
+
+async fn still_video(State(state): State<AppState>, JsonBody(params): JsonBody<StillVideoRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let audio = state.workspace.require_media(&params.audio)?;
+        let image = state.workspace.require_media(&params.image)?;
+        let motion = params.motion()?;
+
+        let output_dir = state.workspace.exports_dir();
+        std::fs::create_dir_all(&output_dir)?;
+        let stem = audio.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+        let output_path = output_dir.join(format!("{}.mp4", stem));
+
+        let mut ffmpeg = FFmpegClient::new();
+        let video = ffmpeg.with_input(&audio).still_image_video(&image, motion, &output_path)?;
+
+        Ok(Json(json!({
+            "message": "Video rendered successfully",
+            "output_file": state.workspace.relative(&video)
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct LoopRequest {
+    media_id: String,
+    crossfade: Option<f64>,
+    loops: Option<u32>,
+    duration: Option<f64>,
+}
+
+impl Validate for LoopRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.loops.is_none() && self.duration.is_none() {
+            return Err(ProcessorError::BadRequest("Either loops or duration is required".to_string()));
+        }
+        if self.duration.is_some_and(|seconds| !(seconds.is_finite() && seconds > 0.0)) {
+            return Err(ProcessorError::BadRequest("duration must be a positive number of seconds".to_string()));
+        }
+        if self.crossfade.is_some_and(|seconds| !(seconds.is_finite() && seconds > 0.0)) {
+            return Err(ProcessorError::BadRequest("crossfade must be a positive number of seconds".to_string()));
+        }
+        Ok(())
+    }
+}
+
+async fn loop_audio(State(state): State<AppState>, JsonBody(params): JsonBody<LoopRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = state.workspace.require_media(&params.media_id)?;
+        let crossfade = params.crossfade.unwrap_or(5.0);
+
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg.with_input(&input);
+        let track = ffmpeg.duration_seconds()?;
+        if track <= crossfade {
+            return Err(ProcessorError::BadRequest("Track is shorter than the crossfade".to_string()));
+        }
+
+        // Either an explicit loop count, or enough loops to cover the target duration
+        let loops = match (params.loops, params.duration) {
+            (Some(loops), _) => loops.max(1),
+            (None, Some(duration)) => (duration / (track - crossfade)).ceil() as u32,
+            (None, None) => return Err(ProcessorError::BadRequest("Either loops or duration is required".to_string())),
+        };
+
+        let output_dir = state.workspace.exports_dir();
+        std::fs::create_dir_all(&output_dir)?;
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let output_path = output_dir.join(format!("{}.loop.mp3", stem));
+
+        let output = ffmpeg.seamless_loop(crossfade, loops, params.duration, &output_path)?;
+
+        Ok(Json(json!({
+            "message": "Loop exported successfully",
+            "loops": loops,
+            "output_file": state.workspace.relative(&output)
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct GateRequest {
+    media_id: String,
+    threshold_db: Option<f64>,
+    room_tone_start: Option<f64>,
+    room_tone_duration: Option<f64>,
+    room_tone_db: Option<f64>,
+}
+
+impl GateRequest {
+    fn options(&self) -> GateOptions {
+        GateOptions {
+            threshold_db: self.threshold_db.unwrap_or(-40.0),
+            room_tone_start: self.room_tone_start.unwrap_or(0.0),
+            room_tone_duration: self.room_tone_duration.unwrap_or(1.0),
+            room_tone_db: self.room_tone_db.unwrap_or(-3.0),
+        }
+    }
+}
+
+impl Validate for GateRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options().validate()
+    }
+}
+
+async fn gate_audio(State(state): State<AppState>, JsonBody(params): JsonBody<GateRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = state.workspace.require_media(&params.media_id)?;
+        let options = params.options();
+
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg.with_input(&input);
+        if options.room_tone_start + options.room_tone_duration > ffmpeg.duration_seconds()? {
+            return Err(ProcessorError::BadRequest("The room tone runs past the end of the recording".to_string()));
+        }
+
+        let output_dir = state.workspace.exports_dir();
+        std::fs::create_dir_all(&output_dir)?;
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let extension = input.extension().and_then(|s| s.to_str()).unwrap_or("mp3");
+        let output_path = output_dir.join(format!("{}.gated.{}", stem, extension));
+
+        let output = ffmpeg.gate_with_room_tone(options, &output_path)?;
+
+        Ok(Json(json!({
+            "message": "Noise gate applied successfully",
+            "output_file": state.workspace.relative(&output)
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct TrimSilenceRequest {
+    media_id: String,
+    /// Level below which audio counts as silence (default -35 dB)
+    threshold_db: Option<f64>,
+    /// Shortest stretch worth cutting, in seconds (default 1)
+    min_duration: Option<f64>,
+    /// Silence left in place at each side of a cut, in seconds (default 0.25)
+    padding: Option<f64>,
+}
+
+impl TrimSilenceRequest {
+    fn min_duration(&self) -> f64 {
+        self.min_duration.unwrap_or(1.0)
+    }
+
+    fn padding(&self) -> f64 {
+        self.padding.unwrap_or(0.25)
+    }
+}
+
+impl Validate for TrimSilenceRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        let (min_duration, padding) = (self.min_duration(), self.padding());
+        if !(min_duration > 0.0 && padding >= 0.0 && padding * 2.0 < min_duration) {
+            return Err(ProcessorError::BadRequest("Need min_duration > 0 and 0 <= padding < min_duration / 2".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Cut dead air out of a recording
+async fn trim_silence(State(state): State<AppState>, JsonBody(params): JsonBody<TrimSilenceRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = state.workspace.require_media(&params.media_id)?;
+        let threshold_db = params.threshold_db.unwrap_or(-35.0);
+        let min_duration = params.min_duration();
+        let padding = params.padding();
+
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg.with_input(&input);
+        let silences = ffmpeg.detect_silence(threshold_db, min_duration)?;
+        if silences.is_empty() {
+            return Ok(Json(json!({
+                "message": "No silence found",
+                "silences": silences,
+                "removed_seconds": 0.0,
+                "output_file": null
+            })));
+        }
+
+        let output_dir = state.workspace.exports_dir();
+        std::fs::create_dir_all(&output_dir)?;
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("media");
+        let extension = input.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+        let output_path = output_dir.join(format!("{}.silence-trimmed.{}", stem, extension));
+        let output = ffmpeg.remove_silence(&silences, padding, &output_path)?;
+
+        let removed: f64 = silences.iter().map(|s| (s.duration() - 2.0 * padding).max(0.0)).sum();
+        Ok(Json(json!({
+            "message": "Silence removed successfully",
+            "silences": silences,
+            "removed_seconds": removed,
+            "output_file": state.workspace.relative(&output)
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct InsertSponsorRequest {
+    media_id: String,
+    clip: String,
+    at: f64,
+    fade: Option<f64>,
+}
+
+impl Validate for InsertSponsorRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if !(self.at.is_finite() && self.at > 0.0) {
+            return Err(ProcessorError::BadRequest("at must be a positive number of seconds".to_string()));
+        }
+        if self.fade.is_some_and(|seconds| !(seconds.is_finite() && seconds >= 0.0)) {
+            return Err(ProcessorError::BadRequest("fade must not be negative".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Insert a recorded sponsor clip into a video and record it in the EDL
+async fn insert_sponsor(State(state): State<AppState>, JsonBody(params): JsonBody<InsertSponsorRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input = state.workspace.require_media(&params.media_id)?;
+        let clip = state.workspace.require_media(&params.clip)?;
+        let fade = params.fade.unwrap_or(0.5);
+
+        let output_dir = state.workspace.exports_dir();
+        std::fs::create_dir_all(&output_dir)?;
+        let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+        let extension = input.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+        let output_path = output_dir.join(format!("{}.sponsored.{}", stem, extension));
+
+        let mut ffmpeg = FFmpegClient::new();
+        let output = ffmpeg.with_input(&input).insert_clip(&clip, params.at, fade, &output_path)?;
+
+        let clip_duration = FFmpegClient::new()
+            .with_input(&clip)
+            .duration_seconds()
+            .unwrap_or(0.0);
+        let edit = edl::Edit::InsertClip {
+            at: params.at,
+            clip: params.clip.clone(),
+            clip_duration,
+            fade,
+        };
+
+        let edl = edl::EditDecisionList::record(&input, &output, edit)?;
+
+        Ok(Json(json!({
+            "message": "Sponsor clip inserted successfully",
+            "output_file": state.workspace.relative(&output),
+            "edl": edl
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct EdlQuery {
+    path: String,
+}
+
+async fn get_edl(State(state): State<AppState>, Query(params): Query<EdlQuery>) -> Result<Json<Value>, ProcessorError> {
+    match state.workspace.resolve(&params.path) {
+        Some(path) if path.exists() => Ok(Json(json!(edl::EditDecisionList::load(&path)))),
+        _ => Err(ProcessorError::NotFound(format!("File not found: {}", params.path))),
+    }
+}
+
+#[derive(Deserialize)]
+struct MeditationRequest {
+    voice: String,
+    ambient: String,
+    image: String,
+    preset: Option<String>,
+    ambient_volume: Option<f64>,
+    fade_out: Option<f64>,
+}
+
+impl Validate for MeditationRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.ambient_volume.is_some_and(|volume| !(0.0..=1.0).contains(&volume)) {
+            return Err(ProcessorError::BadRequest("ambient_volume must be between 0 and 1".to_string()));
+        }
+        if self.fade_out.is_some_and(|seconds| seconds < 0.0) {
+            return Err(ProcessorError::BadRequest("fade_out must not be negative".to_string()));
+        }
+        Ok(())
+    }
+}
+
+async fn render_meditation(State(state): State<AppState>, JsonBody(params): JsonBody<MeditationRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let voice = state.workspace.require_media(&params.voice)?;
+        let image = state.workspace.require_media(&params.image)?;
+        let ambient = state.workspace.find_ambient(&params.ambient)
+            .ok_or_else(|| ProcessorError::NotFound(format!("Ambient bed not found: {}", params.ambient)))?;
+        let preset = match params.preset.as_deref() {
+            None => pipeline::VoicePreset::Gentle,
+            Some(name) => pipeline::VoicePreset::from_name(name)
+                .ok_or_else(|| ProcessorError::BadRequest(format!("Unknown preset: {}", name)))?,
+        };
+
+        let output_dir = state.workspace.exports_dir();
+        let options = pipeline::MeditationOptions {
+            voice: &voice,
+            ambient: &ambient,
+            image: &image,
+            preset,
+            ambient_volume: params.ambient_volume.unwrap_or(0.25),
+            fade_out: params.fade_out.unwrap_or(20.0),
+            output_dir: &output_dir,
+        };
+
+        let render = pipeline::render_meditation(&options)?;
+
+        Ok(Json(json!({
+            "message": "Meditation rendered successfully",
+            "audio": state.workspace.relative(&render.audio),
+            "video": state.workspace.relative(&render.video)
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct SponsorRequest {
+    sponsor: String,
+    points: String,
+    template: Option<String>,
+    media_id: Option<String>,
+    anonymize: Option<bool>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+}
+
+impl Validate for SponsorRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.sponsor.trim().is_empty() {
+            return Err(ProcessorError::BadRequest("sponsor is required".to_string()));
+        }
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Generate a sponsor-read script, optionally tied to a video's transcript
+async fn generate_sponsor(State(state): State<AppState>, JsonBody(params): JsonBody<SponsorRequest>) -> Result<Json<Value>, ProcessorError> {
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+
+    let transcript = match params.media_id.as_deref() {
+        None => "(no transcript provided)".to_string(),
+        Some(media_id) => {
+            let input_path = state.workspace.require_media(media_id)?;
+            let mut t = transcriber(&state, None, false, &TranscriptionOptions::default())?.transcribe(&input_path).await?;
+            Glossary::load(&template).apply_to_segments(&mut t.segments);
+            if params.anonymize.unwrap_or(false) {
+                anonymize_segments(&template, &mut t.segments);
+            }
+            t.segments
+                .iter()
+                .map(|segment| segment.text.clone())
+                .collect::<Vec<String>>()
+                .join(" ")
+        }
+    };
+
+    let llm = LlmClient::new(params.provider)?;
+
+    let response = llm.generate_sponsor_script(&params.sponsor, &params.points, &transcript, &template).await?;
+
+    match serde_json::from_str::<Value>(&response) {
+        Ok(content) => Ok(Json(content)),
+        Err(_) => Ok(Json(json!({ "script": response }))),
+    }
+}
+
+#[derive(Deserialize)]
+struct ChaptersParams {
+    media_id: String,
+    template: Option<String>,
+    anonymize: Option<bool>,
+    /// `local` or `api`; defaults to TRANSCRIPTION_BACKEND
+    backend: Option<BackendKind>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+}
+
+/// `/generate/chapters`'s body: its own parameters and the whisper settings
+#[derive(Deserialize)]
+struct ChaptersRequest {
+    #[serde(flatten)]
+    params: ChaptersParams,
+    #[serde(flatten)]
+    options: TranscriptionOptions,
+    #[serde(flatten)]
+    job: JobOptions,
+}
+
+impl Validate for ChaptersRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        self.options.validate()?;
+        PromptTemplate::from_name(self.params.template.as_deref()).map(|_| ())
+    }
+}
+
+/// YouTube chapter markers for a video, placed using the transcript's
+/// segment timings, optionally as a background job
+async fn generate_chapters(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<ChaptersRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    if !request.job.background() {
+        let chapters = media_chapters(&state, &request.params, &request.options).await?;
+        return Ok((StatusCode::OK, Json(json!(chapters))));
+    }
+    state.workspace.require_media(&request.params.media_id)?;
+    let job_state = state.clone();
+    queue_job(&state, "chapters", request.job.callback_url.clone(), move |_| {
+        block_on(media_chapters(&job_state, &request.params, &request.options))
+    })
+}
+
+async fn media_chapters(
+    state: &AppState,
+    params: &ChaptersParams,
+    options: &TranscriptionOptions,
+) -> Result<pipeline::ChapterList, ProcessorError> {
+    let input = state.workspace.require_media(&params.media_id)?;
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+    let anonymize = params.anonymize.unwrap_or(false);
+
+    // Chapters written for this media and template before are reused
+    let media_hash = state.store.media_hash(&input)?;
+    let mut content_key = template.name().to_string();
+    if anonymize {
+        content_key.push_str("+anonymized");
+    }
+    if let Some(provider) = params.provider {
+        content_key.push_str(&format!("+provider={}", provider.name()));
+    }
+    let stored = state.store
+        .content(&media_hash, "chapters", &content_key)?
+        .and_then(|stored| serde_json::from_str::<pipeline::ChapterList>(&stored).ok());
+    if let Some(chapters) = stored {
+        tracing::info!("Using stored chapters for {} ({})", params.media_id, content_key);
+        return Ok(chapters);
+    }
+
+    let transcriber = transcriber(state, params.backend, false, options)?;
+    let llm = LlmClient::new(params.provider)?;
+    let chapters = pipeline::generate_chapters(&pipeline::ChapterOptions {
+        input: &input,
+        transcriber: transcriber.as_ref(),
+        llm: &llm,
+        template,
+        anonymize,
+    })
+    .await?;
+
+    let serialized = serde_json::to_string(&chapters)
+        .map_err(|e| ProcessorError::Database(e.to_string()))?;
+    state.store.save_content(&media_hash, "chapters", &content_key, &serialized)?;
+    Ok(chapters)
+}
+
+#[derive(Deserialize)]
+struct PublishKitRequest {
+    media_id: String,
+    template: Option<String>,
+    thumbnail_at: Option<f64>,
+    zip: Option<bool>,
+    anonymize: Option<bool>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+}
+
+impl Validate for PublishKitRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.thumbnail_at.is_some_and(|t| t < 0.0) {
+            return Err(ProcessorError::BadRequest("thumbnail_at must not be negative".to_string()));
+        }
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Assemble a ready-to-upload folder (and optionally a zip) for a video
+async fn publish_kit(State(state): State<AppState>, JsonBody(params): JsonBody<PublishKitRequest>) -> Result<Json<Value>, ProcessorError> {
+    let input = state.workspace.require_media(&params.media_id)?;
+
+    let output_dir = state.workspace.exports_dir();
+    let transcriber = transcriber(&state, None, false, &TranscriptionOptions::default())?;
+    let llm = LlmClient::new(params.provider)?;
+    let title = media_title(&state, &params.media_id);
+    let options = publish::KitOptions {
+        input: &input,
+        transcriber: transcriber.as_ref(),
+        llm: &llm,
+        template: PromptTemplate::from_name(params.template.as_deref())?,
+        title: &title,
+        thumbnail_at: params.thumbnail_at,
+        output_dir: &output_dir,
+        zip: params.zip.unwrap_or(false),
+        anonymize: params.anonymize.unwrap_or(false),
+    };
+
+    let kit = publish::build_publish_kit(&options).await?;
+
+    Ok(Json(json!({
+        "message": "Publish kit created successfully",
+        "kit": kit
+    })))
+}
+
+/// An LLM client with per-request overrides of the configured provider and
+/// model settings
+fn llm_client(
+    provider: Option<Provider>,
+    model: Option<&str>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+) -> Result<LlmClient, ProcessorError> {
+    let mut builder = LlmClient::builder();
+    if let Some(provider) = provider {
+        builder.provider(provider);
+    }
+    if let Some(model) = model {
+        builder.model(model);
+    }
+    if let Some(temperature) = temperature {
+        builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = max_tokens {
+        builder.max_tokens(max_tokens);
+    }
+    builder.build()
+}
+
+#[derive(Deserialize)]
+struct HighlightsRequest {
+    media_id: String,
+    template: Option<String>,
+    /// Most highlights to keep (default 5)
+    max_clips: Option<usize>,
+    /// Seconds added before and after each highlight (default 1)
+    padding: Option<f64>,
+    anonymize: Option<bool>,
+    /// Seconds each clip overlaps the next (default 0; 0.5 with a `transition`)
+    crossfade: Option<f64>,
+    /// Video transition between clips (default fade)
+    transition: Option<Transition>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+    #[serde(flatten)]
+    job: JobOptions,
+}
+
+impl Validate for HighlightsRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.max_clips == Some(0) || self.padding.is_some_and(|padding| padding < 0.0) {
+            return Err(ProcessorError::BadRequest("Need max_clips >= 1 and padding >= 0".to_string()));
+        }
+        merge_options(self.crossfade, self.transition)?;
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
+}
+
+/// Cut a highlight reel from a full recording: transcribe it, let the LLM
+/// pick the best moments, and join those into one file. As a background
+/// job it answers 202 straight away.
+async fn highlights(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<HighlightsRequest>,
+) -> Result<(StatusCode, Json<Value>), ProcessorError> {
+    if !request.job.background() {
+        return highlight_reel(&state, &request).await.map(|reel| (StatusCode::OK, Json(reel)));
+    }
+    state.workspace.require_media(&request.media_id)?;
+    let job_state = state.clone();
+    queue_job(&state, "highlights", request.job.callback_url.clone(), move |_| {
+        block_on(highlight_reel(&job_state, &request))
+    })
+}
+
+async fn highlight_reel(state: &AppState, request: &HighlightsRequest) -> Result<Value, ProcessorError> {
+    let input = state.workspace.require_media(&request.media_id)?;
+    let max_clips = request.max_clips.unwrap_or(5);
+    let padding = request.padding.unwrap_or(1.0);
+    let merge = merge_options(request.crossfade, request.transition)?;
+
+    let output_dir = state.workspace.exports_dir();
+    let transcriber = transcriber(state, None, false, &TranscriptionOptions::default())?;
+    let llm = LlmClient::new(request.provider)?;
+    let options = pipeline::HighlightOptions {
+        input: &input,
+        transcriber: transcriber.as_ref(),
+        llm: &llm,
+        template: PromptTemplate::from_name(request.template.as_deref())?,
+        max_clips,
+        padding,
+        output_dir: &output_dir,
+        anonymize: request.anonymize.unwrap_or(false),
+        merge,
+    };
+
+    let reel = pipeline::build_highlight_reel(&options).await?;
+
+    Ok(json!({
+        "message": "Highlight reel created successfully",
+        "highlights": reel.highlights,
+        "clips": reel.clips.iter().map(|clip| state.workspace.relative(clip)).collect::<Vec<_>>(),
+        "output_file": state.workspace.relative(&reel.reel)
+    }))
+}
+
+#[derive(Deserialize)]
+struct PromptRequest {
+    text: String,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+impl Validate for PromptRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.text.trim().is_empty() {
+            return Err(ProcessorError::BadRequest("text is required".to_string()));
+        }
+        Ok(())
+    }
+}
+
+async fn chat(JsonBody(params): JsonBody<PromptRequest>) -> Result<Json<Value>, ProcessorError> {
+    let client = llm_client(params.provider, params.model.as_deref(), params.temperature, params.max_tokens)?;
+
+    let completion = client.complete(&params.text).await?;
+
+    Ok(Json(json!({
+        "provider": client.provider(),
+        "model": client.model(),
+        "response": completion.content,
+        "usage": completion.usage
+    })))
+}
+
+/// `/chat` as server-sent events: a `token` event per piece of the reply as
+/// it arrives, then `done` (or `error` if the stream breaks off)
+async fn chat_stream(
+    JsonBody(params): JsonBody<PromptRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ProcessorError> {
+    let client = llm_client(params.provider, params.model.as_deref(), params.temperature, params.max_tokens)?;
+    let tokens = client.complete_stream(&params.text).await?;
+
+    let events = tokens
+        .map(|token| {
+            let event = match token {
+                Ok(content) => Event::default().event("token").json_data(json!({ "content": content })),
+                Err(e) => Event::default().event("error").json_data(json!({ "error": e.to_string(), "kind": e.kind() })),
+            };
+            Ok(event.unwrap_or_default())
+        })
+        .chain(stream::once(async { Ok(Event::default().event("done").data("")) }));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Deserialize)]
+struct GenerateRequest {
+    template: Option<String>,
+    /// LLM to use: `openai`, `anthropic` or `ollama`; defaults to LLM_PROVIDER
+    provider: Option<Provider>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+impl Validate for GenerateRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        PromptTemplate::from_name(self.template.as_deref()).map(|_| ())
+    }
+}
+
+async fn generate_test_content(JsonBody(params): JsonBody<GenerateRequest>) -> Result<Json<YouTubeContent>, ProcessorError> {
+    let client = llm_client(params.provider, params.model.as_deref(), params.temperature, params.max_tokens)?;
+
+    tracing::info!("Starting YouTube content generation..."); 
+
+    let test_transcript = "Okay, what is up Medman Sports? Today we're talking about tennis serves...";
+
+    let template = PromptTemplate::from_name(params.template.as_deref())?;
+
+    let content = client.generate_youtube_content(test_transcript, "Tennis Serve Basics", &template).await?;
+    Ok(Json(content))
+}
+
+#[derive(Deserialize)]
+struct BenchmarkRequest {
+    media_id: String,
+    model: Option<String>,
+    /// Length of the chunks the split stage cuts (default 30)
+    chunk_seconds: Option<u64>,
+}
+
+impl Validate for BenchmarkRequest {
+    fn validate(&self) -> Result<(), ProcessorError> {
+        if self.chunk_seconds == Some(0) {
+            return Err(ProcessorError::BadRequest("chunk_seconds must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Time each pipeline stage on a reference file, to compare hardware and
+/// configuration changes
+async fn admin_benchmark(State(state): State<AppState>, JsonBody(params): JsonBody<BenchmarkRequest>) -> Result<Json<Value>, ProcessorError> {
+    process::blocking(move || {
+        let input_path = state.workspace.require_media(&params.media_id)?;
+
+        // Removed when dropped, even if a stage errors out
+        let work_dir = workspace::scratch_dir(&state.workspace.outputs_dir())?;
+        let options = benchmark::BenchmarkOptions {
+            input: &input_path,
+            whisper_model: params.model.as_deref(),
+            chunk_seconds: params.chunk_seconds.unwrap_or(30),
+            work_dir: work_dir.path(),
+        };
+
+        let report = benchmark::run_benchmark(&options)?;
+        Ok(Json(json!(report)))
+    })
+    .await
+}
+
+/// The HTTP API for a given state. Routes for disabled features are left out.
+fn router(state: AppState) -> Router {
+    // Build our router: ffmpeg and whisper routes are always available
+    let mut app = Router::new()
+        .route("/hello", get(hello))
+        .route("/features", get(features))
+        .route("/templates", get(list_templates))
+        .route("/events", get(events::stream))
+        .route("/media", get(list_media))
+        .route("/media/:id", get(get_media).delete(delete_media))
+        .route("/media/info", get(media_info))
+        .route("/media/upload", post(media::upload_multipart).layer(DefaultBodyLimit::disable()))
+        .route("/media/upload/:name", put(media::upload))
+        .route("/media/download", get(media::download))
+        .route("/split", post(split_video))
+        .route("/merge", post(merge_chunks))
+        .route("/split-region", post(split_region))
+        .route("/media/still-video", post(still_video))
+        .route("/media/loop", post(loop_audio))
+        .route("/media/gate", post(gate_audio))
+        .route("/media/trim-silence", post(trim_silence))
+        .route("/media/snippet", get(snippet))
+        .route("/media/extract-audio", post(extract_audio))
+        .route("/media/burn-subtitles", post(burn_subtitles))
+        .route("/media/thumbnail", post(thumbnail))
+        .route("/media/waveform", get(waveform))
+        .route("/media/normalize", post(normalize))
+        .route("/media/hls", post(hls))
+        .route("/hls/:name/:file", get(hls_file))
+        .route("/media/thumbnail-for-quote", post(thumbnail_for_quote))
+        .route("/edit/insert-sponsor", post(insert_sponsor))
+        .route("/edit/edl", get(get_edl))
+        .route("/meditation/render", post(render_meditation))
+        .route("/transcribe", post(transcribe))
+        .route("/transcribe/subtitles", post(transcribe_subtitles))
+        .route("/jobs/:id", get(get_job))
+        .route("/transcribe-to-json", post(transcribe_to_json))
+        .route("/transcripts", get(list_stored_transcripts))
+        .route("/transcripts/artifacts", get(transcript_artifacts))
+        .route("/search", get(search_transcripts))
+        .route("/transcripts/:hash", get(get_stored_transcript).delete(delete_stored_transcript))
+        .route("/analysis/split-points", post(detect_split_points))
+        .route("/analysis/intro", post(detect_intro))
+        .route("/admin/benchmark", post(admin_benchmark));
+
+    if state.features.llm {
+        app = app
+            .route("/transcribe/bilingual", post(transcribe_bilingual))
+            .route("/batch/process", post(batch_process))
+            .route("/batch/:id", get(get_batch))
+            .route("/transcribe/review", post(transcribe_review))
+            .route("/transcribe-and-optimize", post(transcribe_and_optimize))
+            .route("/chat", post(chat))
+            .route("/chat/stream", post(chat_stream))
+            .route("/generate", post(generate_test_content))
+            .route("/generate/sponsor", post(generate_sponsor))
+            .route("/generate/chapters", post(generate_chapters))
+            .route("/highlights", post(highlights));
+    }
+
+    if state.features.publish {
+        app = app.route("/publish-kit", post(publish_kit));
+    }
+
+    app
+        .route("/metrics", get(telemetry::export))
+        // Buffered bodies are capped; uploads stream and enforce their own limit
+        .layer(DefaultBodyLimit::max(state.limits.max_request_bytes))
+        .layer(axum::middleware::from_fn(telemetry::track_requests))
+        .with_state(state)
+}
+
+#[tokio::main]
+async fn main() {
+    // Load environment variables from .env file
+    dotenv().ok();
+
+    let config = match Config::load(Cli::parse()) {
+        Ok(config) => config::init(config),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    telemetry::init_logging(config.log_format);
+    tracing::info!("Tools: ffmpeg={} ffprobe={} whisper={} (model {})",
+        config.ffmpeg_path, config.ffprobe_path, config.whisper_path, config.whisper_model);
+
+    let workspace = Workspace::from_config(config)
+        .open()
+        .expect("Failed to open data directory");
+    tracing::info!("Data directory: {:?}", workspace.root());
+
+    let state = AppState {
+        cache: Arc::new(ArtifactCache::new(workspace.cache_dir())),
+        limits: BodyLimits::from_env(),
+        ingest: IngestOptions::from_env(),
+        features: Features::from_env(),
+        jobs: Arc::new(JobQueue::from_env()),
+        batches: Arc::new(BatchQueue::from_env()),
+        store: Arc::new(
+            Store::open(&workspace.db_dir().join("processor.sqlite3")).expect("Failed to open database"),
+        ),
+        workspace,
+    };
+    tracing::info!("Body limits: {:?}", state.limits);
+    tracing::info!("Features: {:?}", state.features);
+    let registry = templates::registry();
+    tracing::info!(
+        "Templates in {:?}: {:?}",
+        registry.dir(),
+        registry.list().iter().map(|t| t.name()).collect::<Vec<_>>()
+    );
+
+    library::spawn_retention(state.workspace.clone(), library::Retention::from_env());
+
+    let app = router(state);
 
     // Run the server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
-    println!("Server running on http://127.0.0.1:3000");
+    let listener = tokio::net::TcpListener::bind(config.addr()).await.unwrap();
+    tracing::info!("Server running on http://{}", config.addr());
     
     axum::serve(listener, app).await.unwrap();
 }
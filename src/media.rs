@@ -0,0 +1,381 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Multipart, Path as UrlPath, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
+};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+use crate::cache::FileHasher;
+use crate::error::ProcessorError;
+use crate::ffmpeg::{FFmpegClient, Intro, IntroOptions};
+use crate::process;
+use crate::workspace::{self, Workspace};
+use crate::AppState;
+
+/// Top-level workspace directories the download endpoint may serve from
+const DOWNLOADABLE_DIRS: &[&str] = &["media", "transcripts", "outputs"];
+
+/// Distinguishes concurrent uploads' temporary files
+static UPLOAD_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Intros shorter than this are left alone
+const MIN_INTRO_SECONDS: f64 = 0.5;
+
+/// Body-size limits, read from the environment at startup
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimits {
+    /// Maximum size of a streamed upload (MAX_UPLOAD_BYTES, default 20 GiB)
+    pub max_upload_bytes: u64,
+    /// Maximum size of any other buffered request body (MAX_REQUEST_BYTES, default 2 MiB)
+    pub max_request_bytes: usize,
+}
+
+impl BodyLimits {
+    pub fn from_env() -> Self {
+        let max_upload_bytes = std::env::var("MAX_UPLOAD_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20 * 1024 * 1024 * 1024);
+        let max_request_bytes = std::env::var("MAX_REQUEST_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2 * 1024 * 1024);
+
+        Self {
+            max_upload_bytes,
+            max_request_bytes,
+        }
+    }
+}
+
+/// Processing applied to uploads once they land on disk
+#[derive(Clone, Copy, Debug)]
+pub struct IngestOptions {
+    /// Trim leading black frames and static slates from video uploads
+    /// (AUTO_TRIM_INTRO, default off; `?trim_intro=` overrides per upload)
+    pub trim_intro: bool,
+}
+
+impl IngestOptions {
+    pub fn from_env() -> Self {
+        let trim_intro = std::env::var("AUTO_TRIM_INTRO")
+            .is_ok_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on" | "yes"));
+        Self { trim_intro }
+    }
+}
+
+/// Default intro detection thresholds
+pub fn intro_options() -> IntroOptions {
+    IntroOptions {
+        scan: 120.0,
+        min_black: 0.1,
+        min_static: 1.0,
+        max_gap: 0.5,
+    }
+}
+
+/// Trim a detected intro off `path` in place. Returns the intro, or `None`
+/// when there was nothing to trim.
+pub fn trim_intro(path: &Path) -> Result<Option<Intro>, ProcessorError> {
+    let mut ffmpeg = FFmpegClient::new();
+    ffmpeg.with_input(path);
+    if !ffmpeg.get_info()?.has_video() {
+        return Ok(None);
+    }
+
+    let intro = ffmpeg.detect_intro(intro_options())?;
+    if intro.end < MIN_INTRO_SECONDS {
+        return Ok(None);
+    }
+
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+    let trimmed = path.with_extension(format!("trimmed.{}", extension));
+    ffmpeg.trim_start(intro.end, &trimmed)?;
+    std::fs::rename(&trimmed, path)?;
+    tracing::info!("Trimmed {:.2}s intro from {}", intro.end, path.display());
+
+    Ok(Some(intro))
+}
+
+/// `trim_intro` for an uploaded file, bringing its record's size and hash
+/// up to date with the trimmed content. The media ID stays that of the
+/// original upload, so uploading the same file again still finds it.
+pub fn trim_uploaded_intro(workspace: &Workspace, record: &mut MediaRecord) -> Result<Option<Intro>, ProcessorError> {
+    let path = workspace.media_dir().join(&record.file);
+    let intro = trim_intro(&path)?;
+    if intro.is_some() {
+        record.sha256 = FileHasher::default().hash(&path)?;
+        record.bytes = std::fs::metadata(&path)?.len();
+        workspace.save_media_record(record)?;
+    }
+    Ok(intro)
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    trim_intro: Option<bool>,
+}
+
+/// An uploaded media file. Stored as `media/<id>.<ext>` with this record in
+/// `db/media/<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaRecord {
+    /// Leading 16 hex digits of the content hash, so re-uploading the same
+    /// file yields the same ID
+    pub id: String,
+    /// File name as uploaded
+    pub name: String,
+    /// Stored file name within `media/`
+    pub file: String,
+    pub bytes: u64,
+    pub sha256: String,
+    /// Seconds since the Unix epoch
+    pub uploaded_at: u64,
+}
+
+impl MediaRecord {
+    /// The record for content with hash `sha256` uploaded as `name`, stored
+    /// under its ID with the name's extension
+    fn new(name: String, sha256: String, bytes: u64) -> Self {
+        let id = sha256[..16].to_string();
+        let extension = Path::new(&name)
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|ext| ext.to_lowercase())
+            .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()));
+        let file = match extension {
+            Some(ext) => format!("{}.{}", id, ext),
+            None => id.clone(),
+        };
+        MediaRecord {
+            id,
+            name,
+            file,
+            bytes,
+            sha256,
+            uploaded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Register a file made on the server (in a scratch directory under
+/// `media/`) as media of its own, stored and recorded the way an upload of
+/// the same content named `name` would be
+pub fn register_file(workspace: &Workspace, path: &Path, name: &str) -> Result<MediaRecord, ProcessorError> {
+    let sha256 = FileHasher::default().hash(path)?;
+    let record = MediaRecord::new(name.to_string(), sha256, std::fs::metadata(path)?.len());
+
+    let staged = path.with_file_name(&record.file);
+    std::fs::rename(path, &staged)?;
+    // Same name means same content, so replacing an existing file loses nothing
+    workspace::publish(&[staged], &workspace.media_dir(), |_| false)?;
+    workspace.save_media_record(&record)?;
+
+    tracing::info!("Registered {} as media {} ({} bytes)", record.name, record.id, record.bytes);
+    Ok(record)
+}
+
+/// Stream an upload to disk under its media ID, enforcing the upload limit
+/// without buffering it in memory
+async fn store_upload<S, E>(state: &AppState, name: &str, mut stream: S) -> Result<MediaRecord, ProcessorError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let limit = state.limits.max_upload_bytes;
+
+    // Only keep the final path component of the client-supplied name
+    let name = Path::new(name)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| ProcessorError::BadRequest("Invalid file name".to_string()))?;
+
+    let dir = state.workspace.media_dir();
+    tokio::fs::create_dir_all(&dir)
+        .await?;
+
+    let partial = dir.join(format!(".upload-{}.part", UPLOAD_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let mut file = tokio::fs::File::create(&partial)
+        .await?;
+
+    let mut hasher = Sha256::new();
+    let mut written: u64 = 0;
+    while let Some(chunk) = stream.next().await {
+        let result = match chunk {
+            Ok(bytes) => {
+                written += bytes.len() as u64;
+                if written > limit {
+                    Err(ProcessorError::PayloadTooLarge(format!("Upload exceeds limit of {} bytes", limit)))
+                } else {
+                    hasher.update(&bytes);
+                    file.write_all(&bytes)
+                        .await
+                        .map_err(ProcessorError::from)
+                }
+            }
+            Err(e) => Err(ProcessorError::BadRequest(e.to_string())),
+        };
+
+        if let Err(e) = result {
+            drop(file);
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    }
+
+    file.flush()
+        .await?;
+    drop(file);
+
+    let sha256: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    let record = MediaRecord::new(name, sha256, written);
+    tokio::fs::rename(&partial, dir.join(&record.file))
+        .await?;
+    state.workspace
+        .save_media_record(&record)?;
+
+    tracing::info!("Uploaded {} as media {} ({} bytes)", record.name, record.id, written);
+    Ok(record)
+}
+
+/// Post-upload processing and the response describing the stored media
+fn finish_upload(state: &AppState, mut record: MediaRecord, trim: Option<bool>) -> Result<Json<Value>, ProcessorError> {
+    let intro = if trim.unwrap_or(state.ingest.trim_intro) {
+        trim_uploaded_intro(&state.workspace, &mut record)
+            .map_err(|e| ProcessorError::FFmpegError(format!("Intro trim failed: {}", e)))?
+    } else {
+        None
+    };
+
+    Ok(Json(json!({
+        "message": "File uploaded successfully",
+        "media_id": record.id,
+        "name": record.name,
+        "bytes": record.bytes,
+        "trimmed_intro": intro
+    })))
+}
+
+/// Raw upload: the request body is the file
+pub async fn upload(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    Query(params): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<Value>, ProcessorError> {
+    let limit = state.limits.max_upload_bytes;
+    let declared_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared_length.is_some_and(|len| len > limit) {
+        return Err(ProcessorError::PayloadTooLarge(format!("Upload exceeds limit of {} bytes", limit)));
+    }
+
+    let record = store_upload(&state, &name, body.into_data_stream()).await?;
+    process::blocking(move || finish_upload(&state, record, params.trim_intro)).await
+}
+
+/// Multipart upload: stores the `file` field (or the first field carrying a
+/// file name) and returns its media ID
+pub async fn upload_multipart(
+    State(state): State<AppState>,
+    Query(params): Query<UploadQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, ProcessorError> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ProcessorError::BadRequest(e.to_string()))?
+    {
+        let name = match field.file_name() {
+            Some(name) => name.to_string(),
+            None if field.name() == Some("file") => "upload".to_string(),
+            None => continue,
+        };
+
+        let record = store_upload(&state, &name, field).await?;
+        return process::blocking(move || finish_upload(&state, record, params.trim_intro)).await;
+    }
+
+    Err(ProcessorError::BadRequest("No file field in upload".to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct DownloadQuery {
+    path: String,
+}
+
+/// Stream a workspace file back to the client in chunks
+pub async fn download(State(state): State<AppState>, Query(params): Query<DownloadQuery>) -> Response {
+    let path = match state.workspace.resolve(&params.path) {
+        Some(path) => path,
+        None => return ProcessorError::BadRequest("Invalid path".to_string()).into_response(),
+    };
+
+    let allowed = Path::new(&params.path)
+        .components()
+        .next()
+        .is_some_and(|c| matches!(c, Component::Normal(dir) if DOWNLOADABLE_DIRS.iter().any(|d| dir == *d)));
+    if !allowed {
+        return ProcessorError::BadRequest("Path cannot be downloaded".to_string()).into_response();
+    }
+
+    stream_file(&path).await
+}
+
+/// Stream a file from disk as an attachment, without buffering it
+pub async fn stream_file(path: &Path) -> Response {
+    let file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(_) => return ProcessorError::NotFound("File not found".to_string()).into_response(),
+    };
+    let length = file.metadata().await.map(|m| m.len()).ok();
+
+    let file_name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("download");
+
+    let content_type = match path.extension().and_then(|s| s.to_str()) {
+        Some("mp3") => "audio/mpeg",
+        Some("srt") => "application/x-subrip",
+        Some("vtt") => "text/vtt",
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("ts") => "video/mp2t",
+        _ => "application/octet-stream",
+    };
+
+    let mut response = Body::from_stream(ReaderStream::new(file)).into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+    if let Ok(value) = format!("attachment; filename=\"{}\"", file_name.replace('"', "")).parse() {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    if let Some(length) = length {
+        headers.insert(header::CONTENT_LENGTH, length.into());
+    }
+
+    response
+}
+
+/// Like `stream_file`, but for a player to fetch rather than to save
+pub async fn stream_inline(path: &Path) -> Response {
+    let mut response = stream_file(path).await;
+    response.headers_mut().remove(header::CONTENT_DISPOSITION);
+    response
+}
@@ -1,28 +1,417 @@
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream, Stream};
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
 use std::env;
-use std::fs;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
 
+use crate::error::ProcessorError;
+use crate::llm::{Completion, CompletionBackend, Message, Provider, TokenUsage};
+use crate::scheduler::{scheduler, LlmScheduler, Permit};
+use crate::telemetry;
+
+/// A client for OpenAI's chat completions API, or any server that speaks
+/// it (Ollama, vLLM, LM Studio, proxies)
 pub struct OpenAIClient {
     client: reqwest::Client,
-    api_key: String,
+    config: OpenAIConfig,
 }
 
-#[derive(Serialize)]
-struct ChatMessage {
-    role: String,
-    content: String,
+/// Model settings sent with every chat request
+#[derive(Debug, Clone)]
+pub struct OpenAIConfig {
+    pub model: String,
+    /// Sampling temperature in [0, 2]; the API default when unset
+    pub temperature: Option<f32>,
+    /// Cap on tokens in the reply; the API default when unset
+    pub max_tokens: Option<u32>,
+    /// API root, e.g. `https://api.openai.com/v1`
+    pub base_url: String,
+    /// Sent as a bearer token; local servers need none
+    pub api_key: Option<String>,
+    /// Who we report the replies as coming from
+    pub provider: Provider,
+    pub retry: RetryPolicy,
+}
+
+impl OpenAIConfig {
+    /// Defaults from OPENAI_MODEL (default gpt-3.5-turbo), OPENAI_TEMPERATURE,
+    /// OPENAI_MAX_TOKENS and OPENAI_BASE_URL, with `RetryPolicy::from_env`
+    pub fn from_env() -> Self {
+        Self {
+            model: env::var("OPENAI_MODEL")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| "gpt-3.5-turbo".to_string()),
+            temperature: env::var("OPENAI_TEMPERATURE").ok().and_then(|v| v.parse().ok()),
+            max_tokens: env::var("OPENAI_MAX_TOKENS").ok().and_then(|v| v.parse().ok()),
+            base_url: base_url(),
+            api_key: env::var("OPENAI_API_KEY").ok(),
+            provider: Provider::OpenAI,
+            retry: RetryPolicy::from_env(),
+        }
+    }
+
+    /// An Ollama server's OpenAI-compatible API: OLLAMA_BASE_URL (default
+    /// `http://localhost:11434/v1`) and OLLAMA_MODEL (default llama3.1),
+    /// without a key. Local models are slow, so each attempt gets
+    /// OLLAMA_TIMEOUT_SECS (default 600).
+    pub fn ollama() -> Self {
+        let setting = |name: &str| env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let mut retry = RetryPolicy::from_env();
+        retry.timeout = Duration::from_secs(
+            setting("OLLAMA_TIMEOUT_SECS").and_then(|v| v.parse().ok()).unwrap_or(600),
+        );
+        Self {
+            model: setting("OLLAMA_MODEL").unwrap_or_else(|| "llama3.1".to_string()),
+            temperature: None,
+            max_tokens: None,
+            base_url: setting("OLLAMA_BASE_URL")
+                .map(|v| v.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| "http://localhost:11434/v1".to_string()),
+            api_key: None,
+            provider: Provider::Ollama,
+            retry,
+        }
+    }
+}
+
+/// OPENAI_BASE_URL, default `https://api.openai.com/v1`, for proxies and
+/// compatible servers
+pub fn base_url() -> String {
+    env::var("OPENAI_BASE_URL")
+        .ok()
+        .map(|v| v.trim().trim_end_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "https://api.openai.com/v1".to_string())
+}
+
+/// Longest Retry-After we are willing to wait out; a longer one fails the
+/// request instead of holding it open
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Cap on the exponential backoff between attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How OpenAI requests are timed out and retried. Rate limits (429), server
+/// errors (500, 502, 503, 504), timeouts and dropped connections are retried
+/// with exponential backoff and jitter, or after the server's Retry-After.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Time allowed for the response to start arriving
+    pub timeout: Duration,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Backoff before the first retry; it doubles with each one
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// OPENAI_TIMEOUT_SECS (default 120) and OPENAI_MAX_RETRIES (default 3)
+    pub fn from_env() -> Self {
+        Self {
+            timeout: Duration::from_secs(
+                env::var("OPENAI_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(120),
+            ),
+            max_retries: env::var("OPENAI_MAX_RETRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(3),
+            base_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Send the request `build` makes, building it afresh for each attempt,
+    /// until it succeeds, fails for good, or runs out of retries. Errors
+    /// carry the status and body of the last reply.
+    pub async fn send<F>(&self, what: &str, build: F) -> Result<Response, ProcessorError>
+    where
+        F: FnMut() -> Result<RequestBuilder, ProcessorError>,
+    {
+        let (response, ()) = self.send_with(what, || async {}, build).await?;
+        Ok(response)
+    }
+
+    /// Like `send`, but every attempt first waits for a permit of its own
+    /// from `scheduler`, so retries count against the rate budget too.
+    /// Returns the successful attempt's permit, to record the real usage on.
+    pub async fn send_throttled<'s, F>(
+        &self,
+        what: &str,
+        scheduler: &'s LlmScheduler,
+        estimated_tokens: u64,
+        build: F,
+    ) -> Result<(Response, Permit<'s>), ProcessorError>
+    where
+        F: FnMut() -> Result<RequestBuilder, ProcessorError>,
+    {
+        self.send_with(what, || scheduler.acquire(estimated_tokens), build).await
+    }
+
+    /// The retry loop behind `send` and `send_throttled`: `before` runs ahead
+    /// of each attempt, and what it gives is kept for as long as the attempt
+    async fn send_with<F, B, Fut, T>(&self, what: &str, mut before: B, mut build: F) -> Result<(Response, T), ProcessorError>
+    where
+        F: FnMut() -> Result<RequestBuilder, ProcessorError>,
+        B: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut retry = 0;
+        loop {
+            let attempt = before().await;
+            let (error, retry_after) = match tokio::time::timeout(self.timeout, build()?.send()).await {
+                Err(_) => (format!("no response within {:?}", self.timeout), None),
+                Ok(Err(e)) if e.is_timeout() || e.is_connect() || e.is_request() => (e.to_string(), None),
+                Ok(Err(e)) => return Err(e.into()),
+                Ok(Ok(response)) if response.status().is_success() => return Ok((response, attempt)),
+                Ok(Ok(response)) => {
+                    let status = response.status();
+                    let retry_after = retry_after(response.headers());
+                    let body = response.text().await.unwrap_or_default();
+                    // Out of credit is also a 429, but waiting won't help
+                    if !is_retryable(status) || body.contains("insufficient_quota") {
+                        return Err(ProcessorError::OpenAIError(format!("{}: {}", status, body)));
+                    }
+                    (format!("{}: {}", status, body), retry_after)
+                }
+            };
+
+            if retry >= self.max_retries {
+                return Err(ProcessorError::OpenAIError(format!(
+                    "{} failed after {} attempts: {}",
+                    what,
+                    retry + 1,
+                    error
+                )));
+            }
+            let delay = match retry_after {
+                Some(wait) if wait > MAX_RETRY_AFTER => {
+                    return Err(ProcessorError::OpenAIError(format!(
+                        "{} rate limited for {}s: {}",
+                        what,
+                        wait.as_secs(),
+                        error
+                    )));
+                }
+                Some(wait) => wait,
+                None => self.backoff(retry),
+            };
+            drop(attempt);
+            retry += 1;
+            tracing::warn!("{} failed ({}); retry {} of {} in {:?}", what, error, retry, self.max_retries, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Exponential backoff for the given retry, with "equal jitter": half
+    /// fixed, half random, so concurrent callers spread out
+    fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self.base_delay.saturating_mul(1 << retry.min(16)).min(MAX_BACKOFF);
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        ceiling.mul_f64(0.5 + random / 2.0)
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// How long the server asked us to wait: OpenAI's `retry-after-ms`, or the
+/// standard `Retry-After` in seconds. HTTP dates aren't supported and fall
+/// back to backoff.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+    header("retry-after-ms")
+        .map(|ms| ms / 1000.0)
+        .or_else(|| header("retry-after"))
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// Configures an `OpenAIClient`, starting from the environment defaults
+pub struct OpenAIClientBuilder {
+    config: OpenAIConfig,
+}
+
+impl OpenAIClientBuilder {
+    pub fn model(&mut self, model: &str) -> &mut Self {
+        self.config.model = model.to_string();
+        self
+    }
+
+    pub fn temperature(&mut self, temperature: f32) -> &mut Self {
+        self.config.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(&mut self, max_tokens: u32) -> &mut Self {
+        self.config.max_tokens = Some(max_tokens);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn base_url(&mut self, base_url: &str) -> &mut Self {
+        self.config.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn build(&self) -> Result<OpenAIClient, ProcessorError> {
+        let config = self.config.clone();
+        if config.model.trim().is_empty() {
+            return Err(ProcessorError::BadRequest("Model name is empty".to_string()));
+        }
+        if config.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            return Err(ProcessorError::BadRequest("Temperature must be between 0 and 2".to_string()));
+        }
+        if config.max_tokens == Some(0) {
+            return Err(ProcessorError::BadRequest("max_tokens must be at least 1".to_string()));
+        }
+
+        let mut headers = HeaderMap::new();
+        match &config.api_key {
+            Some(api_key) => {
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {}", api_key))
+                        .map_err(|_| ProcessorError::OpenAIError("OPENAI_API_KEY is not a valid header value".to_string()))?,
+                );
+            }
+            None if config.provider == Provider::OpenAI => {
+                return Err(ProcessorError::OpenAIError("OPENAI_API_KEY is not set".to_string()));
+            }
+            None => {}
+        }
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+
+        Ok(OpenAIClient { client, config })
+    }
 }
 
 #[derive(Serialize)]
 struct ChatRequest {
     model: String,
-    messages: Vec<ChatMessage>,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+/// Constrains the reply's format; `json_object` guarantees syntactically
+/// valid JSON (the prompt must still ask for JSON)
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+impl ResponseFormat {
+    fn json_object() -> Self {
+        Self { kind: "json_object" }
+    }
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    /// Ask for a final chunk reporting token usage
+    include_usage: bool,
+}
+
+/// One `data:` line of a streamed completion
+#[derive(Deserialize)]
+struct ChatChunk {
+    choices: Vec<ChunkChoice>,
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+/// Where a streamed completion has got to
+struct ChatStream {
+    response: reqwest::Response,
+    /// Longest wait for the next piece before giving up
+    chunk_timeout: Duration,
+    /// Received bytes not yet split into lines
+    buffer: Vec<u8>,
+    /// Content parsed but not yet handed out
+    pending: VecDeque<String>,
+    /// Settles when the stream is dropped, finished or not
+    permit: Permit<'static>,
+    provider: Provider,
+    done: bool,
+}
+
+impl ChatStream {
+    /// Parse every complete line in the buffer, returning false at `[DONE]`
+    fn drain_lines(&mut self) -> Result<bool, ProcessorError> {
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let data = match line.trim().strip_prefix("data:") {
+                Some(data) => data.trim(),
+                None => continue,
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+
+            let chunk: ChatChunk = serde_json::from_str(data)
+                .map_err(|e| ProcessorError::OpenAIError(format!("Unexpected stream chunk: {}", e)))?;
+            if let Some(usage) = chunk.usage {
+                telemetry::metrics().tokens_used(self.provider, &usage);
+                self.permit.record_usage(usage.total_tokens);
+            }
+            self.pending.extend(
+                chunk.choices
+                    .into_iter()
+                    .filter_map(|choice| choice.delta.content)
+                    .filter(|content| !content.is_empty()),
+            );
+        }
+        Ok(true)
+    }
 }
 
 #[derive(Deserialize)]
 struct ChatResponse {
     choices: Vec<Choice>,
+    usage: Option<TokenUsage>,
+}
+
+impl ChatRequest {
+    fn estimated_tokens(&self) -> u64 {
+        self.messages
+            .iter()
+            .map(|m| LlmScheduler::estimate_tokens(&m.content))
+            .sum()
+    }
+}
+
+impl ChatResponse {
+    /// Tokens the provider billed, falling back to the estimate if unreported
+    fn total_tokens(&self, estimate: u64) -> u64 {
+        self.usage.as_ref().map(|u| u.total_tokens).unwrap_or(estimate)
+    }
 }
 
 #[derive(Deserialize)]
@@ -35,105 +424,165 @@ struct ResponseMessage {
     content: String,
 }
 
-#[derive(Debug)]
-pub enum PromptTemplate {
-    MedManSports,
-    MamaMeditations
-}
-
 impl OpenAIClient {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let api_key = env::var("OPENAI_API_KEY")
-            .expect("OPENAI_API_KEY must be set");
-
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", api_key))?,
-        );
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    pub fn builder() -> OpenAIClientBuilder {
+        OpenAIClientBuilder { config: OpenAIConfig::from_env() }
+    }
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?;
+    /// A builder for a local Ollama server, see `OpenAIConfig::ollama`
+    pub fn ollama() -> OpenAIClientBuilder {
+        OpenAIClientBuilder { config: OpenAIConfig::ollama() }
+    }
 
-        Ok(Self {
-            client,
-            api_key,
-        })
+    /// A chat request with this client's model settings
+    fn chat_request(&self, messages: Vec<Message>) -> ChatRequest {
+        ChatRequest {
+            model: self.config.model.clone(),
+            messages,
+            temperature: self.config.temperature,
+            max_tokens: self.config.max_tokens,
+            stream: false,
+            stream_options: None,
+            response_format: None,
+        }
     }
 
-    pub async fn complete(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let request = ChatRequest {
-            model: "gpt-3.5-turbo".to_string(),
-            messages: vec![ChatMessage {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }],
-        };
+    /// Like `complete`, but yields the reply piece by piece as the model
+    /// produces it. Errors before the first token are returned directly.
+    pub async fn complete_stream(
+        &self,
+        prompt: &str,
+    ) -> Result<impl Stream<Item = Result<String, ProcessorError>>, ProcessorError> {
+        let mut request = self.chat_request(vec![Message::user(prompt)]);
+        request.stream = true;
+        request.stream_options = Some(StreamOptions { include_usage: true });
 
-        let response = self.client
-            .post("https://api.openai.com/v1/chat/completions")
-            .json(&request)
-            .send()
-            .await?
-            .json::<ChatResponse>()
+        let (response, permit) = self.config.retry
+            .send_throttled("Chat stream", scheduler(self.config.provider), request.estimated_tokens(), || {
+                Ok(self.client.post(self.chat_url()).json(&request))
+            })
             .await?;
 
-        Ok(response.choices[0].message.content.clone())
-    }
-
-    pub async fn generate_youtube_content(
-        &self, 
-        transcript: &str,
-        template: PromptTemplate
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        // Get the appropriate template file
-        let template_path = match template {
-            PromptTemplate::MedManSports => "prompt_dev/medmansports.md",
-            PromptTemplate::MamaMeditations => "prompt_dev/mamameditation.md",
+        let state = ChatStream {
+            response,
+            chunk_timeout: self.config.retry.timeout,
+            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            permit,
+            provider: self.config.provider,
+            done: false,
         };
 
-        println!("Using template: {}", template_path);
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(content) = state.pending.pop_front() {
+                    return Some((Ok(content), state));
+                }
+                if state.done {
+                    return None;
+                }
 
-        // Read the prompt template
-        let prompt_template = fs::read_to_string(template_path)
-            .expect("Failed to read prompt template");
+                let chunk = match tokio::time::timeout(state.chunk_timeout, state.response.chunk()).await {
+                    Ok(chunk) => chunk.map_err(ProcessorError::from),
+                    Err(_) => Err(ProcessorError::OpenAIError(format!(
+                        "Stream stalled for {}s",
+                        state.chunk_timeout.as_secs()
+                    ))),
+                };
+                match chunk {
+                    Ok(Some(bytes)) => {
+                        state.buffer.extend_from_slice(&bytes);
+                        match state.drain_lines() {
+                            Ok(true) => {}
+                            Ok(false) => state.done = true,
+                            Err(e) => {
+                                state.done = true;
+                                return Some((Err(e), state));
+                            }
+                        }
+                    }
+                    Ok(None) => state.done = true,
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        }))
+    }
 
-        // Replace the placeholder with actual transcript
-        let prompt = prompt_template.replace("{{TRANSCRIPT_TEXT}}", transcript);
+    /// Send a single prompt and return the reply with its token usage
+    pub async fn complete(&self, prompt: &str) -> Result<Completion, ProcessorError> {
+        self.send_messages(self.chat_request(vec![Message::user(prompt)])).await
+    }
 
-        println!("Sending prompt to OpenAI..."); // Debug line
+    /// Continue a conversation in JSON mode, which guarantees a
+    /// syntactically valid JSON object
+    pub async fn generate_structured(&self, messages: &[Message]) -> Result<Completion, ProcessorError> {
+        let mut request = self.chat_request(messages.to_vec());
+        request.response_format = Some(ResponseFormat::json_object());
+        self.send_messages(request).await
+    }
 
-        let request = ChatRequest {
-            model: "gpt-3.5-turbo".to_string(),
-            messages: vec![ChatMessage {
-                role: "user".to_string(),
-                content: prompt,
-            }],
-        };
+    async fn send_messages(&self, request: ChatRequest) -> Result<Completion, ProcessorError> {
+        let response = self.send_chat(&request).await?;
+        let usage = response.usage;
+        if let Some(usage) = &usage {
+            telemetry::metrics().tokens_used(self.config.provider, usage);
+        }
+        let content = response.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| ProcessorError::OpenAIError("No response choices returned".to_string()))?;
+        Ok(Completion { content, usage })
+    }
+
+    fn chat_url(&self) -> String {
+        format!("{}/chat/completions", self.config.base_url)
+    }
 
-        let response = self.client
-            .post("https://api.openai.com/v1/chat/completions")
-            .json(&request)
-            .send()
+    async fn send_chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProcessorError> {
+        let estimate = request.estimated_tokens();
+        let (response, mut permit) = self.config.retry
+            .send_throttled("Chat completion", scheduler(self.config.provider), estimate, || {
+                Ok(self.client.post(self.chat_url()).json(request))
+            })
             .await?;
 
-        // Debug response status
-        println!("OpenAI Response Status: {}", response.status());
+        let response = tokio::time::timeout(self.config.retry.timeout, response.json::<ChatResponse>())
+            .await
+            .map_err(|_| ProcessorError::OpenAIError("Timed out reading the completion".to_string()))??;
+        permit.record_usage(response.total_tokens(estimate));
+        Ok(response)
+    }
+}
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            println!("OpenAI Error: {}", error_text);
-            return Err(format!("OpenAI request failed: {}", error_text).into());
-        }
+impl CompletionBackend for OpenAIClient {
+    fn provider(&self) -> Provider {
+        self.config.provider
+    }
 
-        let response_json = response.json::<ChatResponse>().await?;
-        
-        if response_json.choices.is_empty() {
-            return Err("No response choices returned".into());
-        }
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Completion, ProcessorError>> {
+        Box::pin(OpenAIClient::complete(self, prompt))
+    }
 
-        Ok(response_json.choices[0].message.content.clone())
+    fn generate_structured<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<Completion, ProcessorError>> {
+        Box::pin(OpenAIClient::generate_structured(self, messages))
     }
-} 
\ No newline at end of file
+
+    fn complete_stream<'a>(
+        &'a self,
+        prompt: &'a str,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String, ProcessorError>>, ProcessorError>> {
+        Box::pin(async move {
+            let pieces: BoxStream<'static, Result<String, ProcessorError>> =
+                Box::pin(OpenAIClient::complete_stream(self, prompt).await?);
+            Ok(pieces)
+        })
+    }
+}
@@ -0,0 +1,276 @@
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::ProcessorError;
+use crate::events::Stage;
+use crate::openai::{self, RetryPolicy};
+use crate::ffmpeg::{AudioCodec, FFmpegClient};
+use crate::store::Store;
+use crate::telemetry;
+use crate::transcription::{TranscriptionBackend, TranscriptionOptions};
+use crate::whisper::{prompt_digest, Segment, TranscriptionResult, Word};
+use crate::workspace;
+
+/// Largest upload the transcription API accepts
+const MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Transcribes through OpenAI's `/v1/audio/transcriptions` API instead of a
+/// local whisper install. The audio track is extracted to a small mono MP3
+/// first, since the API caps uploads at 25 MB.
+pub struct OpenAIWhisperClient {
+    client: reqwest::Client,
+    base_url: String,
+    retry: RetryPolicy,
+    model: String,
+    language: Option<String>,
+    translate: bool,
+    prompt: Option<String>,
+    word_timestamps: bool,
+    work_dir: Option<PathBuf>,
+    store: Option<Arc<Store>>,
+}
+
+/// The parts of a `verbose_json` transcription we use
+#[derive(Deserialize)]
+struct VerboseTranscription {
+    language: Option<String>,
+    #[serde(default)]
+    segments: Vec<ApiSegment>,
+    /// Present when word timestamps were requested; not grouped by segment
+    #[serde(default)]
+    words: Vec<ApiWord>,
+}
+
+#[derive(Deserialize)]
+struct ApiSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    avg_logprob: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ApiWord {
+    word: String,
+    start: f64,
+    end: f64,
+}
+
+impl OpenAIWhisperClient {
+    /// Uses OPENAI_API_KEY and OPENAI_TRANSCRIBE_MODEL (default whisper-1),
+    /// and the chat client's base URL and retry settings
+    pub fn new() -> Result<Self, ProcessorError> {
+        let api_key = env::var("OPENAI_API_KEY")
+            .map_err(|_| ProcessorError::OpenAIError("OPENAI_API_KEY is not set".to_string()))?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .map_err(|_| ProcessorError::OpenAIError("OPENAI_API_KEY is not a valid header value".to_string()))?,
+        );
+
+        Ok(Self {
+            client: reqwest::Client::builder().default_headers(headers).build()?,
+            base_url: openai::base_url(),
+            retry: RetryPolicy::from_env(),
+            model: env::var("OPENAI_TRANSCRIBE_MODEL")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| "whisper-1".to_string()),
+            language: None,
+            translate: false,
+            prompt: None,
+            word_timestamps: false,
+            work_dir: None,
+            store: None,
+        })
+    }
+
+    /// Ask for per-word timings
+    pub fn with_word_timestamps(&mut self, enabled: bool) -> &mut Self {
+        self.word_timestamps = enabled;
+        self
+    }
+
+    /// Apply a request's overrides. Translations go to the API's
+    /// translation endpoint, which always answers in English and has no
+    /// word timings.
+    pub fn with_options(&mut self, options: &TranscriptionOptions) -> &mut Self {
+        if let Some(model) = &options.model {
+            self.model = model.clone();
+        }
+        if let Some(language) = &options.language {
+            self.language = Some(language.clone());
+        }
+        if let Some(translate) = options.translate {
+            self.translate = translate;
+        }
+        if let Some(prompt) = &options.initial_prompt {
+            self.prompt = Some(prompt.clone());
+        }
+        self
+    }
+
+    /// Set the directory the upload audio is extracted into
+    pub fn with_work_dir<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.work_dir = Some(path.into());
+        self
+    }
+
+    /// Reuse transcripts stored for the same media and store new ones
+    pub fn with_store(&mut self, store: Arc<Store>) -> &mut Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Stored transcripts are kept apart from local whisper's
+    fn store_options(&self) -> String {
+        let mut options = format!("api,model={},words={}", self.model, self.word_timestamps);
+        if let Some(language) = &self.language {
+            options.push_str(&format!(",language={}", language));
+        }
+        if self.translate {
+            options.push_str(",task=translate");
+        }
+        if let Some(prompt) = &self.prompt {
+            options.push_str(&format!(",prompt={}", prompt_digest(prompt)));
+        }
+        options
+    }
+
+    /// The upload form around `file`. Translations take no language and
+    /// have no timestamp granularities.
+    fn form(&self, file: Part) -> Form {
+        let mut form = Form::new()
+            .part("file", file)
+            .text("model", self.model.clone())
+            .text("response_format", "verbose_json");
+        if let Some(prompt) = &self.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        if !self.translate {
+            if let Some(language) = &self.language {
+                form = form.text("language", language.clone());
+            }
+            form = form.text("timestamp_granularities[]", "segment");
+            if self.word_timestamps {
+                form = form.text("timestamp_granularities[]", "word");
+            }
+        }
+        form
+    }
+
+    async fn run_transcribe(&self, input: &Path) -> Result<TranscriptionResult, ProcessorError> {
+        let stored = match &self.store {
+            Some(store) => {
+                let hash = store.media_hash(input)?;
+                if let Some(transcription) = store.transcript(&hash, &self.store_options())? {
+                    tracing::info!("Using stored transcript for {:?}", input);
+                    return Ok(transcription);
+                }
+                Some((store.clone(), hash))
+            }
+            None => None,
+        };
+
+        let work_dir = self.work_dir.as_ref()
+            .ok_or_else(|| ProcessorError::BadRequest("Work directory not set".to_string()))?;
+        let scratch = workspace::scratch_dir(work_dir)?;
+        let (source, audio_dir) = (input.to_path_buf(), scratch.path().to_path_buf());
+        let span = tracing::Span::current();
+        let audio = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            FFmpegClient::new()
+                .with_input(&source)
+                .with_output_dir(&audio_dir)
+                .extract_audio(AudioCodec::Mp3, 16000)
+        })
+        .await
+        .map_err(|e| ProcessorError::FFmpegError(format!("audio extraction panicked: {}", e)))??;
+
+        let bytes = std::fs::read(&audio)?;
+        drop(scratch);
+        if bytes.len() as u64 > MAX_UPLOAD_BYTES {
+            return Err(ProcessorError::PayloadTooLarge(format!(
+                "Audio is {} MB, over the transcription API's 25 MB limit; use the local backend",
+                bytes.len() / (1024 * 1024)
+            )));
+        }
+
+        let file_name = audio.file_name().and_then(|s| s.to_str()).unwrap_or("audio.mp3").to_string();
+        let endpoint = if self.translate { "audio/translations" } else { "audio/transcriptions" };
+
+        tracing::info!("Uploading {:?} to the transcription API ({})", input, self.model);
+        // A multipart body can only be sent once, so each attempt builds its own
+        let response = self.retry
+            .send("Transcription upload", || {
+                let file = Part::bytes(bytes.clone()).file_name(file_name.clone()).mime_str("audio/mpeg")?;
+                Ok(self.client
+                    .post(format!("{}/{}", self.base_url, endpoint))
+                    .multipart(self.form(file)))
+            })
+            .await?;
+
+        let transcription = into_result(response.json::<VerboseTranscription>().await?);
+        telemetry::metrics().transcribed("api", &transcription);
+        tracing::info!("Parsed {} segments (language: {:?})", transcription.segments.len(), transcription.language);
+
+        if let Some((store, hash)) = stored {
+            let name = input.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            store.save_transcript(&hash, name, &self.store_options(), &transcription)?;
+        }
+        Ok(transcription)
+    }
+}
+
+impl TranscriptionBackend for OpenAIWhisperClient {
+    fn name(&self) -> &'static str {
+        "api"
+    }
+
+    fn transcribe<'a>(&'a self, input: &'a Path) -> BoxFuture<'a, Result<TranscriptionResult, ProcessorError>> {
+        Box::pin(async move {
+            let stage = Stage::start("transcribe_api");
+            stage.finish(self.run_transcribe(input).await)
+        })
+    }
+}
+
+/// Convert the API's reply, handing each word to the segment it starts in
+fn into_result(reply: VerboseTranscription) -> TranscriptionResult {
+    let mut words = reply.words.into_iter().peekable();
+    let segments = reply.segments
+        .into_iter()
+        .map(|segment| {
+            let mut segment_words = Vec::new();
+            while let Some(word) = words.next_if(|word| word.start < segment.end) {
+                segment_words.push(Word {
+                    start: word.start,
+                    end: word.end,
+                    word: word.word.trim().to_string(),
+                    probability: None,
+                });
+            }
+            Segment {
+                start: segment.start,
+                end: segment.end,
+                text: segment.text.trim().to_string(),
+                confidence: segment.avg_logprob.map(|logprob| logprob.exp().clamp(0.0, 1.0)),
+                words: segment_words,
+                speaker: None,
+            }
+        })
+        .collect();
+
+    TranscriptionResult {
+        segments,
+        language: reply.language,
+        artifacts: Vec::new(),
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::anonymize::Anonymizer;
+use crate::error::ProcessorError;
+use crate::events::Stage;
+use crate::ffmpeg::{FFmpegClient, ImageMotion, MergeOptions, ReencodeOptions};
+use crate::glossary::Glossary;
+use crate::llm::{Chapter, Highlight, LlmClient};
+use crate::process;
+use crate::templates::PromptTemplate;
+use crate::transcription::TranscriptionBackend;
+
+/// EQ/reverb treatments for a meditation voice recording
+#[derive(Debug, Clone, Copy)]
+pub enum VoicePreset {
+    /// Light cleanup with a short, soft room
+    Gentle,
+    /// Fuller low-mids, softened highs
+    Warm,
+    /// Long, airy reverb tail
+    Spacious,
+}
+
+impl VoicePreset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gentle" => Some(Self::Gentle),
+            "warm" => Some(Self::Warm),
+            "spacious" => Some(Self::Spacious),
+            _ => None,
+        }
+    }
+
+    /// The ffmpeg audio filter chain for this preset
+    pub fn filter(&self) -> &'static str {
+        match self {
+            Self::Gentle => {
+                "highpass=f=80,lowpass=f=12000,\
+                equalizer=f=3000:t=q:w=1:g=-2,\
+                aecho=0.8:0.7:40:0.15"
+            }
+            Self::Warm => {
+                "highpass=f=70,\
+                equalizer=f=200:t=q:w=1:g=3,\
+                equalizer=f=6000:t=q:w=1:g=-3,\
+                aecho=0.8:0.8:60:0.2"
+            }
+            Self::Spacious => {
+                "highpass=f=80,\
+                equalizer=f=250:t=q:w=1:g=1,\
+                aecho=0.8:0.9:500|1000:0.3|0.2"
+            }
+        }
+    }
+}
+
+/// Settings for rendering an audio-only meditation
+pub struct MeditationOptions<'a> {
+    pub voice: &'a Path,
+    pub ambient: &'a Path,
+    pub image: &'a Path,
+    pub preset: VoicePreset,
+    pub ambient_volume: f64,
+    pub fade_out: f64,
+    pub output_dir: &'a Path,
+}
+
+#[derive(Debug)]
+pub struct MeditationRender {
+    pub audio: PathBuf,
+    pub video: PathBuf,
+}
+
+/// MamaMeditations audio-only mode: treat the voice recording, mix it over an
+/// ambient bed with a long fade-out, then render both the finished audio and
+/// a static-image video for YouTube
+pub fn render_meditation(options: &MeditationOptions) -> Result<MeditationRender, ProcessorError> {
+    let stage = Stage::start("render_meditation");
+    stage.finish(run_meditation(options))
+}
+
+fn run_meditation(options: &MeditationOptions) -> Result<MeditationRender, ProcessorError> {
+    std::fs::create_dir_all(options.output_dir)?;
+
+    let stem = options.voice
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("meditation");
+    let audio_path = options.output_dir.join(format!("{}.meditation.mp3", stem));
+    let video_path = options.output_dir.join(format!("{}.meditation.mp4", stem));
+
+    tracing::info!("Rendering meditation audio with {:?} preset", options.preset);
+    let mut ffmpeg = FFmpegClient::new();
+    let audio = ffmpeg
+        .with_input(options.voice)
+        .mix_with_ambient(
+            options.ambient,
+            options.preset.filter(),
+            options.ambient_volume,
+            options.fade_out,
+            &audio_path,
+        )?;
+
+    tracing::info!("Rendering meditation video from {:?}", options.image);
+    let mut ffmpeg = FFmpegClient::new();
+    let video = ffmpeg
+        .with_input(&audio)
+        .still_image_video(options.image, ImageMotion::Static, &video_path)?;
+
+    Ok(MeditationRender { audio, video })
+}
+
+/// Settings for cutting a highlight reel out of a full recording
+pub struct HighlightOptions<'a> {
+    pub input: &'a Path,
+    /// Local whisper or the transcription API
+    pub transcriber: &'a dyn TranscriptionBackend,
+    /// The model that picks the highlights
+    pub llm: &'a LlmClient,
+    pub template: PromptTemplate,
+    /// Most highlights to keep
+    pub max_clips: usize,
+    /// Seconds added before and after each highlight
+    pub padding: f64,
+    pub output_dir: &'a Path,
+    /// Redact personal information from the transcript sent to the LLM
+    pub anonymize: bool,
+    /// How the clips are joined
+    pub merge: MergeOptions,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HighlightReel {
+    /// The moments that made the cut, padded, in playback order
+    pub highlights: Vec<Highlight>,
+    pub clips: Vec<PathBuf>,
+    pub reel: PathBuf,
+}
+
+/// Transcribe a recording, have the LLM pick its best moments, then cut
+/// those out frame-accurately and join them into one reel
+pub async fn build_highlight_reel(options: &HighlightOptions<'_>) -> Result<HighlightReel, ProcessorError> {
+    let stage = Stage::start("highlight_reel");
+    stage.finish(run_highlight_reel(options).await)
+}
+
+async fn run_highlight_reel(options: &HighlightOptions<'_>) -> Result<HighlightReel, ProcessorError> {
+    let mut transcription = options.transcriber.transcribe(options.input).await?;
+    let glossary = Glossary::load(&options.template);
+    glossary.apply_to_segments(&mut transcription.segments);
+    if options.anonymize {
+        Anonymizer::new(&glossary).anonymize(&mut transcription.segments);
+    }
+
+    let segments: Vec<(f64, f64, String)> = transcription.segments
+        .iter()
+        .map(|segment| (segment.start, segment.end, segment.text.clone()))
+        .collect();
+    let picked = options.llm.find_highlights(&segments, options.max_clips, &options.template).await?;
+
+    let input = options.input.to_path_buf();
+    let duration = process::blocking(move || FFmpegClient::new().with_input(&input).duration_seconds()).await?;
+    let highlights = arrange_highlights(picked, options.padding, duration);
+    if highlights.is_empty() {
+        return Err(ProcessorError::NotFound("No highlights found in the recording".to_string()));
+    }
+
+    let stem = options.input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("recording");
+    let extension = options.input
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("mp4");
+    let clips_dir = options.output_dir.join(format!("{}-highlights", stem));
+    std::fs::create_dir_all(&clips_dir)?;
+
+    let input = options.input.to_path_buf();
+    let cuts = highlights.clone();
+    let reel_path = options.output_dir.join(format!("{}.highlights.{}", stem, extension));
+    let clip_extension = extension.to_string();
+    let merge = options.merge;
+    let (clips, reel) = process::blocking(move || {
+        // Every clip is encoded the same way, so the concat can stream-copy them
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg
+            .with_input(&input)
+            .with_reencode(ReencodeOptions::default());
+        let mut clips = Vec::new();
+        for (i, highlight) in cuts.iter().enumerate() {
+            tracing::info!("Cutting highlight {}: {} ({:.1}s-{:.1}s)", i + 1, highlight.title, highlight.start, highlight.end);
+            let output = clips_dir.join(format!("clip_{:03}.{}", i + 1, clip_extension));
+            clips.push(ffmpeg.extract_clip(highlight.start, highlight.end, &output)?);
+        }
+
+        let reel = FFmpegClient::new().merge_chunks(clips.clone(), reel_path, &merge)?;
+        Ok((clips, reel))
+    })
+    .await?;
+
+    Ok(HighlightReel { highlights, clips, reel })
+}
+
+/// Pad each highlight, clamp it to the recording, and merge any that then
+/// overlap, in playback order
+fn arrange_highlights(mut highlights: Vec<Highlight>, padding: f64, duration: f64) -> Vec<Highlight> {
+    for highlight in &mut highlights {
+        highlight.start = (highlight.start - padding).max(0.0);
+        highlight.end = (highlight.end + padding).min(duration);
+    }
+    highlights.retain(|h| h.end > h.start);
+    highlights.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let mut arranged: Vec<Highlight> = Vec::new();
+    for highlight in highlights {
+        match arranged.last_mut() {
+            Some(last) if highlight.start <= last.end => {
+                last.end = last.end.max(highlight.end);
+                last.title = format!("{} / {}", last.title, highlight.title);
+            }
+            _ => arranged.push(highlight),
+        }
+    }
+    arranged
+}
+
+/// YouTube ignores chapter lists with fewer entries than this
+pub const MIN_CHAPTERS: usize = 3;
+
+/// Shortest chapter YouTube accepts, in seconds
+pub const MIN_CHAPTER_SECONDS: f64 = 10.0;
+
+/// Settings for writing YouTube chapters from a recording's transcript
+pub struct ChapterOptions<'a> {
+    pub input: &'a Path,
+    /// Local whisper or the transcription API
+    pub transcriber: &'a dyn TranscriptionBackend,
+    /// The model that finds the sections
+    pub llm: &'a LlmClient,
+    pub template: PromptTemplate,
+    /// Redact personal information from the transcript sent to the LLM
+    pub anonymize: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChapterList {
+    pub chapters: Vec<Chapter>,
+    /// The chapters as description lines, ready to paste
+    pub description: String,
+    /// Whether YouTube will show these as chapters (enough of them)
+    pub youtube_ready: bool,
+}
+
+/// Transcribe a recording and have the LLM mark where its sections start,
+/// using the segment timings
+pub async fn generate_chapters(options: &ChapterOptions<'_>) -> Result<ChapterList, ProcessorError> {
+    let stage = Stage::start("chapters");
+    stage.finish(run_chapters(options).await)
+}
+
+async fn run_chapters(options: &ChapterOptions<'_>) -> Result<ChapterList, ProcessorError> {
+    let mut transcription = options.transcriber.transcribe(options.input).await?;
+    let glossary = Glossary::load(&options.template);
+    glossary.apply_to_segments(&mut transcription.segments);
+    if options.anonymize {
+        Anonymizer::new(&glossary).anonymize(&mut transcription.segments);
+    }
+
+    let segments: Vec<(f64, f64, String)> = transcription.segments
+        .iter()
+        .map(|segment| (segment.start, segment.end, segment.text.clone()))
+        .collect();
+    let picked = options.llm.find_chapters(&segments, &options.template).await?;
+
+    let input = options.input.to_path_buf();
+    let duration = process::blocking(move || FFmpegClient::new().with_input(&input).duration_seconds()).await?;
+    let chapters = arrange_chapters(picked, duration);
+    if chapters.is_empty() {
+        return Err(ProcessorError::NotFound("No chapters found in the recording".to_string()));
+    }
+
+    Ok(ChapterList {
+        description: chapters.iter().map(Chapter::line).collect::<Vec<_>>().join("\n"),
+        youtube_ready: chapters.len() >= MIN_CHAPTERS,
+        chapters,
+    })
+}
+
+/// Apply YouTube's chapter rules to chapters in playback order: the first
+/// starts at 00:00 (a late first chapter gets an "Intro" ahead of it), and
+/// each runs at least `MIN_CHAPTER_SECONDS`, so one starting too soon after
+/// the previous or too close to the end is dropped
+pub fn arrange_chapters(chapters: Vec<Chapter>, duration: f64) -> Vec<Chapter> {
+    let mut arranged: Vec<Chapter> = Vec::new();
+    for chapter in chapters {
+        let start = chapter.seconds();
+        if duration - start < MIN_CHAPTER_SECONDS {
+            break;
+        }
+        match arranged.last() {
+            None if start < MIN_CHAPTER_SECONDS => arranged.push(Chapter::at(0.0, &chapter.title)),
+            None => arranged.extend([Chapter::at(0.0, "Intro"), chapter]),
+            Some(last) if start - last.seconds() >= MIN_CHAPTER_SECONDS => arranged.push(chapter),
+            Some(_) => {}
+        }
+    }
+    arranged
+}
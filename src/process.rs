@@ -0,0 +1,211 @@
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config::config;
+use crate::error::ProcessorError;
+use crate::events::{self, PipelineEvent};
+
+/// How long a process waits for a free slot before the request gives up
+const MAX_SLOT_WAIT: Duration = Duration::from_secs(10 * 60);
+
+/// Runs external tools (ffmpeg, whisper). Everything that spawns a process
+/// goes through the installed runner, so tests can swap in a fake.
+pub trait ProcessRunner: Send + Sync {
+    fn run(&self, program: &str, args: &[String]) -> Result<Output, std::io::Error>;
+
+    /// Like `run`, but hands each line of stdout to `on_line` as it is
+    /// written. By default the lines are replayed once the process exits.
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<Output, std::io::Error> {
+        let output = self.run(program, args)?;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            on_line(line);
+        }
+        Ok(output)
+    }
+}
+
+/// Spawns real processes and waits for them
+pub struct SystemRunner;
+
+impl ProcessRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[String]) -> Result<Output, std::io::Error> {
+        Command::new(program).args(args).output()
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<Output, std::io::Error> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Drain stderr on its own thread so a chatty process can't fill the
+        // pipe and stall while we wait on stdout
+        let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+        let stderr_reader = std::thread::spawn(move || {
+            let mut stderr = Vec::new();
+            let _ = stderr_pipe.read_to_end(&mut stderr);
+            stderr
+        });
+
+        let mut stdout = Vec::new();
+        let mut reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            on_line(String::from_utf8_lossy(&line).trim_end());
+            stdout.append(&mut line);
+        }
+
+        let status = child.wait()?;
+        let stderr = stderr_reader.join().unwrap_or_default();
+        Ok(Output { status, stdout, stderr })
+    }
+}
+
+/// Caps how many external processes run at once, whichever requests they
+/// belong to. Past the cap, processes queue for a slot instead of piling
+/// onto the CPU. The wait blocks its thread, so async code starts processes
+/// through `blocking`.
+#[derive(Debug)]
+pub struct ProcessLimiter {
+    limit: usize,
+    running: Mutex<usize>,
+    freed: Condvar,
+}
+
+/// A taken slot, given back when dropped
+#[derive(Debug)]
+pub struct ProcessSlot<'a> {
+    limiter: &'a ProcessLimiter,
+}
+
+impl ProcessLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            running: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Take a slot, waiting at most `timeout` for one to free up
+    pub fn acquire(&self, timeout: Duration) -> Result<ProcessSlot<'_>, ProcessorError> {
+        let running = self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *running >= self.limit {
+            tracing::warn!("All {} process slots busy; waiting", self.limit);
+        }
+        let (mut running, wait) = self.freed
+            .wait_timeout_while(running, timeout, |running| *running >= self.limit)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if wait.timed_out() && *running >= self.limit {
+            return Err(ProcessorError::Busy(format!(
+                "Server busy: all {} process slots stayed taken for {:?}",
+                self.limit, timeout
+            )));
+        }
+        *running += 1;
+        Ok(ProcessSlot { limiter: self })
+    }
+}
+
+impl Drop for ProcessSlot<'_> {
+    fn drop(&mut self) {
+        let mut running = self.limiter.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *running -= 1;
+        self.limiter.freed.notify_one();
+    }
+}
+
+static LIMITER: OnceLock<ProcessLimiter> = OnceLock::new();
+
+/// The process-wide limiter, sized by `max_processes` in the config
+fn limiter() -> &'static ProcessLimiter {
+    LIMITER.get_or_init(|| ProcessLimiter::new(config().max_processes))
+}
+
+/// Run `work`, which starts external processes, on the blocking pool. A
+/// process can wait minutes for a slot and then minutes more to finish;
+/// doing either on a runtime worker would stall every other request.
+pub async fn blocking<T, F>(work: F) -> Result<T, ProcessorError>
+where
+    F: FnOnce() -> Result<T, ProcessorError> + Send + 'static,
+    T: Send + 'static,
+{
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(work))
+        .await
+        .map_err(|e| ProcessorError::Io(std::io::Error::other(format!("blocking task panicked: {}", e))))?
+}
+
+/// Turn a failure to run `program` into the caller's error. A request that
+/// never got a process slot is reported as busy whichever tool it wanted.
+pub fn spawn_error(program: &str, e: std::io::Error, wrap: fn(String) -> ProcessorError) -> ProcessorError {
+    if e.get_ref().is_some_and(|inner| inner.is::<ProcessorError>()) {
+        if let Some(Ok(busy)) = e.into_inner().map(|inner| inner.downcast::<ProcessorError>()) {
+            return *busy;
+        }
+        return wrap(format!("could not run {}", program));
+    }
+    wrap(format!("could not run {}: {}", program, e))
+}
+
+static RUNNER: OnceLock<Arc<dyn ProcessRunner>> = OnceLock::new();
+
+/// Install the process runner. Only the first call has any effect, and it
+/// must happen before the first process is spawned.
+#[cfg(test)]
+pub fn set_runner(runner: Arc<dyn ProcessRunner>) -> bool {
+    RUNNER.set(runner).is_ok()
+}
+
+fn runner() -> &'static Arc<dyn ProcessRunner> {
+    RUNNER.get_or_init(|| Arc::new(SystemRunner))
+}
+
+/// Run `program` to completion through the installed runner, reporting it
+/// on the event bus
+pub fn run(program: &str, args: &[String]) -> Result<Output, std::io::Error> {
+    reported(program, args, || runner().run(program, args))
+}
+
+/// Run `program` to completion, handing each line of its stdout to
+/// `on_line` as it arrives
+pub fn run_streaming(program: &str, args: &[String], on_line: &mut dyn FnMut(&str)) -> Result<Output, std::io::Error> {
+    reported(program, args, || runner().run_streaming(program, args, on_line))
+}
+
+fn reported<F>(program: &str, args: &[String], run: F) -> Result<Output, std::io::Error>
+where
+    F: FnOnce() -> Result<Output, std::io::Error>,
+{
+    let _slot = limiter().acquire(MAX_SLOT_WAIT).map_err(std::io::Error::other)?;
+
+    events::emit(PipelineEvent::SubprocessSpawned {
+        program: program.to_string(),
+        args: args.to_vec(),
+    });
+    let started = Instant::now();
+
+    let output = run();
+
+    events::emit(PipelineEvent::SubprocessExited {
+        program: program.to_string(),
+        success: output.as_ref().is_ok_and(|o| o.status.success()),
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
+
+    output
+}
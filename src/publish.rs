@@ -0,0 +1,158 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::anonymize::Anonymizer;
+use crate::error::ProcessorError;
+use crate::events::Stage;
+use crate::ffmpeg::FFmpegClient;
+use crate::glossary::Glossary;
+use crate::llm::LlmClient;
+use crate::process;
+use crate::subtitles;
+use crate::templates::PromptTemplate;
+use crate::transcription::TranscriptionBackend;
+
+/// What a publish kit was built from
+pub struct KitOptions<'a> {
+    pub input: &'a Path,
+    /// Local whisper or the transcription API
+    pub transcriber: &'a dyn TranscriptionBackend,
+    /// The model that writes the copy
+    pub llm: &'a LlmClient,
+    pub template: PromptTemplate,
+    /// Working title given to the LLM
+    pub title: &'a str,
+    /// When to grab the thumbnail frame; defaults to 10% into the video
+    pub thumbnail_at: Option<f64>,
+    pub output_dir: &'a Path,
+    pub zip: bool,
+    /// Redact personal information from the transcript sent to the LLM.
+    /// The captions keep the original text.
+    pub anonymize: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublishKit {
+    pub folder: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub zip: Option<PathBuf>,
+}
+
+/// Assemble everything needed to upload a video by hand into one folder:
+/// the final export, a thumbnail, captions, title/description/tags and
+/// social posts. Optionally zips the folder as well.
+pub async fn build_publish_kit(options: &KitOptions<'_>) -> Result<PublishKit, ProcessorError> {
+    let stage = Stage::start("publish_kit");
+    stage.finish(run_publish_kit(options).await)
+}
+
+async fn run_publish_kit(options: &KitOptions<'_>) -> Result<PublishKit, ProcessorError> {
+    let stem = options.input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("video");
+    let folder = options.output_dir.join(format!("{}-publish-kit", stem));
+    std::fs::create_dir_all(&folder)?;
+
+    let mut files = Vec::new();
+
+    // Final export
+    let video_name = options.input
+        .file_name()
+        .ok_or_else(|| ProcessorError::BadRequest("Invalid input path".to_string()))?;
+    let video = folder.join(video_name);
+    std::fs::copy(options.input, &video)?;
+    files.push(video);
+
+    // Thumbnail
+    let (input, thumbnail_at) = (options.input.to_path_buf(), options.thumbnail_at);
+    let thumbnail = folder.join("thumbnail.jpg");
+    files.push(process::blocking(move || {
+        let mut ffmpeg = FFmpegClient::new();
+        ffmpeg.with_input(&input);
+        let thumbnail_at = match thumbnail_at {
+            Some(t) => t,
+            None => ffmpeg.duration_seconds()? * 0.1,
+        };
+        ffmpeg.extract_frame(thumbnail_at, &thumbnail)
+    })
+    .await?);
+
+    // Captions
+    let mut transcription = options.transcriber.transcribe(options.input).await?;
+    Glossary::load(&options.template).apply_to_segments(&mut transcription.segments);
+    let srt = folder.join(format!("{}.srt", stem));
+    std::fs::write(&srt, subtitles::to_srt(&transcription.segments))?;
+    files.push(srt);
+
+    // Title, description and tags
+    if options.anonymize {
+        Anonymizer::new(&Glossary::load(&options.template)).anonymize(&mut transcription.segments);
+    }
+    let full_text = transcription.segments
+        .iter()
+        .map(|segment| segment.text.clone())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let content = options.llm.generate_youtube_content(&full_text, options.title, &options.template).await?;
+
+    let title_file = folder.join("title.txt");
+    std::fs::write(&title_file, &content.title)?;
+    files.push(title_file);
+
+    let description_file = folder.join("description.txt");
+    std::fs::write(&description_file, &content.description)?;
+    files.push(description_file);
+
+    // Comma-separated, ready to paste into YouTube Studio's tags box
+    let tags_file = folder.join("tags.txt");
+    std::fs::write(&tags_file, content.tags.join(", "))?;
+    files.push(tags_file);
+
+    // Social copy
+    let social = options.llm.generate_social_copy(&content.title, &content.description, &options.template).await?;
+    let social_doc = match serde_json::from_str::<Value>(&social) {
+        Ok(posts) => format!(
+            "# X / Twitter\n\n{}\n\n# Instagram\n\n{}\n\n# Community\n\n{}\n",
+            posts["twitter"].as_str().unwrap_or_default(),
+            posts["instagram"].as_str().unwrap_or_default(),
+            posts["community"].as_str().unwrap_or_default()
+        ),
+        Err(_) => social,
+    };
+    let social_file = folder.join("social.md");
+    std::fs::write(&social_file, social_doc)?;
+    files.push(social_file);
+
+    let zip = if options.zip {
+        Some(zip_folder(&folder, &files)?)
+    } else {
+        None
+    };
+
+    Ok(PublishKit { folder, files, zip })
+}
+
+/// Zip the kit's files (flat, by file name) next to the folder
+fn zip_folder(folder: &Path, files: &[PathBuf]) -> Result<PathBuf, ProcessorError> {
+    let zip_path = folder.with_extension("zip");
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+
+    for file in files {
+        let name = file.file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| ProcessorError::BadRequest("Invalid file name".to_string()))?;
+        writer.start_file(name, options).map_err(std::io::Error::other)?;
+        let mut source = std::fs::File::open(file)?;
+        std::io::copy(&mut source, &mut writer)?;
+    }
+
+    writer.finish().map_err(std::io::Error::other)?.flush()?;
+    Ok(zip_path)
+}
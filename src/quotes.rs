@@ -0,0 +1,101 @@
+use serde::Serialize;
+
+use crate::whisper::Segment;
+
+/// Where a quote was found in a transcript
+#[derive(Debug, Serialize)]
+pub struct QuoteMatch {
+    /// Estimated time the quote starts, in seconds
+    pub time: f64,
+    pub segment: usize,
+    /// Fraction of the quote's words that matched, in [0, 1]
+    pub score: f64,
+    /// The transcript text that matched
+    pub text: String,
+}
+
+struct TimedWord {
+    word: String,
+    raw: String,
+    time: f64,
+    segment: usize,
+}
+
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Find `quote` in the transcript, tolerating small transcription differences.
+/// Whisper only times whole segments, so word times are interpolated across
+/// each segment by word position.
+pub fn find_quote(segments: &[Segment], quote: &str, min_score: f64) -> Option<QuoteMatch> {
+    let quote: Vec<String> = quote
+        .split_whitespace()
+        .map(normalize)
+        .filter(|w| !w.is_empty())
+        .collect();
+    if quote.is_empty() {
+        return None;
+    }
+
+    let words: Vec<TimedWord> = segments
+        .iter()
+        .enumerate()
+        .flat_map(|(index, segment)| {
+            let raw: Vec<&str> = segment.text.split_whitespace().collect();
+            let step = (segment.end - segment.start) / raw.len().max(1) as f64;
+            raw.into_iter().enumerate().map(move |(i, word)| TimedWord {
+                word: normalize(word),
+                raw: word.to_string(),
+                time: segment.start + step * i as f64,
+                segment: index,
+            })
+        })
+        .collect();
+
+    // Score every window of the quote's length by words matching in position
+    let best = (0..words.len())
+        .map(|start| {
+            let matched = quote
+                .iter()
+                .zip(&words[start..])
+                .filter(|(q, w)| **q == w.word)
+                .count();
+            (start, matched)
+        })
+        .max_by_key(|(start, matched)| (*matched, std::cmp::Reverse(*start)))?;
+
+    let score = best.1 as f64 / quote.len() as f64;
+    if score < min_score {
+        return None;
+    }
+
+    let first = &words[best.0];
+    let end = (best.0 + quote.len()).min(words.len());
+    Some(QuoteMatch {
+        time: first.time,
+        segment: first.segment,
+        score,
+        text: words[best.0..end]
+            .iter()
+            .map(|w| w.raw.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+    })
+}
+
+/// `count` timestamps spread evenly over `time ± spread`, clamped at zero
+pub fn candidate_times(time: f64, spread: f64, count: usize) -> Vec<f64> {
+    if count <= 1 {
+        return vec![time.max(0.0)];
+    }
+    let step = spread * 2.0 / (count - 1) as f64;
+    let mut times: Vec<f64> = (0..count)
+        .map(|i| (time - spread + step * i as f64).max(0.0))
+        .collect();
+    times.dedup();
+    times
+}
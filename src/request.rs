@@ -0,0 +1,49 @@
+use axum::async_trait;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::ProcessorError;
+
+/// Checks on a request body that don't need the filesystem, run before any
+/// work starts so a job never gets queued just to fail
+pub trait Validate {
+    fn validate(&self) -> Result<(), ProcessorError>;
+}
+
+/// A JSON request body, deserialized and validated. Bodies that aren't
+/// JSON or don't fit the type are answered like any other bad request, as
+/// `{"error": ..., "kind": "bad_request"}`. A `media_id` in the body is
+/// recorded on the request's span.
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ProcessorError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<Value>::from_request(request, state).await.map_err(rejection)?;
+        if let Some(media_id) = value.get("media_id").and_then(Value::as_str) {
+            tracing::Span::current().record("media_id", media_id);
+        }
+        let body = T::deserialize(value).map_err(|e| {
+            ProcessorError::BadRequest(format!("Failed to deserialize the JSON body into the target type: {}", e))
+        })?;
+        body.validate()?;
+        Ok(JsonBody(body))
+    }
+}
+
+fn rejection(rejection: JsonRejection) -> ProcessorError {
+    match rejection.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => ProcessorError::PayloadTooLarge(rejection.body_text()),
+        _ => ProcessorError::BadRequest(rejection.body_text()),
+    }
+}
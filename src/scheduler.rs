@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::llm::Provider;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Tokens reserved for the completion on top of the prompt estimate, until
+/// the response reports actual usage
+const COMPLETION_RESERVE: u64 = 500;
+
+/// Rate budgets for one provider's LLM calls, shared by every pipeline in the
+/// process so a batch regeneration waits its turn instead of tripping the
+/// provider's rate limits
+pub struct LlmScheduler {
+    /// `None` lets every request straight through
+    requests_per_minute: Option<usize>,
+    tokens_per_minute: u64,
+    window: Mutex<VecDeque<Reservation>>,
+    next_id: AtomicU64,
+}
+
+struct Reservation {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+/// A granted slot in the current window, holding the request's token
+/// estimate until `record_usage` replaces it with the real count. Dropping
+/// the permit settles the reservation, however the request ended.
+pub struct Permit<'a> {
+    scheduler: &'a LlmScheduler,
+    id: u64,
+    tokens: u64,
+}
+
+impl Permit<'_> {
+    /// The tokens the provider actually billed for this request
+    pub fn record_usage(&mut self, tokens: u64) {
+        self.tokens = tokens;
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.scheduler.settle(self.id, self.tokens);
+    }
+}
+
+impl LlmScheduler {
+    /// Budgets from `<PREFIX>_REQUESTS_PER_MINUTE` and
+    /// `<PREFIX>_TOKENS_PER_MINUTE`, with the given defaults
+    pub fn from_env(prefix: &str, requests_per_minute: usize, tokens_per_minute: u64) -> Self {
+        let setting = |name: &str| std::env::var(format!("{}_{}", prefix, name)).ok();
+        Self::new(
+            setting("REQUESTS_PER_MINUTE").and_then(|v| v.parse().ok()).unwrap_or(requests_per_minute),
+            setting("TOKENS_PER_MINUTE").and_then(|v| v.parse().ok()).unwrap_or(tokens_per_minute),
+        )
+    }
+
+    pub fn new(requests_per_minute: usize, tokens_per_minute: u64) -> Self {
+        Self {
+            requests_per_minute: Some(requests_per_minute),
+            tokens_per_minute,
+            window: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// A scheduler that never makes anyone wait, for local models
+    pub fn unlimited() -> Self {
+        Self {
+            requests_per_minute: None,
+            tokens_per_minute: u64::MAX,
+            window: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Rough token count for a prompt plus room for the completion
+    pub fn estimate_tokens(prompt: &str) -> u64 {
+        (prompt.len() as u64).div_ceil(4) + COMPLETION_RESERVE
+    }
+
+    /// Wait until both budgets have room for a request of `estimated_tokens`.
+    /// A request larger than the whole token budget is let through once the
+    /// window is empty rather than waiting forever.
+    pub async fn acquire(&self, estimated_tokens: u64) -> Permit<'_> {
+        let Some(requests_per_minute) = self.requests_per_minute else {
+            // Nothing is reserved, so settling finds nothing to update
+            return Permit { scheduler: self, id: 0, tokens: estimated_tokens };
+        };
+        loop {
+            let wait = {
+                let mut window = self.window.lock().unwrap();
+                let now = Instant::now();
+                while window.front().is_some_and(|r| now.duration_since(r.at) >= WINDOW) {
+                    window.pop_front();
+                }
+
+                let used: u64 = window.iter().map(|r| r.tokens).sum();
+                let fits = window.is_empty()
+                    || (window.len() < requests_per_minute
+                        && used + estimated_tokens <= self.tokens_per_minute);
+
+                if fits {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    window.push_back(Reservation { id, at: now, tokens: estimated_tokens });
+                    return Permit { scheduler: self, id, tokens: estimated_tokens };
+                }
+
+                // Room frees up when the oldest reservation leaves the window
+                let oldest = window.front().map(|r| r.at).unwrap_or(now);
+                tracing::warn!(
+                    "LLM budget exhausted ({} requests, {} tokens in the last minute); waiting",
+                    window.len(),
+                    used
+                );
+                (oldest + WINDOW).saturating_duration_since(now)
+            };
+
+            tokio::time::sleep(wait.max(Duration::from_millis(50))).await;
+        }
+    }
+
+    /// Replace a reservation's estimate with the tokens it finally counted for
+    fn settle(&self, id: u64, tokens: u64) {
+        let mut window = self.window.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(reservation) = window.iter_mut().find(|r| r.id == id) {
+            reservation.tokens = tokens;
+        }
+    }
+
+    /// Requests and tokens counted in the current window
+    #[cfg(test)]
+    pub fn usage(&self) -> (usize, u64) {
+        let window = self.window.lock().unwrap();
+        (window.len(), window.iter().map(|r| r.tokens).sum())
+    }
+}
+
+/// The process-wide scheduler a provider's requests go through, each with
+/// its own budget. Ollama runs locally and isn't throttled.
+pub fn scheduler(provider: Provider) -> &'static LlmScheduler {
+    static OPENAI: OnceLock<LlmScheduler> = OnceLock::new();
+    static ANTHROPIC: OnceLock<LlmScheduler> = OnceLock::new();
+    static OLLAMA: OnceLock<LlmScheduler> = OnceLock::new();
+    match provider {
+        Provider::OpenAI => OPENAI.get_or_init(|| LlmScheduler::from_env("OPENAI", 500, 90_000)),
+        Provider::Anthropic => ANTHROPIC.get_or_init(|| LlmScheduler::from_env("ANTHROPIC", 50, 40_000)),
+        Provider::Ollama => OLLAMA.get_or_init(LlmScheduler::unlimited),
+    }
+}
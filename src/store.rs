@@ -0,0 +1,381 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache::FileHasher;
+use crate::error::ProcessorError;
+use crate::whisper::{Segment, TranscriptionResult};
+
+/// Shortest media hash prefix the lookup routes accept
+const MIN_HASH_PREFIX: usize = 16;
+
+/// Most search hits returned at once
+const MAX_SEARCH_RESULTS: usize = 500;
+
+/// SQLite database of finished transcriptions and generated content, keyed
+/// by the SHA-256 of the media file, so the same media is only transcribed
+/// (or written up) once however it is addressed.
+pub struct Store {
+    conn: Mutex<Connection>,
+    hasher: FileHasher,
+}
+
+/// A stored transcription without its segments
+#[derive(Debug, Serialize)]
+pub struct TranscriptSummary {
+    pub media_hash: String,
+    pub media_name: String,
+    /// Whisper settings the transcript was made with, e.g. `model=base,words=false`
+    pub options: String,
+    pub language: Option<String>,
+    pub segment_count: usize,
+    /// Seconds since the Unix epoch
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoredTranscript {
+    #[serde(flatten)]
+    pub summary: TranscriptSummary,
+    pub segments: Vec<Segment>,
+}
+
+/// A stored transcript segment that matched a search
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub media_hash: String,
+    pub media_name: String,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// The segment text with matched words in `[brackets]`
+    pub highlighted: String,
+}
+
+/// LLM output generated from a media file's transcript
+#[derive(Debug, Serialize)]
+pub struct StoredContent {
+    pub media_hash: String,
+    /// What was generated, e.g. `youtube`
+    pub kind: String,
+    /// What distinguishes variants of the same kind, e.g. the prompt template
+    pub key: String,
+    pub content: Value,
+    pub created_at: i64,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Check a client-supplied hash prefix and turn it into a LIKE pattern
+fn hash_pattern(prefix: &str) -> Result<String, ProcessorError> {
+    let valid = prefix.len() >= MIN_HASH_PREFIX
+        && prefix.len() <= 64
+        && prefix.chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err(ProcessorError::BadRequest(format!(
+            "Expected at least {} hex digits of a media hash",
+            MIN_HASH_PREFIX
+        )));
+    }
+    Ok(format!("{}%", prefix.to_lowercase()))
+}
+
+/// Turn free text into an FTS5 query matching segments that contain every
+/// word (or a word starting with it, for words ending in `*`). Quoting each
+/// word keeps FTS5 operators and punctuation in the text from being parsed.
+fn match_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '*')
+        .filter_map(|word| {
+            let prefix = word.ends_with('*');
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if word.is_empty() {
+                return None;
+            }
+            Some(format!("\"{}\"{}", word, if prefix { "*" } else { "" }))
+        })
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Replace a transcript's rows in the search index
+fn index_segments(conn: &Connection, media_hash: &str, options: &str, segments: &[Segment]) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM segment_search WHERE media_hash = ?1 AND options = ?2",
+        params![media_hash, options],
+    )?;
+    let mut insert = conn.prepare(
+        "INSERT INTO segment_search (text, media_hash, options, start_time, end_time) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for segment in segments {
+        insert.execute(params![segment.text, media_hash, options, segment.start, segment.end])?;
+    }
+    Ok(())
+}
+
+impl Store {
+    /// Open (or create) the database at `path`
+    pub fn open(path: &Path) -> Result<Self, ProcessorError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE IF NOT EXISTS transcripts (
+                media_hash TEXT NOT NULL,
+                options TEXT NOT NULL,
+                media_name TEXT NOT NULL,
+                language TEXT,
+                segments TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (media_hash, options)
+            );
+            CREATE TABLE IF NOT EXISTS generated_content (
+                media_hash TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                key TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (media_hash, kind, key)
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS segment_search USING fts5(
+                text,
+                media_hash UNINDEXED,
+                options UNINDEXED,
+                start_time UNINDEXED,
+                end_time UNINDEXED,
+                tokenize = 'porter unicode61'
+            );",
+        )?;
+
+        // Transcripts stored before search existed aren't indexed yet
+        let unindexed: Vec<(String, String, String)> = conn
+            .prepare(
+                "SELECT media_hash, options, segments FROM transcripts t WHERE NOT EXISTS (
+                    SELECT 1 FROM segment_search s WHERE s.media_hash = t.media_hash AND s.options = t.options
+                )",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_, _>>()?;
+        for (media_hash, options, segments) in &unindexed {
+            match serde_json::from_str::<Vec<Segment>>(segments) {
+                Ok(segments) if !segments.is_empty() => index_segments(&conn, media_hash, options, &segments)?,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Not indexing corrupt stored transcript {}: {}", media_hash, e),
+            }
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            hasher: FileHasher::default(),
+        })
+    }
+
+    /// The key media is stored under: the SHA-256 of its contents
+    pub fn media_hash(&self, path: &Path) -> Result<String, ProcessorError> {
+        Ok(self.hasher.hash(path)?)
+    }
+
+    pub fn transcript(&self, media_hash: &str, options: &str) -> Result<Option<TranscriptionResult>, ProcessorError> {
+        let row: Option<(Option<String>, String)> = self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT language, segments FROM transcripts WHERE media_hash = ?1 AND options = ?2",
+                params![media_hash, options],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        row.map(|(language, segments)| {
+            Ok(TranscriptionResult {
+                segments: serde_json::from_str(&segments)
+                    .map_err(|e| ProcessorError::Database(format!("Corrupt stored transcript: {}", e)))?,
+                language,
+                artifacts: Vec::new(),
+            })
+        })
+        .transpose()
+    }
+
+    pub fn save_transcript(
+        &self,
+        media_hash: &str,
+        media_name: &str,
+        options: &str,
+        transcription: &TranscriptionResult,
+    ) -> Result<(), ProcessorError> {
+        let segments = serde_json::to_string(&transcription.segments)
+            .map_err(|e| ProcessorError::Database(e.to_string()))?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO transcripts (media_hash, options, media_name, language, segments, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![media_hash, options, media_name, transcription.language, segments, now()],
+        )?;
+        index_segments(&tx, media_hash, options, &transcription.segments)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored segments containing every word of `query`, best matches
+    /// first. Each media file is searched in its newest transcript only, so
+    /// transcribing it with other settings doesn't repeat its hits.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, ProcessorError> {
+        let Some(pattern) = match_query(query) else {
+            return Err(ProcessorError::BadRequest("Search query has no words".to_string()));
+        };
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT s.media_hash, t.media_name, s.start_time, s.end_time, s.text,
+                highlight(segment_search, 0, '[', ']')
+            FROM segment_search s
+            JOIN transcripts t ON t.media_hash = s.media_hash AND t.options = s.options
+            WHERE segment_search MATCH ?1
+                AND t.options = (
+                    SELECT options FROM transcripts WHERE media_hash = s.media_hash
+                    ORDER BY created_at DESC, options LIMIT 1
+                )
+            ORDER BY rank, s.media_hash, s.start_time
+            LIMIT ?2",
+        )?;
+        let hits = statement
+            .query_map(params![pattern, limit.min(MAX_SEARCH_RESULTS) as i64], |row| {
+                Ok(SearchHit {
+                    media_hash: row.get(0)?,
+                    media_name: row.get(1)?,
+                    start: row.get(2)?,
+                    end: row.get(3)?,
+                    text: row.get(4)?,
+                    highlighted: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
+
+    /// Every stored transcript, newest first
+    pub fn list_transcripts(&self) -> Result<Vec<TranscriptSummary>, ProcessorError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT media_hash, media_name, options, language, json_array_length(segments), created_at
+            FROM transcripts ORDER BY created_at DESC",
+        )?;
+        let summaries = statement
+            .query_map([], |row| {
+                Ok(TranscriptSummary {
+                    media_hash: row.get(0)?,
+                    media_name: row.get(1)?,
+                    options: row.get(2)?,
+                    language: row.get(3)?,
+                    segment_count: row.get::<_, i64>(4)? as usize,
+                    created_at: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(summaries)
+    }
+
+    /// Transcripts for media whose hash starts with `prefix`
+    pub fn find_transcripts(&self, prefix: &str) -> Result<Vec<StoredTranscript>, ProcessorError> {
+        let pattern = hash_pattern(prefix)?;
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT media_hash, media_name, options, language, segments, created_at
+            FROM transcripts WHERE media_hash LIKE ?1 ORDER BY created_at DESC",
+        )?;
+        let rows = statement
+            .query_map(params![pattern], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i64>(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(media_hash, media_name, options, language, segments, created_at)| {
+                let segments: Vec<Segment> = serde_json::from_str(&segments)
+                    .map_err(|e| ProcessorError::Database(format!("Corrupt stored transcript: {}", e)))?;
+                Ok(StoredTranscript {
+                    summary: TranscriptSummary {
+                        media_hash,
+                        media_name,
+                        options,
+                        language,
+                        segment_count: segments.len(),
+                        created_at,
+                    },
+                    segments,
+                })
+            })
+            .collect()
+    }
+
+    pub fn content(&self, media_hash: &str, kind: &str, key: &str) -> Result<Option<String>, ProcessorError> {
+        Ok(self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT content FROM generated_content WHERE media_hash = ?1 AND kind = ?2 AND key = ?3",
+                params![media_hash, kind, key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn save_content(&self, media_hash: &str, kind: &str, key: &str, content: &str) -> Result<(), ProcessorError> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO generated_content (media_hash, kind, key, content, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![media_hash, kind, key, content, now()],
+        )?;
+        Ok(())
+    }
+
+    /// Generated content for media whose hash starts with `prefix`. Content
+    /// that is JSON is returned parsed.
+    pub fn find_content(&self, prefix: &str) -> Result<Vec<StoredContent>, ProcessorError> {
+        let pattern = hash_pattern(prefix)?;
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT media_hash, kind, key, content, created_at
+            FROM generated_content WHERE media_hash LIKE ?1 ORDER BY created_at DESC",
+        )?;
+        let content = statement
+            .query_map(params![pattern], |row| {
+                let content: String = row.get(3)?;
+                Ok(StoredContent {
+                    media_hash: row.get(0)?,
+                    kind: row.get(1)?,
+                    key: row.get(2)?,
+                    content: serde_json::from_str(&content).unwrap_or(Value::String(content)),
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(content)
+    }
+
+    /// Forget everything stored for media whose hash starts with `prefix`.
+    /// Returns the number of transcripts and content entries removed.
+    pub fn delete(&self, prefix: &str) -> Result<(usize, usize), ProcessorError> {
+        let pattern = hash_pattern(prefix)?;
+        let conn = self.conn.lock().unwrap();
+        let transcripts = conn.execute("DELETE FROM transcripts WHERE media_hash LIKE ?1", params![pattern])?;
+        conn.execute("DELETE FROM segment_search WHERE media_hash LIKE ?1", params![pattern])?;
+        let content = conn.execute("DELETE FROM generated_content WHERE media_hash LIKE ?1", params![pattern])?;
+        Ok((transcripts, content))
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Deserialize;
+
+use crate::whisper::Segment;
+
+/// Caption file formats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    #[default]
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+
+    pub fn render(&self, segments: &[Segment]) -> String {
+        match self {
+            SubtitleFormat::Srt => to_srt(segments),
+            SubtitleFormat::Vtt => to_vtt(segments),
+        }
+    }
+}
+
+/// Format seconds as an SRT timestamp (`HH:MM:SS,mmm`)
+pub fn srt_timestamp(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_ms / 3_600_000;
+    let minutes = (total_ms % 3_600_000) / 60_000;
+    let secs = (total_ms % 60_000) / 1000;
+    let ms = total_ms % 1000;
+    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs, ms)
+}
+
+/// Render segments as an SRT file, one cue per segment
+pub fn to_srt(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                srt_timestamp(segment.start),
+                srt_timestamp(segment.end),
+                segment.text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Format seconds as a WebVTT timestamp (`HH:MM:SS.mmm`)
+pub fn vtt_timestamp(seconds: f64) -> String {
+    srt_timestamp(seconds).replace(',', ".")
+}
+
+/// Render segments as a WebVTT file, one cue per segment
+pub fn to_vtt(segments: &[Segment]) -> String {
+    let cues: Vec<String> = segments
+        .iter()
+        .map(|segment| {
+            format!(
+                "{} --> {}\n{}\n",
+                vtt_timestamp(segment.start),
+                vtt_timestamp(segment.end),
+                // A blank line would end the cue early
+                segment.text.trim().replace("\n\n", "\n")
+            )
+        })
+        .collect();
+    format!("WEBVTT\n\n{}", cues.join("\n"))
+}
+
+/// Render an SRT file where every cue carries the original line followed by
+/// its translation, for language-learning captions
+pub fn to_bilingual_srt(segments: &[Segment], translations: &[String]) -> String {
+    segments
+        .iter()
+        .zip(translations)
+        .enumerate()
+        .map(|(i, (segment, translation))| {
+            format!(
+                "{}\n{} --> {}\n{}\n{}\n",
+                i + 1,
+                srt_timestamp(segment.start),
+                srt_timestamp(segment.end),
+                segment.text.trim(),
+                translation.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render a side-by-side transcript as a Markdown table
+pub fn to_side_by_side(segments: &[Segment], translations: &[String], language: &str) -> String {
+    let mut doc = format!("| Time | Original | {} |\n|---|---|---|\n", language);
+    for (segment, translation) in segments.iter().zip(translations) {
+        let time = srt_timestamp(segment.start);
+        doc.push_str(&format!(
+            "| {} | {} | {} |\n",
+            &time[..8],
+            segment.text.trim().replace('|', "\\|"),
+            translation.trim().replace('|', "\\|")
+        ));
+    }
+    doc
+}
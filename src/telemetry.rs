@@ -0,0 +1,240 @@
+use axum::extract::{MatchedPath, Request};
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use serde::Deserialize;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+use crate::events::PipelineEvent;
+use crate::llm::{Provider, TokenUsage};
+use crate::whisper::TranscriptionResult;
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Install the global tracing subscriber. RUST_LOG picks what is logged
+/// (default `info`); every line carries the spans it was logged in, so
+/// work done for a request shows its route and media ID.
+pub fn init_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let installed = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(false).try_init(),
+    };
+    if let Err(e) = installed {
+        eprintln!("Logging was already set up: {}", e);
+    }
+}
+
+/// Counters and histograms exported on `/metrics`
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    jobs_processed: IntCounterVec,
+    stage_duration: HistogramVec,
+    subprocess_duration: HistogramVec,
+    subprocess_failures: IntCounterVec,
+    transcribed_seconds: IntCounterVec,
+    llm_tokens: IntCounterVec,
+}
+
+/// Buckets for work that takes from milliseconds to the best part of an hour
+const LONG_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 3600.0];
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid counter");
+            registry.register(Box::new(counter.clone())).expect("metric registered once");
+            counter
+        };
+        let histogram = |name: &str, help: &str, labels: &[&str], buckets: &[f64]| {
+            let opts = HistogramOpts::new(name, help).buckets(buckets.to_vec());
+            let histogram = HistogramVec::new(opts, labels).expect("valid histogram");
+            registry.register(Box::new(histogram.clone())).expect("metric registered once");
+            histogram
+        };
+
+        Self {
+            http_requests: counter(
+                "http_requests_total",
+                "HTTP requests answered, by route and status",
+                &["method", "route", "status"],
+            ),
+            http_duration: histogram(
+                "http_request_duration_seconds",
+                "Time to answer HTTP requests",
+                &["method", "route"],
+                prometheus::DEFAULT_BUCKETS,
+            ),
+            jobs_processed: counter(
+                "jobs_processed_total",
+                "Background jobs finished, by kind and outcome",
+                &["kind", "status"],
+            ),
+            stage_duration: histogram(
+                "stage_duration_seconds",
+                "Time spent in pipeline stages (transcription, LLM calls, renders)",
+                &["stage", "success"],
+                LONG_BUCKETS,
+            ),
+            subprocess_duration: histogram(
+                "subprocess_duration_seconds",
+                "Time spent in ffmpeg, whisper and other external tools",
+                &["program"],
+                LONG_BUCKETS,
+            ),
+            subprocess_failures: counter(
+                "subprocess_failures_total",
+                "External tool runs that exited unsuccessfully",
+                &["program"],
+            ),
+            transcribed_seconds: counter(
+                "transcription_audio_seconds_total",
+                "Seconds of audio transcribed, by backend",
+                &["backend"],
+            ),
+            llm_tokens: counter(
+                "llm_tokens_total",
+                "Tokens billed by LLM providers",
+                &["provider", "type"],
+            ),
+            registry,
+        }
+    }
+
+    /// Everything in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    pub fn job_finished(&self, kind: &str, success: bool) {
+        let status = if success { "completed" } else { "failed" };
+        self.jobs_processed.with_label_values(&[kind, status]).inc();
+    }
+
+    /// Count the audio behind a fresh (not stored) transcription
+    pub fn transcribed(&self, backend: &str, transcription: &TranscriptionResult) {
+        let seconds = transcription.segments.last().map_or(0.0, |segment| segment.end);
+        self.transcribed_seconds.with_label_values(&[backend]).inc_by(seconds.round() as u64);
+    }
+
+    pub fn tokens_used(&self, provider: Provider, usage: &TokenUsage) {
+        self.llm_tokens.with_label_values(&[provider.name(), "prompt"]).inc_by(usage.prompt_tokens);
+        self.llm_tokens.with_label_values(&[provider.name(), "completion"]).inc_by(usage.completion_tokens);
+    }
+
+    /// Record what an event says about durations and failures, and log it
+    /// in the span of the work that emitted it
+    pub fn observe(&self, event: &PipelineEvent) {
+        match event {
+            PipelineEvent::StageStarted { stage } => tracing::debug!(stage = %stage, "stage started"),
+            PipelineEvent::StageFinished { stage, success, duration_ms } => {
+                self.stage_duration
+                    .with_label_values(&[stage, if *success { "true" } else { "false" }])
+                    .observe(*duration_ms as f64 / 1000.0);
+                tracing::info!(stage = %stage, success, duration_ms, "stage finished");
+            }
+            PipelineEvent::SubprocessSpawned { program, args } => {
+                tracing::debug!(program = %program, args = ?args, "subprocess spawned");
+            }
+            PipelineEvent::SubprocessExited { program, success, duration_ms } => {
+                self.subprocess_duration.with_label_values(&[program]).observe(*duration_ms as f64 / 1000.0);
+                if *success {
+                    tracing::info!(program = %program, duration_ms, "subprocess exited");
+                } else {
+                    self.subprocess_failures.with_label_values(&[program]).inc();
+                    tracing::warn!(program = %program, duration_ms, "subprocess failed");
+                }
+            }
+            PipelineEvent::ArtifactProduced { path, cached } => {
+                tracing::info!(path = %path, cached, "artifact produced");
+            }
+            PipelineEvent::Progress { input, percent } => tracing::debug!(input = %input, percent, "progress"),
+        }
+    }
+}
+
+/// The process-wide metrics. Like the event bus, they are recorded deep
+/// inside the clients, so they are global rather than threaded through.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// `/metrics`: everything in the Prometheus text format
+pub async fn export() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], metrics().render())
+}
+
+#[derive(Deserialize)]
+struct MediaQuery {
+    media_id: Option<String>,
+}
+
+/// Run each request in a span named after its route, log how it went and
+/// count it. The span's media ID comes from the query string, or from the
+/// JSON body once `JsonBody` has read it.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let span = tracing::info_span!("request", method = %method, route = %route, media_id = tracing::field::Empty);
+    if let Some(media_id) = request
+        .uri()
+        .query()
+        .and_then(|query| serde_urlencoded::from_str::<MediaQuery>(query).ok())
+        .and_then(|query| query.media_id)
+    {
+        span.record("media_id", media_id.as_str());
+    }
+
+    let started = Instant::now();
+    let response = next.run(request).instrument(span.clone()).await;
+    let elapsed = started.elapsed();
+
+    let status = response.status();
+    let metrics = metrics();
+    metrics.http_requests.with_label_values(&[&method, &route, status.as_str()]).inc();
+    metrics.http_duration.with_label_values(&[&method, &route]).observe(elapsed.as_secs_f64());
+    span.in_scope(|| {
+        let duration_ms = elapsed.as_millis() as u64;
+        if status.is_server_error() {
+            tracing::warn!(status = status.as_u16(), duration_ms, "request failed");
+        } else {
+            tracing::info!(status = status.as_u16(), duration_ms, "request finished");
+        }
+    });
+    response
+}
@@ -0,0 +1,188 @@
+use regex::Regex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::error::ProcessorError;
+
+/// Placeholders a YouTube content template may use
+pub const PLACEHOLDERS: [&str; 3] = ["TRANSCRIPT_TEXT", "VIDEO_TITLE", "CHANNEL_NAME"];
+
+/// A channel profile: its YouTube content prompt, plus the name used in
+/// requests and for per-profile files such as the glossary
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    name: String,
+    /// Channel name substituted for `{{CHANNEL_NAME}}`
+    channel: String,
+    /// Short description of the channel's subject matter, used as context
+    /// when asking the model about domain terms
+    domain: String,
+    /// Placeholders the prompt uses, in order of first appearance
+    placeholders: Vec<String>,
+    path: PathBuf,
+    #[serde(skip)]
+    body: String,
+}
+
+impl PromptTemplate {
+    /// Look up a registered template by its request name, falling back to the
+    /// default template when no name is given
+    pub fn from_name(name: Option<&str>) -> Result<Self, ProcessorError> {
+        registry().resolve(name)
+    }
+
+    /// Parse a template file. An optional front matter block between `---`
+    /// lines sets `channel` and `domain`; both default to the name.
+    pub fn parse(name: &str, path: &Path, content: &str) -> Self {
+        let mut channel = None;
+        let mut domain = None;
+
+        let body = match content.strip_prefix("---\n").and_then(|rest| rest.split_once("\n---\n")) {
+            Some((front_matter, body)) => {
+                for line in front_matter.lines() {
+                    match line.split_once(':').map(|(k, v)| (k.trim(), v.trim())) {
+                        Some(("channel", value)) => channel = Some(value.to_string()),
+                        Some(("domain", value)) => domain = Some(value.to_string()),
+                        _ => tracing::warn!("Ignoring front matter line {:?} in {:?}", line, path),
+                    }
+                }
+                body
+            }
+            None => content,
+        };
+
+        let channel = channel.unwrap_or_else(|| name.to_string());
+        let mut placeholders: Vec<String> = Vec::new();
+        for capture in placeholder_pattern().captures_iter(body) {
+            let placeholder = capture[1].to_string();
+            if !placeholders.contains(&placeholder) {
+                placeholders.push(placeholder);
+            }
+        }
+
+        Self {
+            name: name.to_string(),
+            domain: domain.unwrap_or_else(|| channel.clone()),
+            channel,
+            placeholders,
+            path: path.to_path_buf(),
+            body: body.to_string(),
+        }
+    }
+
+    /// The profile name used in requests and for per-profile files
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// The prompt with its placeholders filled in
+    pub fn render(&self, transcript: &str, video_title: &str) -> String {
+        self.body
+            .replace("{{CHANNEL_NAME}}", &self.channel)
+            .replace("{{VIDEO_TITLE}}", video_title)
+            .replace("{{TRANSCRIPT_TEXT}}", transcript)
+    }
+}
+
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\{\{([A-Z_]+)\}\}").unwrap())
+}
+
+/// Every template found in the templates directory, by name
+pub struct TemplateRegistry {
+    dir: PathBuf,
+    templates: Vec<PromptTemplate>,
+    default: String,
+}
+
+impl TemplateRegistry {
+    /// Scan PROMPT_TEMPLATES_DIR (default `prompt_dev/templates`); requests
+    /// without a template use PROMPT_TEMPLATE_DEFAULT (default `med-man-sports`)
+    pub fn from_env() -> Self {
+        let dir = std::env::var("PROMPT_TEMPLATES_DIR").unwrap_or_else(|_| "prompt_dev/templates".to_string());
+        let default = std::env::var("PROMPT_TEMPLATE_DEFAULT").unwrap_or_else(|_| "med-man-sports".to_string());
+        Self::load(Path::new(&dir), &default)
+    }
+
+    /// Load every `<name>.md` in `dir` as a template called `<name>`.
+    /// Unreadable files are skipped with a warning.
+    pub fn load(dir: &Path, default: &str) -> Self {
+        let mut templates = Vec::new();
+        match std::fs::read_dir(dir) {
+            Ok(entries) => {
+                for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+                    if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                        continue;
+                    }
+                    let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                        continue;
+                    };
+                    match std::fs::read_to_string(&path) {
+                        Ok(content) => templates.push(PromptTemplate::parse(name, &path, &content)),
+                        Err(e) => tracing::warn!("Skipping template {:?}: {}", path, e),
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Could not read templates directory {:?}: {}", dir, e),
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for template in &templates {
+            if !template.placeholders.iter().any(|p| p == "TRANSCRIPT_TEXT") {
+                tracing::warn!("Template {} has no {{{{TRANSCRIPT_TEXT}}}} placeholder", template.name);
+            }
+            for placeholder in &template.placeholders {
+                if !PLACEHOLDERS.contains(&placeholder.as_str()) {
+                    tracing::warn!("Template {} uses unknown placeholder {{{{{}}}}}", template.name, placeholder);
+                }
+            }
+        }
+
+        Self {
+            dir: dir.to_path_buf(),
+            templates,
+            default: default.to_string(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+
+    pub fn list(&self) -> &[PromptTemplate] {
+        &self.templates
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.iter().find(|template| template.name == name)
+    }
+
+    /// The named template, or the default one when no name is given
+    pub fn resolve(&self, name: Option<&str>) -> Result<PromptTemplate, ProcessorError> {
+        let name = name.unwrap_or(&self.default);
+        self.get(name).cloned().ok_or_else(|| {
+            let known: Vec<&str> = self.templates.iter().map(|t| t.name.as_str()).collect();
+            ProcessorError::BadRequest(format!(
+                "Unknown template {:?}; available: {}",
+                name,
+                known.join(", ")
+            ))
+        })
+    }
+}
+
+/// The process-wide template registry, loaded on first use
+pub fn registry() -> &'static TemplateRegistry {
+    static REGISTRY: OnceLock<TemplateRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(TemplateRegistry::from_env)
+}
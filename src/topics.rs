@@ -0,0 +1,129 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::whisper::Segment;
+
+/// Words too common to say anything about the topic
+const STOPWORDS: &[&str] = &[
+    "the", "and", "that", "this", "with", "for", "you", "your", "are", "was",
+    "but", "not", "have", "has", "had", "just", "like", "what", "about", "they",
+    "them", "there", "then", "from", "okay", "yeah", "really", "going", "gonna",
+    "can", "all", "our", "out", "get", "got", "know", "its", "it's", "i'm",
+    "we're", "you're", "so", "because", "when", "which", "will", "would", "into",
+];
+
+/// Thresholds for proposing a split
+#[derive(Debug, Clone, Copy)]
+pub struct SplitOptions {
+    /// Minimum gap between segments, in seconds, to count as a long silence
+    pub min_silence: f64,
+    /// Maximum word-overlap similarity between the two sides of a gap
+    pub max_similarity: f64,
+    /// Seconds of transcript on each side of a gap to compare
+    pub window: f64,
+    /// Shortest part, in seconds, a split may produce
+    pub min_part: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SplitPoint {
+    /// Where to cut, in the middle of the silence
+    pub time: f64,
+    pub silence: f64,
+    pub similarity: f64,
+    pub before: String,
+    pub after: String,
+}
+
+/// Find places where a long silence coincides with a change of topic,
+/// suggesting the recording holds more than one video
+pub fn detect_split_points(segments: &[Segment], options: &SplitOptions) -> Vec<SplitPoint> {
+    let mut points: Vec<SplitPoint> = Vec::new();
+    let total_end = segments.last().map(|s| s.end).unwrap_or(0.0);
+
+    for (i, pair) in segments.windows(2).enumerate() {
+        let gap = pair[1].start - pair[0].end;
+        if gap < options.min_silence {
+            continue;
+        }
+
+        let time = pair[0].end + gap / 2.0;
+        let last_cut = points.last().map(|p| p.time).unwrap_or(0.0);
+        if time - last_cut < options.min_part || total_end - time < options.min_part {
+            continue;
+        }
+
+        let before: Vec<&Segment> = segments[..=i]
+            .iter()
+            .rev()
+            .take_while(|s| pair[0].end - s.start <= options.window)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        let after: Vec<&Segment> = segments[i + 1..]
+            .iter()
+            .take_while(|s| s.end - pair[1].start <= options.window)
+            .collect();
+
+        let before_text = join_text(&before);
+        let after_text = join_text(&after);
+        let similarity = cosine_similarity(&word_counts(&before_text), &word_counts(&after_text));
+
+        if similarity <= options.max_similarity {
+            points.push(SplitPoint {
+                time,
+                silence: gap,
+                similarity,
+                before: preview(&before_text, true),
+                after: preview(&after_text, false),
+            });
+        }
+    }
+
+    points
+}
+
+fn join_text(segments: &[&Segment]) -> String {
+    segments
+        .iter()
+        .map(|s| s.text.trim())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn word_counts(text: &str) -> HashMap<String, f64> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        let word: String = word
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '\'')
+            .collect::<String>()
+            .to_lowercase();
+        if word.len() > 2 && !STOPWORDS.contains(&word.as_str()) {
+            *counts.entry(word).or_insert(0.0) += 1.0;
+        }
+    }
+    counts
+}
+
+fn cosine_similarity(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a.iter().filter_map(|(w, x)| b.get(w).map(|y| x * y)).sum();
+    let norm_a = a.values().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.values().map(|x| x * x).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// The last (or first) dozen words of a side, for a reviewer to eyeball
+fn preview(text: &str, tail: bool) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let taken = if tail {
+        &words[words.len().saturating_sub(12)..]
+    } else {
+        &words[..words.len().min(12)]
+    };
+    taken.join(" ")
+}
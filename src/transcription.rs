@@ -0,0 +1,104 @@
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::error::ProcessorError;
+use crate::whisper::{TranscriptionResult, WhisperClient};
+
+/// Something that turns a media file into timed transcript segments
+pub trait TranscriptionBackend: Send + Sync {
+    /// Short name reported with results, e.g. `local`
+    fn name(&self) -> &'static str;
+
+    fn transcribe<'a>(&'a self, input: &'a Path) -> BoxFuture<'a, Result<TranscriptionResult, ProcessorError>>;
+}
+
+/// Longest `initial_prompt` accepted; whisper only looks at the last 224
+/// tokens of it anyway
+const MAX_PROMPT_CHARS: usize = 1000;
+
+/// Per-request transcription settings, read from the query string or JSON
+/// body alongside each endpoint's own parameters. Unset fields keep the
+/// backend's defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TranscriptionOptions {
+    /// Whisper model (tiny, base, small, medium, large, ...), or the API
+    /// model with the `api` backend
+    pub model: Option<String>,
+    /// Spoken language, e.g. `en`; detected from the audio when unset
+    pub language: Option<String>,
+    /// Translate the speech into English instead of transcribing it
+    pub translate: Option<bool>,
+    /// Text that primes vocabulary and style, e.g. player and team names
+    pub initial_prompt: Option<String>,
+}
+
+impl TranscriptionOptions {
+    pub fn validate(&self) -> Result<(), ProcessorError> {
+        if let Some(model) = &self.model {
+            let valid = !model.is_empty()
+                && !model.starts_with('-')
+                && model.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'));
+            if !valid {
+                return Err(ProcessorError::BadRequest(format!("Invalid model {:?}", model)));
+            }
+        }
+        if let Some(language) = &self.language {
+            if language.is_empty() || language.len() > 20 || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(ProcessorError::BadRequest(format!(
+                    "Invalid language {:?}; expected a code like en or a name like English",
+                    language
+                )));
+            }
+        }
+        if self.initial_prompt.as_ref().is_some_and(|prompt| prompt.chars().count() > MAX_PROMPT_CHARS) {
+            return Err(ProcessorError::BadRequest(format!(
+                "initial_prompt is limited to {} characters",
+                MAX_PROMPT_CHARS
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Which transcription backend to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The whisper CLI on this machine
+    Local,
+    /// OpenAI's hosted transcription API
+    Api,
+}
+
+impl BackendKind {
+    /// TRANSCRIPTION_BACKEND: `local` (default) or `api`
+    pub fn from_env() -> Self {
+        match std::env::var("TRANSCRIPTION_BACKEND").as_deref().map(str::trim) {
+            Ok("api") => BackendKind::Api,
+            Ok("local") | Err(_) => BackendKind::Local,
+            Ok(other) => {
+                tracing::warn!("Unknown TRANSCRIPTION_BACKEND {:?}, using local whisper", other);
+                BackendKind::Local
+            }
+        }
+    }
+}
+
+impl TranscriptionBackend for WhisperClient {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    /// Runs whisper on a blocking thread so the runtime stays responsive
+    fn transcribe<'a>(&'a self, input: &'a Path) -> BoxFuture<'a, Result<TranscriptionResult, ProcessorError>> {
+        let client = self.clone();
+        let input = input.to_path_buf();
+        Box::pin(async move {
+            let span = tracing::Span::current();
+            tokio::task::spawn_blocking(move || span.in_scope(|| WhisperClient::transcribe(&client, &input)))
+                .await
+                .map_err(|e| ProcessorError::WhisperError(format!("transcription panicked: {}", e)))?
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::ProcessorError;
+use crate::jobs::{Job, JobStatus};
+use crate::openai::RetryPolicy;
+
+/// Header carrying `sha256=<hex HMAC>` of `<timestamp>.<body>`
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Header carrying the Unix time the delivery was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+
+/// Posts finished jobs to the `callback_url` they were submitted with.
+/// Deliveries are signed when a secret is configured, and retried like
+/// OpenAI requests: on timeouts, dropped connections, 429 and 5xx replies.
+#[derive(Clone)]
+pub struct WebhookSender {
+    client: reqwest::Client,
+    secret: Option<String>,
+    retry: RetryPolicy,
+}
+
+/// How delivering a job's callback went, reported on the job
+#[derive(Clone, Debug, Serialize)]
+pub struct WebhookDelivery {
+    pub url: String,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    /// `job.completed` or `job.failed`
+    event: &'static str,
+    job: &'a Job,
+}
+
+impl WebhookSender {
+    pub fn new(secret: Option<String>, retry: RetryPolicy) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            secret,
+            retry,
+        }
+    }
+
+    /// WEBHOOK_SECRET signs deliveries (unsigned when unset); each attempt
+    /// gets WEBHOOK_TIMEOUT_SECS (default 10), with up to WEBHOOK_RETRIES
+    /// retries (default 5)
+    pub fn from_env() -> Self {
+        let secret = std::env::var("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+        if secret.is_none() {
+            tracing::warn!("WEBHOOK_SECRET is not set; webhook deliveries will be unsigned");
+        }
+        let retry = RetryPolicy {
+            timeout: Duration::from_secs(
+                std::env::var("WEBHOOK_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(10),
+            ),
+            max_retries: std::env::var("WEBHOOK_RETRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(5),
+            base_delay: Duration::from_secs(1),
+        };
+        Self::new(secret, retry)
+    }
+
+    /// POST a finished job to `url`
+    pub async fn deliver(&self, url: &str, job: &Job) -> WebhookDelivery {
+        let outcome = self.send(url, job).await;
+        match &outcome {
+            Ok(()) => tracing::info!("Delivered job {} to {}", job.id, url),
+            Err(e) => tracing::warn!("Could not deliver job {} to {}: {}", job.id, url, e),
+        }
+        WebhookDelivery {
+            url: url.to_string(),
+            delivered: outcome.is_ok(),
+            error: outcome.err().map(|e| e.to_string()),
+        }
+    }
+
+    async fn send(&self, url: &str, job: &Job) -> Result<(), ProcessorError> {
+        let event = match job.status {
+            JobStatus::Failed => "job.failed",
+            _ => "job.completed",
+        };
+        let body = serde_json::to_string(&Payload { event, job })
+            .map_err(|e| ProcessorError::BadRequest(e.to_string()))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let signature = self.secret.as_deref().map(|secret| sign(secret, timestamp, &body));
+
+        self.retry
+            .send("Webhook delivery", || {
+                let mut request = self.client
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .body(body.clone());
+                if let Some(signature) = &signature {
+                    request = request.header(SIGNATURE_HEADER, signature);
+                }
+                Ok(request)
+            })
+            .await
+            .map(|_| ())
+    }
+}
+
+/// The signature header value for a delivery: `sha256=` and the hex
+/// HMAC-SHA256 of `<timestamp>.<body>` under the shared secret. Receivers
+/// should recompute it and reject stale timestamps.
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    let digest: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", digest)
+}
+
+/// Accept only absolute http(s) URLs as callbacks
+pub fn validate_callback_url(url: &str) -> Result<(), ProcessorError> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => Ok(()),
+        _ => Err(ProcessorError::BadRequest(format!(
+            "callback_url must be an absolute http(s) URL, got {:?}",
+            url
+        ))),
+    }
+}
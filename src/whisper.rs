@@ -1,102 +1,536 @@
-use std::process::Command;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
+use crate::config::config;
+use crate::error::ProcessorError;
+use crate::events::{self, Stage};
+use crate::ffmpeg::{FFmpegClient, OutputFormat as AudioFormat};
+use crate::library;
+use crate::process;
+use crate::store::Store;
+use crate::telemetry;
+use crate::transcription::TranscriptionOptions;
+use crate::workspace;
+
+#[derive(Clone)]
 pub struct WhisperClient {
     binary_path: String,
     model: String,
+    /// Spoken language; whisper detects it when unset
+    language: Option<String>,
+    translate: bool,
+    initial_prompt: Option<String>,
+    output_dir: Option<PathBuf>,
+    output_formats: Vec<OutputFormat>,
+    word_timestamps: bool,
+    store: Option<Arc<Store>>,
 }
 
 #[derive(Debug)]
 pub struct TranscriptionResult {
     pub segments: Vec<Segment>,
+    /// Spoken language whisper detected (or was told), e.g. `en`
+    pub language: Option<String>,
+    /// Files whisper wrote that were kept, registered in the library
+    pub artifacts: Vec<PathBuf>,
 }
 
-#[derive(Debug)]
+/// How a long recording is cut up for `WhisperClient::transcribe_chunked`
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkOptions {
+    /// Length of each piece in seconds
+    pub chunk_seconds: u64,
+    /// Most whisper processes running at once
+    pub concurrency: usize,
+}
+
+/// Transcript files whisper can write alongside its stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Txt,
+    Vtt,
+    Srt,
+    Tsv,
+    Json,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 5] = [
+        OutputFormat::Txt,
+        OutputFormat::Vtt,
+        OutputFormat::Srt,
+        OutputFormat::Tsv,
+        OutputFormat::Json,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.extension() == name)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Txt => "txt",
+            OutputFormat::Vtt => "vtt",
+            OutputFormat::Srt => "srt",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    /// Formats to keep from WHISPER_OUTPUT_FORMATS: `all` (default), `none`,
+    /// or a comma-separated list such as `srt,json`
+    pub fn from_env() -> Vec<Self> {
+        match std::env::var("WHISPER_OUTPUT_FORMATS") {
+            Err(_) => Self::ALL.to_vec(),
+            Ok(value) if value.trim() == "all" => Self::ALL.to_vec(),
+            Ok(value) if value.trim() == "none" => Vec::new(),
+            Ok(value) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .filter_map(|name| {
+                    let format = Self::from_name(name);
+                    if format.is_none() {
+                        tracing::warn!("Ignoring unknown whisper output format {:?}", name);
+                    }
+                    format
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Segment {
     pub start: f64,
     pub end: f64,
     pub text: String,
+    /// Model confidence in [0, 1], derived from whisper's average log-probability
+    pub confidence: Option<f64>,
+    /// Per-word timings; empty unless word timestamps were requested
+    pub words: Vec<Word>,
+    /// Who is talking, when the transcript was diarized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Word {
+    pub start: f64,
+    pub end: f64,
+    pub word: String,
+    /// Model confidence in [0, 1]
+    pub probability: Option<f64>,
+}
+
+/// The parts of whisper's JSON output file we use
+#[derive(Deserialize)]
+struct WhisperJson {
+    segments: Vec<WhisperJsonSegment>,
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WhisperJsonSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    avg_logprob: Option<f64>,
+    #[serde(default)]
+    words: Vec<WhisperJsonWord>,
+}
+
+#[derive(Deserialize)]
+struct WhisperJsonWord {
+    word: String,
+    start: f64,
+    end: f64,
+    probability: Option<f64>,
 }
 
 impl WhisperClient {
+    /// Uses the configured whisper binary and default model
     pub fn new() -> Self {
         Self {
-            binary_path: "whisper".to_string(),
-            model: "base".to_string(),
+            binary_path: config().whisper_path.clone(),
+            model: config().whisper_model.clone(),
+            language: None,
+            translate: false,
+            initial_prompt: None,
+            output_dir: None,
+            output_formats: OutputFormat::from_env(),
+            word_timestamps: false,
+            store: None,
         }
     }
 
-    pub fn transcribe(&self, input_path: &PathBuf) -> Result<TranscriptionResult, std::io::Error> {
-        println!("Attempting to transcribe file: {:?}", input_path);
+    /// Set the whisper model (tiny, base, small, medium, large, ...)
+    pub fn with_model(&mut self, model: &str) -> &mut Self {
+        self.model = model.to_string();
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Skip language detection, e.g. `en` for noisy commentary whisper
+    /// tends to misidentify
+    pub fn with_language(&mut self, language: &str) -> &mut Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// Translate the speech into English instead of transcribing it
+    pub fn with_translate(&mut self, enabled: bool) -> &mut Self {
+        self.translate = enabled;
+        self
+    }
+
+    /// Prime whisper with names and jargon it should expect
+    pub fn with_initial_prompt(&mut self, prompt: &str) -> &mut Self {
+        self.initial_prompt = Some(prompt.to_string());
+        self
+    }
+
+    /// Apply a request's overrides
+    pub fn with_options(&mut self, options: &TranscriptionOptions) -> &mut Self {
+        if let Some(model) = &options.model {
+            self.with_model(model);
+        }
+        if let Some(language) = &options.language {
+            self.with_language(language);
+        }
+        if let Some(translate) = options.translate {
+            self.with_translate(translate);
+        }
+        if let Some(prompt) = &options.initial_prompt {
+            self.with_initial_prompt(prompt);
+        }
+        self
+    }
+
+    /// Choose which of whisper's transcript files to keep
+    pub fn with_output_formats(&mut self, formats: &[OutputFormat]) -> &mut Self {
+        self.output_formats = formats.to_vec();
+        self
+    }
+
+    /// Ask whisper for per-word timings (slower)
+    pub fn with_word_timestamps(&mut self, enabled: bool) -> &mut Self {
+        self.word_timestamps = enabled;
+        self
+    }
+
+    /// Set the directory whisper writes its transcript files into
+    pub fn with_output_dir<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.output_dir = Some(path.into());
+        self
+    }
+
+    /// Reuse transcripts stored for the same media (by content hash) and
+    /// store new ones
+    pub fn with_store(&mut self, store: Arc<Store>) -> &mut Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn transcribe(&self, input_path: &Path) -> Result<TranscriptionResult, ProcessorError> {
+        let stage = Stage::start("transcribe");
+        stage.finish(self.run_stored_transcribe(input_path))
+    }
+
+    /// Transcribe a long recording in pieces: split it into
+    /// `chunk_seconds` WAV chunks, run up to `concurrency` whisper processes
+    /// over them at once, and join the results with timestamps shifted back
+    /// onto the full recording. Words straddling a cut may be misheard.
+    /// Whisper's per-chunk files are discarded, so the result has no
+    /// artifacts. `progress` is told the fraction of chunks done.
+    pub async fn transcribe_chunked(
+        &self,
+        input_path: &Path,
+        options: ChunkOptions,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<TranscriptionResult, ProcessorError> {
+        let stage = Stage::start("transcribe_chunked");
+        stage.finish(self.run_transcribe_chunked(input_path, options, progress).await)
+    }
+
+    async fn run_transcribe_chunked(
+        &self,
+        input_path: &Path,
+        options: ChunkOptions,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<TranscriptionResult, ProcessorError> {
+        if options.chunk_seconds == 0 || options.concurrency == 0 {
+            return Err(ProcessorError::BadRequest("Chunk length and concurrency must be at least 1".to_string()));
+        }
+
+        let stored = match &self.store {
+            Some(store) => {
+                let hash = store.media_hash(input_path)?;
+                if let Some(transcription) = store.transcript(&hash, &self.store_options())? {
+                    tracing::info!("Using stored transcript for {:?}", input_path);
+                    return Ok(transcription);
+                }
+                Some((store.clone(), hash))
+            }
+            None => None,
+        };
+
+        let output_dir = self.output_dir.as_ref()
+            .ok_or_else(|| ProcessorError::BadRequest("Output directory not set".to_string()))?;
+        // Pieces live in their own scratch directory, gone once this returns
+        let chunks_dir = workspace::scratch_dir(output_dir)?;
+        let transcription = self.transcribe_chunks(input_path, chunks_dir.path(), options, progress).await?;
+
+        if let Some((store, hash)) = stored {
+            let name = input_path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            store.save_transcript(&hash, name, &self.store_options(), &transcription)?;
+        }
+        Ok(transcription)
+    }
+
+    async fn transcribe_chunks(
+        &self,
+        input_path: &Path,
+        chunks_dir: &Path,
+        options: ChunkOptions,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<TranscriptionResult, ProcessorError> {
+        // Split, and measure each chunk so the offsets match what was cut
+        let input = input_path.to_path_buf();
+        let dir = chunks_dir.to_path_buf();
+        let span = tracing::Span::current();
+        let chunks: Vec<(PathBuf, f64)> = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let mut ffmpeg = FFmpegClient::new();
+            ffmpeg
+                .with_input(&input)
+                .with_output_dir(&dir)
+                .with_chunk_duration(options.chunk_seconds)
+                .with_output_format(AudioFormat::Wav);
+            ffmpeg.split_into_chunks()?
+                .into_iter()
+                .map(|chunk| {
+                    let duration = FFmpegClient::new().with_input(&chunk).duration_seconds()?;
+                    Ok((chunk, duration))
+                })
+                .collect::<Result<Vec<_>, ProcessorError>>()
+        })
+        .await
+        .map_err(|e| ProcessorError::WhisperError(format!("chunk split panicked: {}", e)))??;
+        tracing::info!("Transcribing {:?} in {} chunks", input_path, chunks.len());
+
+        let mut chunk_client = self.clone();
+        chunk_client.store = None;
+        chunk_client.output_dir = Some(chunks_dir.to_path_buf());
+        chunk_client.output_formats = vec![OutputFormat::Json];
+        let chunk_client = Arc::new(chunk_client);
+
+        let permits = Arc::new(Semaphore::new(options.concurrency));
+        let tasks: Vec<_> = chunks
+            .iter()
+            .map(|(chunk, _)| {
+                let permits = permits.clone();
+                let client = chunk_client.clone();
+                let chunk = chunk.clone();
+                let span = tracing::Span::current();
+                tokio::spawn(async move {
+                    let _permit = permits.acquire_owned().await.expect("whisper semaphore closed");
+                    tokio::task::spawn_blocking(move || span.in_scope(|| client.run_transcribe(&chunk)))
+                        .await
+                        .map_err(|e| ProcessorError::WhisperError(format!("chunk transcription panicked: {}", e)))?
+                })
+            })
+            .collect();
+
+        let mut segments = Vec::new();
+        let mut language = None;
+        let mut offset = 0.0;
+        let total = tasks.len();
+        for (done, (task, (_, duration))) in tasks.into_iter().zip(&chunks).enumerate() {
+            let part = task
+                .await
+                .map_err(|e| ProcessorError::WhisperError(format!("chunk transcription panicked: {}", e)))??;
+            language = language.or(part.language);
+            segments.extend(part.segments.into_iter().map(|segment| shift_segment(segment, offset)));
+            offset += duration;
+            progress((done + 1) as f64 / total as f64);
+        }
+
+        Ok(TranscriptionResult { segments, language, artifacts: Vec::new() })
+    }
+
+    /// The whisper settings that change a transcript, which stored
+    /// transcripts are keyed by alongside the media hash. Settings left at
+    /// their defaults are omitted so older keys still match.
+    fn store_options(&self) -> String {
+        let mut options = format!("model={},words={}", self.model, self.word_timestamps);
+        if let Some(language) = &self.language {
+            options.push_str(&format!(",language={}", language));
+        }
+        if self.translate {
+            options.push_str(",task=translate");
+        }
+        if let Some(prompt) = &self.initial_prompt {
+            options.push_str(&format!(",prompt={}", prompt_digest(prompt)));
+        }
+        options
+    }
+
+    fn run_stored_transcribe(&self, input_path: &Path) -> Result<TranscriptionResult, ProcessorError> {
+        let Some(store) = &self.store else {
+            return self.run_transcribe(input_path);
+        };
+
+        let hash = store.media_hash(input_path)?;
+        let options = self.store_options();
+        if let Some(stored) = store.transcript(&hash, &options)? {
+            tracing::info!("Using stored transcript for {:?} ({})", input_path, options);
+            return Ok(stored);
+        }
+
+        let transcription = self.run_transcribe(input_path)?;
+        let name = input_path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+        store.save_transcript(&hash, name, &options, &transcription)?;
+        Ok(transcription)
+    }
+
+    fn run_transcribe(&self, input_path: &Path) -> Result<TranscriptionResult, ProcessorError> {
+        tracing::debug!("Attempting to transcribe file: {:?}", input_path);
         
         let input_str = input_path.to_str()
-            .ok_or_else(|| std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Invalid path"
-            ))?;
-
-        // Create videos directory for output
-        let videos_dir = std::env::current_dir()?.join("src").join("videos");
-        std::fs::create_dir_all(&videos_dir)?;
+            .ok_or_else(|| ProcessorError::BadRequest("Invalid path".to_string()))?;
+
+        let output_dir = self.output_dir.as_ref()
+            .ok_or_else(|| ProcessorError::BadRequest("Output directory not set".to_string()))?;
+        // whisper names its files after the input, so each run writes into a
+        // scratch directory of its own and the results are moved over after
+        let scratch = workspace::scratch_dir(output_dir)?;
         
-        println!("Running whisper command with path: {}", input_str);
+        tracing::debug!("Running whisper command with path: {}", input_str);
         
-        let output = Command::new(&self.binary_path)
-            .arg(input_str)
-            .args(&["--model", &self.model])
-            .args(&["--output_dir", videos_dir.to_str().unwrap()])  // Add output directory
-            .output()?;
+        // The JSON file is always needed; it is what we parse. whisper writes one
+        // format or all of them, so anything else means "all" and pruning.
+        let output_format = if self.output_formats.iter().all(|f| *f == OutputFormat::Json) {
+            "json"
+        } else {
+            "all"
+        };
+        let mut args = vec![
+            input_str.to_string(),
+            "--model".to_string(), self.model.clone(),
+            "--output_dir".to_string(), scratch.path().to_str().unwrap().to_string(),
+            "--output_format".to_string(), output_format.to_string(),
+            "--word_timestamps".to_string(), if self.word_timestamps { "True" } else { "False" }.to_string(),
+            "--verbose".to_string(), "False".to_string(),
+        ];
+        if let Some(language) = &self.language {
+            args.extend(["--language".to_string(), language.clone()]);
+        }
+        if self.translate {
+            args.extend(["--task".to_string(), "translate".to_string()]);
+        }
+        if let Some(prompt) = &self.initial_prompt {
+            args.extend(["--initial_prompt".to_string(), prompt.clone()]);
+        }
+        let output = process::run(&self.binary_path, &args)
+            .map_err(|e| process::spawn_error(&self.binary_path, e, ProcessorError::WhisperError))?;
 
-        println!("Whisper command executed with status: {:?}", output.status);
+        tracing::debug!("Whisper command executed with status: {:?}", output.status);
         
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
-            println!("Whisper error: {}", error);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                error.to_string()
-            ));
-        }
-
-        // Print the stdout to see what we're getting
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        println!("Whisper output: {}", stdout);
-
-        let segments = self.parse_output(&stdout);
-        println!("Parsed segments: {:?}", segments);
-
-        Ok(TranscriptionResult { segments })
-    }
-
-    fn parse_output(&self, output: &str) -> Vec<Segment> {
-        output
-            .lines()
-            .filter_map(|line| {
-                if let Some(timing_and_text) = line.split_once(']') {
-                    if let Some(times) = timing_and_text.0.trim_start_matches('[').split_once("-->") {
-                        let start = parse_timestamp(times.0.trim());
-                        let end = parse_timestamp(times.1.trim());
-                        let text = timing_and_text.1.trim().to_string();
-                        
-                        Some(Segment { start, end, text })
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect()
+            tracing::error!("Whisper error: {}", error);
+            return Err(ProcessorError::WhisperError(error.to_string()));
+        }
+
+        let stem = input_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let json_path = scratch.path().join(format!("{}.json", stem));
+        let content = std::fs::read_to_string(&json_path)
+            .map_err(|e| ProcessorError::WhisperError(format!("no JSON output at {:?}: {}", json_path, e)))?;
+        let (segments, language) = parse_json_output(&content)?;
+        tracing::info!("Parsed {} segments (language: {:?})", segments.len(), language);
+
+        let kept = self.keep_artifacts(scratch.path(), input_path)?;
+        let artifacts = workspace::publish(&kept, output_dir, |_| false)?;
+        for artifact in &artifacts {
+            events::artifact(artifact, false);
+        }
+        library::register_transcript(output_dir, input_path, &artifacts)?;
+
+        let transcription = TranscriptionResult { segments, language, artifacts };
+        telemetry::metrics().transcribed("local", &transcription);
+        Ok(transcription)
+    }
+
+    /// Delete whisper's files in formats that weren't asked for and return
+    /// the ones that remain
+    fn keep_artifacts(&self, output_dir: &Path, input_path: &Path) -> Result<Vec<PathBuf>, ProcessorError> {
+        let stem = input_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+
+        let mut kept = Vec::new();
+        for format in OutputFormat::ALL {
+            let path = output_dir.join(format!("{}.{}", stem, format.extension()));
+            if !path.exists() {
+                continue;
+            }
+            if self.output_formats.contains(&format) {
+                kept.push(path);
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(kept)
     }
 }
 
-fn parse_timestamp(timestamp: &str) -> f64 {
-    let parts: Vec<&str> = timestamp.split(':').collect();
-    if parts.len() == 2 {
-        let minutes: f64 = parts[0].parse().unwrap_or(0.0);
-        let seconds: f64 = parts[1].parse().unwrap_or(0.0);
-        minutes * 60.0 + seconds
-    } else {
-        0.0
+/// Short, stable stand-in for a prompt in stored transcript keys
+pub fn prompt_digest(prompt: &str) -> String {
+    format!("{:x}", Sha256::digest(prompt.as_bytes()))[..16].to_string()
+}
+
+/// Move a chunk's segment (and its words) `offset` seconds later
+fn shift_segment(mut segment: Segment, offset: f64) -> Segment {
+    segment.start += offset;
+    segment.end += offset;
+    for word in &mut segment.words {
+        word.start += offset;
+        word.end += offset;
     }
-} 
\ No newline at end of file
+    segment
+}
+
+/// Parse the JSON file whisper writes into segments and the detected
+/// language. Segment text may span several lines; it is kept as one string.
+fn parse_json_output(content: &str) -> Result<(Vec<Segment>, Option<String>), ProcessorError> {
+    let json: WhisperJson = serde_json::from_str(content)
+        .map_err(|e| ProcessorError::WhisperError(format!("invalid JSON output: {}", e)))?;
+
+    let segments = json.segments
+        .into_iter()
+        .map(|segment| Segment {
+            start: segment.start,
+            end: segment.end,
+            text: segment.text.trim().to_string(),
+            confidence: segment.avg_logprob.map(|logprob| logprob.exp().clamp(0.0, 1.0)),
+            words: segment.words
+                .into_iter()
+                .map(|word| Word {
+                    start: word.start,
+                    end: word.end,
+                    word: word.word.trim().to_string(),
+                    probability: word.probability,
+                })
+                .collect(),
+            speaker: None,
+        })
+        .collect();
+
+    Ok((segments, json.language))
+}
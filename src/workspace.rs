@@ -0,0 +1,396 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+use crate::config::Config;
+use crate::error::ProcessorError;
+use crate::media::MediaRecord;
+
+/// Current version of the on-disk layout. Bump it and add a step to
+/// `Workspace::migrate` whenever the layout changes.
+pub const LAYOUT_VERSION: u32 = 1;
+
+/// Files in the legacy `src/` directory with these extensions are media
+const LEGACY_MEDIA_EXTENSIONS: &[&str] = &[
+    "mp3", "wav", "m4a", "aac", "flac", "mov", "mp4", "mkv", "webm", "jpg", "jpeg", "png",
+];
+
+/// Name prefix of per-request scratch directories
+const SCRATCH_PREFIX: &str = ".scratch-";
+
+/// Held while files are swapped into place, so two requests publishing to
+/// the same directory never interleave
+static PUBLISH: Mutex<()> = Mutex::new(());
+
+/// Managed data directory (`data_dir` in the config, default `./data`):
+///
+/// ```text
+/// data/
+///   layout.json    layout version
+///   media/         source media and uploads
+///   transcripts/   whisper output
+///   outputs/       generated files (chunks, exports, snippets)
+///   cache/         content-addressed artifact cache
+///   db/            database files
+/// ```
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    root: PathBuf,
+    /// Source media, when kept outside the data directory
+    media: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Layout {
+    version: u32,
+}
+
+impl Workspace {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into(), media: None }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut workspace = Self::new(&config.data_dir);
+        workspace.media = config.media_dir.clone();
+        workspace
+    }
+
+    /// Create the layout if needed and migrate older layouts up to the current version
+    pub fn open(self) -> Result<Self, std::io::Error> {
+        std::fs::create_dir_all(&self.root)?;
+
+        let version = self.layout_version();
+        if version > LAYOUT_VERSION {
+            return Err(std::io::Error::other(format!(
+                "Data directory {:?} has layout version {}, newer than supported version {}",
+                self.root, version, LAYOUT_VERSION
+            )));
+        }
+
+        for dir in [
+            self.media_dir(),
+            self.transcripts_dir(),
+            self.outputs_dir(),
+            self.cache_dir(),
+            self.db_dir(),
+        ] {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Left behind by requests that were running when the server stopped
+        for dir in [self.transcripts_dir(), self.outputs_dir()] {
+            sweep_scratch(&dir, 3);
+        }
+        sweep_scratch(&self.media_dir(), 1);
+
+        if version < LAYOUT_VERSION {
+            self.migrate(version)?;
+            std::fs::write(
+                self.root.join("layout.json"),
+                serde_json::to_string_pretty(&Layout { version: LAYOUT_VERSION })?,
+            )?;
+            tracing::info!("Workspace {:?} migrated to layout version {}", self.root, LAYOUT_VERSION);
+        }
+
+        Ok(self)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn media_dir(&self) -> PathBuf {
+        self.media.clone().unwrap_or_else(|| self.root.join("media"))
+    }
+
+    pub fn transcripts_dir(&self) -> PathBuf {
+        self.root.join("transcripts")
+    }
+
+    pub fn outputs_dir(&self) -> PathBuf {
+        self.root.join("outputs")
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("cache")
+    }
+
+    pub fn db_dir(&self) -> PathBuf {
+        self.root.join("db")
+    }
+
+    pub fn chunks_dir(&self) -> PathBuf {
+        self.outputs_dir().join("chunks")
+    }
+
+    /// Generated exports: captions, renders, publish kits
+    pub fn exports_dir(&self) -> PathBuf {
+        self.outputs_dir().join("exports")
+    }
+
+    /// Audio tracks extracted from uploaded media
+    pub fn audio_dir(&self) -> PathBuf {
+        self.outputs_dir().join("audio")
+    }
+
+    /// HLS previews, one directory of playlist and segments each
+    pub fn hls_dir(&self) -> PathBuf {
+        self.outputs_dir().join("hls")
+    }
+
+    /// Lazily generated per-segment audio snippets
+    pub fn snippets_dir(&self) -> PathBuf {
+        self.outputs_dir().join("snippets")
+    }
+
+    /// Ambient beds (rain, ocean, ...) available to the meditation renderer
+    pub fn ambient_dir(&self) -> PathBuf {
+        self.media_dir().join("ambient")
+    }
+
+    /// Find an ambient bed by name, whatever its audio extension
+    pub fn find_ambient(&self, name: &str) -> Option<PathBuf> {
+        std::fs::read_dir(self.ambient_dir())
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| path.file_stem().and_then(|s| s.to_str()) == Some(name))
+    }
+
+    /// Records of uploaded media, one JSON file per media ID
+    pub fn media_records_dir(&self) -> PathBuf {
+        self.db_dir().join("media")
+    }
+
+    pub fn media_record(&self, id: &str) -> Option<MediaRecord> {
+        let path = self.media_records_dir().join(format!("{}.json", safe_name(id)?));
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save_media_record(&self, record: &MediaRecord) -> Result<(), std::io::Error> {
+        std::fs::create_dir_all(self.media_records_dir())?;
+        std::fs::write(
+            self.media_records_dir().join(format!("{}.json", record.id)),
+            serde_json::to_string_pretty(record)?,
+        )
+    }
+
+    /// Every upload record
+    pub fn media_records(&self) -> Vec<MediaRecord> {
+        match std::fs::read_dir(self.media_records_dir()) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+                .filter_map(|content| serde_json::from_str(&content).ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Remove an upload record; a missing one is not an error
+    pub fn delete_media_record(&self, id: &str) -> Result<(), std::io::Error> {
+        let Some(id) = safe_name(id) else {
+            return Ok(());
+        };
+        match std::fs::remove_file(self.media_records_dir().join(format!("{}.json", id))) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Path of an existing media file by media ID. Files placed in `media/`
+    /// by hand have no record; their ID is their file name.
+    pub fn media_path(&self, id: &str) -> Option<PathBuf> {
+        let path = match self.media_record(id) {
+            Some(record) => self.media_dir().join(record.file),
+            None => self.media_dir().join(safe_name(id)?),
+        };
+        path.is_file().then_some(path)
+    }
+
+    /// Like `media_path`, but a missing file is a 404
+    pub fn require_media(&self, id: &str) -> Result<PathBuf, ProcessorError> {
+        self.media_path(id).ok_or_else(|| ProcessorError::media_not_found(id))
+    }
+
+    /// Resolve a path relative to the workspace root
+    pub fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        safe_relative(relative).map(|relative| self.root.join(relative))
+    }
+
+    /// Path of a workspace file relative to the root, for API responses
+    pub fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn layout_version(&self) -> u32 {
+        std::fs::read_to_string(self.root.join("layout.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Layout>(&content).ok())
+            .map(|layout| layout.version)
+            .unwrap_or(0)
+    }
+
+    fn migrate(&self, from: u32) -> Result<(), std::io::Error> {
+        if from < 1 {
+            self.migrate_legacy_src(Path::new("src"))?;
+        }
+        Ok(())
+    }
+
+    /// Version 0 -> 1: move the ad-hoc files under `src/` into the workspace
+    fn migrate_legacy_src(&self, legacy: &Path) -> Result<(), std::io::Error> {
+        let moves = [
+            ("chunks", self.chunks_dir()),
+            ("videos", self.transcripts_dir()),
+            ("uploads", self.media_dir()),
+            ("ambient", self.ambient_dir()),
+            ("exports", self.exports_dir()),
+            ("snippets", self.snippets_dir()),
+            ("cache", self.cache_dir()),
+        ];
+        for (name, target) in moves {
+            let source = legacy.join(name);
+            if source.is_dir() {
+                tracing::info!("Migrating {:?} -> {:?}", source, target);
+                move_dir_contents(&source, &target)?;
+                let _ = std::fs::remove_dir(&source);
+            }
+        }
+
+        let entries = match std::fs::read_dir(legacy) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            let is_media = path.is_file() && path
+                .extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| LEGACY_MEDIA_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+            if !is_media {
+                continue;
+            }
+
+            // merged.mp3 was always a merge output, not a source
+            let target_dir = if path.file_name().and_then(|s| s.to_str()) == Some("merged.mp3") {
+                self.outputs_dir()
+            } else {
+                self.media_dir()
+            };
+            tracing::info!("Migrating {:?} -> {:?}", path, target_dir);
+            move_file(&path, &target_dir.join(path.file_name().unwrap()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Accept only a single plain path component
+fn safe_name(name: &str) -> Option<&str> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Some(name),
+        _ => None,
+    }
+}
+
+/// Accept only plain relative paths (no absolute paths or `..`)
+fn safe_relative(relative: &str) -> Option<&Path> {
+    let relative = Path::new(relative);
+    let safe = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+
+    if !safe || relative.as_os_str().is_empty() {
+        return None;
+    }
+
+    Some(relative)
+}
+
+/// A private working directory inside `parent` for one request. It is
+/// deleted with everything in it when dropped, whether the request
+/// succeeded or not. Being hidden, it never shows up in listings.
+pub fn scratch_dir(parent: &Path) -> Result<TempDir, std::io::Error> {
+    std::fs::create_dir_all(parent)?;
+    tempfile::Builder::new().prefix(SCRATCH_PREFIX).tempdir_in(parent)
+}
+
+/// Move finished `files` (from a scratch directory) into `target`. Files in
+/// `target` that `stale` matches are deleted first, as are same-named ones.
+/// Returns where the files ended up.
+pub fn publish(files: &[PathBuf], target: &Path, stale: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>, std::io::Error> {
+    std::fs::create_dir_all(target)?;
+    let _guard = PUBLISH.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    for entry in std::fs::read_dir(target)?.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_file() && stale(&entry.file_name().to_string_lossy()) {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let mut published = Vec::new();
+    for file in files {
+        let destination = target.join(file.file_name().unwrap_or_default());
+        if std::fs::rename(file, &destination).is_err() {
+            std::fs::copy(file, &destination)?;
+        }
+        published.push(destination);
+    }
+    Ok(published)
+}
+
+/// Remove scratch directories up to `depth` levels below `dir`
+fn sweep_scratch(dir: &Path, depth: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()).filter(|path| path.is_dir()) {
+        let scratch = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .is_some_and(|name| name.starts_with(SCRATCH_PREFIX));
+        if scratch {
+            tracing::info!("Removing leftover scratch directory {:?}", path);
+            let _ = std::fs::remove_dir_all(&path);
+        } else if depth > 1 {
+            sweep_scratch(&path, depth - 1);
+        }
+    }
+}
+
+fn move_dir_contents(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    std::fs::create_dir_all(target)?;
+    for entry in std::fs::read_dir(source)?.filter_map(Result::ok) {
+        let path = entry.path();
+        let destination = target.join(entry.file_name());
+        if path.is_dir() {
+            move_dir_contents(&path, &destination)?;
+            let _ = std::fs::remove_dir(&path);
+        } else {
+            move_file(&path, &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rename, falling back to copy + delete when crossing filesystems.
+/// Existing files at the destination are left alone.
+fn move_file(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    if target.exists() {
+        tracing::warn!("Skipping {:?}: {:?} already exists", source, target);
+        return Ok(());
+    }
+    if std::fs::rename(source, target).is_err() {
+        std::fs::copy(source, target)?;
+        std::fs::remove_file(source)?;
+    }
+    Ok(())
+}